@@ -1,93 +1,2371 @@
-use crate::types::expr::{Expr, Prim};
+#[cfg(test)]
+use crate::parser::parse_constructors::{bool, int, mk_if, mk_let, var};
+#[cfg(test)]
+use crate::typecheck::elaborate::elaborate_expr;
+use crate::types::builtin::{builtin_type, lookup_builtin, Builtin, BUILTINS};
+use crate::types::expr::{get_expr_annotation, pattern_variables, Expr, Op, Pattern, Prim};
+use crate::types::module::Module as SmolModule;
+use crate::types::ty::Type;
+#[cfg(test)]
 use nom::Finish;
 use wasm_encoder::{
-    CodeSection, ExportKind, ExportSection, Function, FunctionSection, Instruction, Module,
-    TypeSection, ValType,
+    BlockType, CodeSection, ConstExpr, DataSection, ElementSection, Elements, ExportKind,
+    ExportSection, Function, FunctionSection, GlobalSection, GlobalType, Instruction, MemArg,
+    MemorySection, MemoryType, Module, RefType, TableSection, TableType, TypeSection, ValType,
 };
 
-pub fn expr_to_wasm<Ann>(expr: Expr<Ann>) -> Vec<u8> {
-    let mut module = Module::new();
+use super::free_variables::free_variables;
 
-    // Encode the type section.
-    let mut types = TypeSection::new();
-    let params = vec![];
-    let results = vec![ValType::I32];
-    types.function(params, results);
-    module.section(&types);
+// function 0 is `alloc`, which takes a size in bytes and returns a pointer
+// to that many fresh bytes of linear memory
+const ALLOC_FUNCTION_INDEX: u32 = 0;
+
+// function 1 is `concat`, which takes two strings and returns a new string
+// holding both of them
+const CONCAT_FUNCTION_INDEX: u32 = 1;
+
+// function 2 is `string_equals`, which compares two strings byte by byte
+const STRING_EQUALS_FUNCTION_INDEX: u32 = 2;
+
+// function 3 is `record_field`, which takes a record and a field id and
+// returns the address of that field's value
+const RECORD_FIELD_FUNCTION_INDEX: u32 = 3;
+
+// global 0 points to the next free byte of linear memory
+const HEAP_POINTER_GLOBAL: u32 = 0;
+
+// every value we have so far is an `i32`, including functions, so every slot
+// in a closure is 4 bytes
+const SLOT_SIZE: u32 = 4;
+
+const PAGE_SIZE_LOG2: i32 = 16;
+
+// a string is a pointer to its length in bytes, followed by the bytes
+//
+// | length | byte 0 | byte 1 | ...
+//
+// string literals live in a data segment at the start of linear memory, and
+// the heap starts after them
+const STRING_LENGTH: MemArg = MemArg {
+    offset: 0,
+    align: 2,
+    memory_index: 0,
+};
+const STRING_HEADER_SIZE: u32 = 4;
+
+// a value made with a constructor is a pointer to its tag, which is the
+// constructor's position in its `data` declaration, followed by its fields
+//
+// | tag | field 0 | field 1 | ...
+//
+// every field is an `i32`, so these use the same slots as closures
+const CONSTRUCTOR_TAG_SLOT: u32 = 0;
+
+// a record is a pointer to how many fields it has, followed by an id for the
+// name of each field and its value
+//
+// | field count | id 0 | value 0 | id 1 | value 1 | ...
+//
+// we look fields up by id rather than by position, so a record with extra
+// fields can be used anywhere one with fewer fields is expected
+const RECORD_FIELD_COUNT_SLOT: u32 = 0;
+
+// a tuple is a pointer to its items, one per slot
+//
+// | item 0 | item 1 | ...
+//
+// but an exported function returning a tuple of at most this many ints and
+// bools returns each item as a separate wasm result instead, so the host
+// doesn't need to read them out of memory. Tuples holding strings, other
+// tuples or anything else on the heap are returned as a pointer, as the host
+// would need to know their layout to decode them
+const MAX_TUPLE_RESULTS: usize = 4;
+
+// whether a tuple of `items` can be returned to the host as separate results
+fn is_flat_tuple<Ann>(items: &[Type<Ann>]) -> bool {
+    items.len() <= MAX_TUPLE_RESULTS
+        && items
+            .iter()
+            .all(|item| matches!(item, Type::TInt { .. } | Type::TBool { .. }))
+}
+
+// lifted functions take the closure pointer then the argument
+const CLOSURE_PARAM: u32 = 0;
+const ARGUMENT_PARAM: u32 = 1;
+
+// wasm features we may use beyond the ones every engine we run on supports
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CompileOptions {
+    // whether calls in tail position can use `return_call_indirect` from the
+    // tail call proposal, so they don't grow the stack. Only turn this on if
+    // the engine running the module supports it
+    //
+    // without it, a function calling itself in tail position still runs in
+    // constant stack, as that's compiled to a loop, but other calls in tail
+    // position, such as between mutually recursive definitions, don't
+    pub tail_calls: bool,
+}
+
+impl CompileOptions {
+    // every feature `engine` can run
+    //
+    // the version of wasmtime we use never enables the tail call proposal,
+    // whatever its `Config` says, so for now `tail_calls` is always off here
+    // and modules that use it can only be run by other engines
+    pub fn for_engine(engine: &wasmtime::Engine) -> Self {
+        CompileOptions {
+            tail_calls: supports_tail_calls(engine),
+        }
+    }
+}
 
-    // Encode the function section.
+// whether `engine` accepts the smallest module that makes a tail call
+fn supports_tail_calls(engine: &wasmtime::Engine) -> bool {
+    let mut types = TypeSection::new();
+    types.function(vec![], vec![]);
     let mut functions = FunctionSection::new();
-    let type_index = 0;
-    functions.function(type_index);
-    module.section(&functions);
+    functions.function(0);
+    let mut body = Function::new(vec![]);
+    body.instruction(&Instruction::ReturnCall(0));
+    body.instruction(&Instruction::End);
+    let mut code = CodeSection::new();
+    code.function(&body);
+
+    let mut module = Module::new();
+    module.section(&types).section(&functions).section(&code);
+
+    wasmtime::Module::validate(engine, &module.finish()).is_ok()
+}
+
+// we compile the output of `elaborate_expr`, so we know the type of every node
+//
+// lambdas are compiled using closure conversion: each lambda is lifted into
+// its own wasm function that takes a pointer to its environment and its
+// argument. A function value is a pointer to a closure in linear memory, laid
+// out as its table index followed by each variable it captured:
+//
+// | table index | captured 0 | captured 1 | ...
+//
+// and we call it by looking up the table index and using `call_indirect`
+pub fn expr_to_wasm<Ann>(expr: Expr<Type<Ann>>) -> Vec<u8>
+where
+    Ann: Clone + Copy,
+{
+    expr_to_wasm_with_options(expr, CompileOptions::default())
+}
+
+pub fn expr_to_wasm_with_options<Ann>(expr: Expr<Type<Ann>>, options: CompileOptions) -> Vec<u8>
+where
+    Ann: Clone + Copy,
+{
+    let mut module = ModuleBuilder::new(options);
+
+    let main_type = get_expr_annotation(expr.clone());
+    let main_index = module.reserve_function();
+
+    // nothing else can refer to `main`, so a small tuple can be returned
+    // straight from the stack without putting it on the heap first
+    match expr {
+        Expr::ETuple { items, .. }
+            if is_flat_tuple(
+                &items
+                    .iter()
+                    .map(|item| get_expr_annotation(item.clone()))
+                    .collect::<Vec<_>>(),
+            ) =>
+        {
+            define_tuple_function(&mut module, main_index, items);
+            module.export("main".to_string(), main_index);
+        }
+        expr => {
+            define_value_function(&mut module, main_index, expr, None);
+            let export_index = export_function(&mut module, main_index, main_type);
+            module.export("main".to_string(), export_index);
+        }
+    }
+
+    module.finish()
+}
+
+// compile the output of `elaborate_module`, exporting every definition by name
+//
+// each definition becomes a function that takes no arguments and returns its
+// value, and referring to a definition calls that function, so definitions
+// can refer to each other in any order
+pub fn module_to_wasm<Ann>(smol_module: SmolModule<Type<Ann>>) -> Vec<u8>
+where
+    Ann: Clone + Copy,
+{
+    module_to_wasm_with_options(smol_module, CompileOptions::default())
+}
+
+pub fn module_to_wasm_with_options<Ann>(
+    smol_module: SmolModule<Type<Ann>>,
+    options: CompileOptions,
+) -> Vec<u8>
+where
+    Ann: Clone + Copy,
+{
+    let mut module = ModuleBuilder::new(options);
+
+    for data in &smol_module.datas {
+        for (tag, constructor) in data.constructors.iter().enumerate() {
+            module.define_constructor(constructor.name.clone(), tag as u32);
+        }
+    }
 
-    // Encode the export section.
-    let mut exports = ExportSection::new();
-    // export function 0 as 'main'
-    exports.export("main", ExportKind::Func, 0);
-    module.section(&exports);
+    // reserve every definition before compiling any of them, so they can call
+    // each other
+    let def_indexes: Vec<u32> = smol_module
+        .defs
+        .iter()
+        .map(|def| {
+            let index = module.reserve_function();
+            module.define_global(def.identifier.clone(), index);
+            index
+        })
+        .collect();
+
+    for (def, &index) in smol_module.defs.iter().zip(&def_indexes) {
+        let recursive = Recursive {
+            name: def.identifier.clone(),
+            top_level: true,
+            outer_params: vec![],
+        };
+        define_value_function(&mut module, index, def.expr.clone(), Some(recursive));
+    }
 
-    // Encode the code section.
-    let mut codes = CodeSection::new();
-    let f = expr_to_function(expr);
+    for (def, index) in smol_module.defs.into_iter().zip(def_indexes) {
+        let export_index = export_function(&mut module, index, def.ann);
+        module.export(def.identifier, export_index);
+    }
+
+    module.finish()
+}
 
-    codes.function(&f);
-    module.section(&codes);
+// compile `expr` as the body of a function that takes no arguments and
+// returns its value
+// a top level definition is `recursive`, so if it's a function, calls to
+// itself in its body can be loops too
+fn define_value_function<Ann>(
+    module: &mut ModuleBuilder,
+    function_index: u32,
+    expr: Expr<Type<Ann>>,
+    recursive: Option<Recursive>,
+) where
+    Ann: Clone + Copy,
+{
+    let result_type = type_to_val_type(get_expr_annotation(expr.clone()));
+    let mut builder = FunctionBuilder::new(0);
 
-    // Extract the encoded Wasm bytes for this module.
-    let wasm_bytes = module.finish();
+    match expr {
+        Expr::ELambda { .. } if recursive.is_some() => {
+            lambda_to_instructions(module, &mut builder, expr, recursive)
+        }
+        expr => tail_expr_to_instructions(module, &mut builder, expr),
+    }
 
-    wasm_bytes
+    let type_index = module.function_type(vec![], vec![result_type]);
+    module.define_function(function_index, type_index, builder.finish());
 }
 
-pub fn expr_to_function<Ann>(expr: Expr<Ann>) -> wasm_encoder::Function {
-    let locals = vec![];
-    let mut f = Function::new(locals);
+// compile each of `items` as the body of a function that takes no arguments
+// and returns them all
+fn define_tuple_function<Ann>(
+    module: &mut ModuleBuilder,
+    function_index: u32,
+    items: Vec<Expr<Type<Ann>>>,
+) where
+    Ann: Clone + Copy,
+{
+    let results = items
+        .iter()
+        .map(|item| type_to_val_type(get_expr_annotation(item.clone())))
+        .collect();
+    let mut builder = FunctionBuilder::new(0);
+
+    for item in items {
+        expr_to_instructions(module, &mut builder, item);
+    }
+
+    let type_index = module.function_type(vec![], results);
+    module.define_function(function_index, type_index, builder.finish());
+}
 
-    expr_to_instructions(&mut f, expr);
+// the host can't do much with a pointer to a closure, so for a definition of
+// type `a -> b -> c` we export a function that takes an `a` and a `b`, gets
+// the closure and applies it to each argument in turn
+//
+// strings are returned as a pointer to their bytes and their length, and
+// small flat tuples as each of their items, so the host doesn't need to know
+// how we lay them out
+fn export_function<Ann>(module: &mut ModuleBuilder, def_index: u32, def_type: Type<Ann>) -> u32
+where
+    Ann: Clone + Copy,
+{
+    let mut closure_types = vec![];
+    let mut result_type = def_type;
+    while let Type::TFunction { result, .. } = result_type.clone() {
+        closure_types.push(result_type);
+        result_type = *result;
+    }
+
+    let returns_small_tuple =
+        matches!(&result_type, Type::TTuple { items, .. } if is_flat_tuple(items));
+    if closure_types.is_empty()
+        && !returns_small_tuple
+        && !matches!(result_type, Type::TString { .. })
+    {
+        return def_index;
+    }
+
+    let params: Vec<ValType> = closure_types
+        .iter()
+        .map(|ty| match ty {
+            Type::TFunction { argument, .. } => type_to_val_type(*argument.clone()),
+            _ => unreachable!("expected a function type"),
+        })
+        .collect();
+
+    let mut f = FunctionBuilder::new(params.len() as u32);
+    let value = f.fresh_local(ValType::I32);
+    f.instruction(Instruction::Call(def_index));
+
+    for (param, closure_type) in closure_types.into_iter().enumerate() {
+        f.instruction(Instruction::LocalSet(value));
+        f.instruction(Instruction::LocalGet(value));
+        f.instruction(Instruction::LocalGet(param as u32));
+        call_closure(module, &mut f, value, closure_type, false);
+    }
+
+    let results = match result_type {
+        Type::TString { .. } => {
+            f.instruction(Instruction::LocalSet(value));
+            f.instruction(Instruction::LocalGet(value));
+            f.instruction(Instruction::I32Const(STRING_HEADER_SIZE as i32));
+            f.instruction(Instruction::I32Add);
+            f.instruction(Instruction::LocalGet(value));
+            f.instruction(Instruction::I32Load(STRING_LENGTH));
+            vec![ValType::I32, ValType::I32]
+        }
+        Type::TTuple { items, .. } if returns_small_tuple => {
+            f.instruction(Instruction::LocalSet(value));
+            items
+                .into_iter()
+                .enumerate()
+                .map(|(slot, item)| {
+                    f.instruction(Instruction::LocalGet(value));
+                    f.instruction(Instruction::I32Load(closure_slot(slot as u32)));
+                    type_to_val_type(item)
+                })
+                .collect()
+        }
+        other => vec![type_to_val_type(other)],
+    };
+
+    let index = module.reserve_function();
+    let type_index = module.function_type(params, results);
+    module.define_function(index, type_index, f.finish());
+    index
+}
+
+// state for the module we're currently compiling
+struct ModuleBuilder {
+    // params and results of each function type, indexed by type index
+    types: Vec<(Vec<ValType>, Vec<ValType>)>,
+    // type index and body of each function, indexed by function index
+    // this is `None` while we're still compiling a function's body
+    functions: Vec<Option<(u32, Function)>>,
+    // function index of each lambda, indexed by table index
+    table: Vec<u32>,
+    // top level definitions and the function that computes each one
+    globals: Vec<(String, u32)>,
+    // functions the host can call, by name
+    exports: Vec<(String, u32)>,
+    // table index of the lifted function for each builtin
+    builtins: Vec<(Builtin, u32)>,
+    // the tag of every constructor
+    constructors: Vec<(String, u32)>,
+    // every record field name we've seen, indexed by id
+    fields: Vec<String>,
+    // initial contents of linear memory, starting at address 0
+    data: Vec<u8>,
+    options: CompileOptions,
+}
+
+impl ModuleBuilder {
+    fn new(options: CompileOptions) -> Self {
+        let mut module = ModuleBuilder {
+            types: vec![],
+            functions: vec![],
+            table: vec![],
+            globals: vec![],
+            exports: vec![],
+            builtins: vec![],
+            constructors: vec![],
+            fields: vec![],
+            data: vec![],
+            options,
+        };
+
+        let alloc_index = module.reserve_function();
+        debug_assert_eq!(alloc_index, ALLOC_FUNCTION_INDEX);
+        let alloc_type = module.function_type(vec![ValType::I32], vec![ValType::I32]);
+        module.define_function(alloc_index, alloc_type, alloc_function());
+
+        let concat_index = module.reserve_function();
+        debug_assert_eq!(concat_index, CONCAT_FUNCTION_INDEX);
+        let i32_pair_type =
+            module.function_type(vec![ValType::I32, ValType::I32], vec![ValType::I32]);
+        module.define_function(concat_index, i32_pair_type, concat_function());
+
+        let equals_index = module.reserve_function();
+        debug_assert_eq!(equals_index, STRING_EQUALS_FUNCTION_INDEX);
+        module.define_function(equals_index, i32_pair_type, string_equals_function());
+
+        let record_field_index = module.reserve_function();
+        debug_assert_eq!(record_field_index, RECORD_FIELD_FUNCTION_INDEX);
+        module.define_function(record_field_index, i32_pair_type, record_field_function());
+
+        // builtins are lifted functions like any other, so they can be put
+        // in closures and passed around
+        for builtin in BUILTINS {
+            let function_index = module.reserve_function();
+            let type_index = function_type_index(&mut module, builtin_type(builtin, ()));
+            module.define_function(function_index, type_index, builtin_function(builtin));
+            let table_index = module.add_to_table(function_index);
+            module.builtins.push((builtin, table_index));
+        }
+
+        module
+    }
+
+    // find or create a function type
+    fn function_type(&mut self, params: Vec<ValType>, results: Vec<ValType>) -> u32 {
+        let signature = (params, results);
+        match self.types.iter().position(|ty| *ty == signature) {
+            Some(index) => index as u32,
+            None => {
+                self.types.push(signature);
+                (self.types.len() - 1) as u32
+            }
+        }
+    }
+
+    // get an index for a function before we've compiled it, so lambdas
+    // inside it can be given indexes of their own
+    fn reserve_function(&mut self) -> u32 {
+        self.functions.push(None);
+        (self.functions.len() - 1) as u32
+    }
+
+    fn define_function(&mut self, function_index: u32, type_index: u32, f: Function) {
+        self.functions[function_index as usize] = Some((type_index, f));
+    }
+
+    // put a function in the table so we can `call_indirect` it
+    fn add_to_table(&mut self, function_index: u32) -> u32 {
+        self.table.push(function_index);
+        (self.table.len() - 1) as u32
+    }
+
+    fn define_global(&mut self, identifier: String, function_index: u32) {
+        self.globals.push((identifier, function_index));
+    }
+
+    fn lookup_global(&self, identifier: &str) -> Option<u32> {
+        self.globals
+            .iter()
+            .find(|(name, _)| name == identifier)
+            .map(|(_, index)| *index)
+    }
+
+    fn define_constructor(&mut self, constructor: String, tag: u32) {
+        self.constructors.push((constructor, tag));
+    }
+
+    fn lookup_constructor_tag(&self, constructor: &str) -> u32 {
+        self.constructors
+            .iter()
+            .find(|(name, _)| name == constructor)
+            .map(|(_, tag)| *tag)
+            .unwrap_or_else(|| {
+                panic!(
+                    "constructor `{}` is not defined, did the program typecheck?",
+                    constructor
+                )
+            })
+    }
+
+    // the id we use for a record field called `name`
+    fn field_id(&mut self, name: &str) -> u32 {
+        match self.fields.iter().position(|field| field == name) {
+            Some(id) => id as u32,
+            None => {
+                self.fields.push(name.to_string());
+                (self.fields.len() - 1) as u32
+            }
+        }
+    }
+
+    // put a string literal in the data segment, returning its address
+    fn string_literal(&mut self, string: &str) -> u32 {
+        let address = self.data.len() as u32;
+        self.data
+            .extend_from_slice(&(string.len() as u32).to_le_bytes());
+        self.data.extend_from_slice(string.as_bytes());
+
+        // keep everything 4 byte aligned
+        while self.data.len() % SLOT_SIZE as usize != 0 {
+            self.data.push(0);
+        }
+
+        address
+    }
+
+    fn export(&mut self, name: String, function_index: u32) {
+        self.exports.push((name, function_index));
+    }
+
+    fn finish(self) -> Vec<u8> {
+        let mut module = Module::new();
+
+        // Encode the type section.
+        let mut types = TypeSection::new();
+        for (params, results) in self.types {
+            types.function(params, results);
+        }
+        module.section(&types);
+
+        // Encode the function section.
+        let mut functions = FunctionSection::new();
+        let mut codes = CodeSection::new();
+        for function in self.functions {
+            let (type_index, f) = function.expect("function was reserved but never defined");
+            functions.function(type_index);
+            codes.function(&f);
+        }
+        module.section(&functions);
 
+        // Encode the table section, this holds every lambda
+        let mut tables = TableSection::new();
+        let table_size = self.table.len() as u32;
+        tables.table(TableType {
+            element_type: RefType::FUNCREF,
+            minimum: table_size,
+            maximum: Some(table_size),
+        });
+        module.section(&tables);
+
+        // Encode the memory section, string literals and then the heap live
+        // here
+        let heap_start = self.data.len() as u32;
+        let page_size = 1 << PAGE_SIZE_LOG2;
+        let mut memories = MemorySection::new();
+        memories.memory(MemoryType {
+            minimum: u64::from(std::cmp::max(1, (heap_start + page_size - 1) / page_size)),
+            maximum: None,
+            memory64: false,
+            shared: false,
+        });
+        module.section(&memories);
+
+        // Encode the global section, this is just the heap pointer
+        let mut globals = GlobalSection::new();
+        globals.global(
+            GlobalType {
+                val_type: ValType::I32,
+                mutable: true,
+            },
+            &ConstExpr::i32_const(heap_start as i32),
+        );
+        module.section(&globals);
+
+        // Encode the export section.
+        let mut exports = ExportSection::new();
+        for (name, function_index) in &self.exports {
+            exports.export(name, ExportKind::Func, *function_index);
+        }
+        exports.export("memory", ExportKind::Memory, 0);
+        module.section(&exports);
+
+        // Encode the element section, filling the table with our lambdas
+        let mut elements = ElementSection::new();
+        elements.active(
+            None,
+            &ConstExpr::i32_const(0),
+            Elements::Functions(&self.table),
+        );
+        module.section(&elements);
+
+        // Encode the code section.
+        module.section(&codes);
+
+        // Encode the data section, this holds our string literals
+        if !self.data.is_empty() {
+            let mut data = DataSection::new();
+            data.active(0, &ConstExpr::i32_const(0), self.data);
+            module.section(&data);
+        }
+
+        // Extract the encoded Wasm bytes for this module.
+        module.finish()
+    }
+}
+
+// bump allocator, returns the current heap pointer then moves it along by
+// `size`, growing memory if we've run out
+fn alloc_function() -> Function {
+    let mut f = Function::new(vec![]);
+    let size = 0;
+
+    // return value, left on the stack until the end
+    f.instruction(&Instruction::GlobalGet(HEAP_POINTER_GLOBAL));
+
+    f.instruction(&Instruction::GlobalGet(HEAP_POINTER_GLOBAL));
+    f.instruction(&Instruction::LocalGet(size));
+    f.instruction(&Instruction::I32Add);
+    f.instruction(&Instruction::GlobalSet(HEAP_POINTER_GLOBAL));
+
+    // if heap pointer > memory size in bytes
+    f.instruction(&Instruction::GlobalGet(HEAP_POINTER_GLOBAL));
+    f.instruction(&Instruction::MemorySize(0));
+    f.instruction(&Instruction::I32Const(PAGE_SIZE_LOG2));
+    f.instruction(&Instruction::I32Shl);
+    f.instruction(&Instruction::I32GtU);
+    f.instruction(&Instruction::If(BlockType::Empty));
+
+    // then grow by enough pages to fit it
+    f.instruction(&Instruction::GlobalGet(HEAP_POINTER_GLOBAL));
+    f.instruction(&Instruction::MemorySize(0));
+    f.instruction(&Instruction::I32Const(PAGE_SIZE_LOG2));
+    f.instruction(&Instruction::I32Shl);
+    f.instruction(&Instruction::I32Sub);
+    f.instruction(&Instruction::I32Const(PAGE_SIZE_LOG2));
+    f.instruction(&Instruction::I32ShrU);
+    f.instruction(&Instruction::I32Const(1));
+    f.instruction(&Instruction::I32Add);
+    f.instruction(&Instruction::MemoryGrow(0));
+    f.instruction(&Instruction::Drop);
+    f.instruction(&Instruction::End);
+
+    f.instruction(&Instruction::End);
+    f
+}
+
+// allocate a new string holding the bytes of string 0 then string 1
+fn concat_function() -> Function {
+    let (left, right) = (0, 1);
+    let (left_length, right_length, result) = (2, 3, 4);
+    let mut f = Function::new(vec![(3, ValType::I32)]);
+
+    f.instruction(&Instruction::LocalGet(left));
+    f.instruction(&Instruction::I32Load(STRING_LENGTH));
+    f.instruction(&Instruction::LocalSet(left_length));
+    f.instruction(&Instruction::LocalGet(right));
+    f.instruction(&Instruction::I32Load(STRING_LENGTH));
+    f.instruction(&Instruction::LocalSet(right_length));
+
+    // allocate the header and both strings, rounded up to a whole slot so
+    // the heap pointer stays aligned
+    f.instruction(&Instruction::LocalGet(left_length));
+    f.instruction(&Instruction::LocalGet(right_length));
+    f.instruction(&Instruction::I32Add);
+    f.instruction(&Instruction::I32Const(
+        (STRING_HEADER_SIZE + SLOT_SIZE - 1) as i32,
+    ));
+    f.instruction(&Instruction::I32Add);
+    f.instruction(&Instruction::I32Const(-(SLOT_SIZE as i32)));
+    f.instruction(&Instruction::I32And);
+    f.instruction(&Instruction::Call(ALLOC_FUNCTION_INDEX));
+    f.instruction(&Instruction::LocalSet(result));
+
+    f.instruction(&Instruction::LocalGet(result));
+    f.instruction(&Instruction::LocalGet(left_length));
+    f.instruction(&Instruction::LocalGet(right_length));
+    f.instruction(&Instruction::I32Add);
+    f.instruction(&Instruction::I32Store(STRING_LENGTH));
+
+    // copy the left string to just after the header
+    f.instruction(&Instruction::LocalGet(result));
+    f.instruction(&Instruction::I32Const(STRING_HEADER_SIZE as i32));
+    f.instruction(&Instruction::I32Add);
+    f.instruction(&Instruction::LocalGet(left));
+    f.instruction(&Instruction::I32Const(STRING_HEADER_SIZE as i32));
+    f.instruction(&Instruction::I32Add);
+    f.instruction(&Instruction::LocalGet(left_length));
+    f.instruction(&Instruction::MemoryCopy {
+        src_mem: 0,
+        dst_mem: 0,
+    });
+
+    // then the right string after that
+    f.instruction(&Instruction::LocalGet(result));
+    f.instruction(&Instruction::I32Const(STRING_HEADER_SIZE as i32));
+    f.instruction(&Instruction::I32Add);
+    f.instruction(&Instruction::LocalGet(left_length));
+    f.instruction(&Instruction::I32Add);
+    f.instruction(&Instruction::LocalGet(right));
+    f.instruction(&Instruction::I32Const(STRING_HEADER_SIZE as i32));
+    f.instruction(&Instruction::I32Add);
+    f.instruction(&Instruction::LocalGet(right_length));
+    f.instruction(&Instruction::MemoryCopy {
+        src_mem: 0,
+        dst_mem: 0,
+    });
+
+    f.instruction(&Instruction::LocalGet(result));
+    f.instruction(&Instruction::End);
+    f
+}
+
+// 1 if strings 0 and 1 hold the same bytes, 0 otherwise
+fn string_equals_function() -> Function {
+    let (left, right) = (0, 1);
+    let (length, index) = (2, 3);
+    let mut f = Function::new(vec![(2, ValType::I32)]);
+    let byte = MemArg {
+        offset: u64::from(STRING_HEADER_SIZE),
+        align: 0,
+        memory_index: 0,
+    };
+
+    // different lengths can't be equal
+    f.instruction(&Instruction::LocalGet(left));
+    f.instruction(&Instruction::I32Load(STRING_LENGTH));
+    f.instruction(&Instruction::LocalTee(length));
+    f.instruction(&Instruction::LocalGet(right));
+    f.instruction(&Instruction::I32Load(STRING_LENGTH));
+    f.instruction(&Instruction::I32Ne);
+    f.instruction(&Instruction::If(BlockType::Empty));
+    f.instruction(&Instruction::I32Const(0));
+    f.instruction(&Instruction::Return);
+    f.instruction(&Instruction::End);
+
+    f.instruction(&Instruction::Block(BlockType::Empty));
+    f.instruction(&Instruction::Loop(BlockType::Empty));
+
+    // stop once we've checked every byte
+    f.instruction(&Instruction::LocalGet(index));
+    f.instruction(&Instruction::LocalGet(length));
+    f.instruction(&Instruction::I32GeU);
+    f.instruction(&Instruction::BrIf(1));
+
+    f.instruction(&Instruction::LocalGet(left));
+    f.instruction(&Instruction::LocalGet(index));
+    f.instruction(&Instruction::I32Add);
+    f.instruction(&Instruction::I32Load8U(byte));
+    f.instruction(&Instruction::LocalGet(right));
+    f.instruction(&Instruction::LocalGet(index));
+    f.instruction(&Instruction::I32Add);
+    f.instruction(&Instruction::I32Load8U(byte));
+    f.instruction(&Instruction::I32Ne);
+    f.instruction(&Instruction::If(BlockType::Empty));
+    f.instruction(&Instruction::I32Const(0));
+    f.instruction(&Instruction::Return);
+    f.instruction(&Instruction::End);
+
+    f.instruction(&Instruction::LocalGet(index));
+    f.instruction(&Instruction::I32Const(1));
+    f.instruction(&Instruction::I32Add);
+    f.instruction(&Instruction::LocalSet(index));
+    f.instruction(&Instruction::Br(0));
+
+    f.instruction(&Instruction::End);
+    f.instruction(&Instruction::End);
+
+    f.instruction(&Instruction::I32Const(1));
+    f.instruction(&Instruction::End);
+    f
+}
+
+// the address of the value of field 1 in record 0
+// the typechecker makes sure the field is there, but we trap rather than
+// reading past the end of the record if it isn't
+fn record_field_function() -> Function {
+    let (record, field) = (0, 1);
+    let (address, end) = (2, 3);
+    let mut f = Function::new(vec![(2, ValType::I32)]);
+
+    // the first id comes straight after the field count
+    f.instruction(&Instruction::LocalGet(record));
+    f.instruction(&Instruction::I32Const(SLOT_SIZE as i32));
+    f.instruction(&Instruction::I32Add);
+    f.instruction(&Instruction::LocalSet(address));
+
+    f.instruction(&Instruction::LocalGet(address));
+    f.instruction(&Instruction::LocalGet(record));
+    f.instruction(&Instruction::I32Load(closure_slot(RECORD_FIELD_COUNT_SLOT)));
+    f.instruction(&Instruction::I32Const(2 * SLOT_SIZE as i32));
+    f.instruction(&Instruction::I32Mul);
+    f.instruction(&Instruction::I32Add);
+    f.instruction(&Instruction::LocalSet(end));
+
+    f.instruction(&Instruction::Loop(BlockType::Empty));
+
+    f.instruction(&Instruction::LocalGet(address));
+    f.instruction(&Instruction::LocalGet(end));
+    f.instruction(&Instruction::I32GeU);
+    f.instruction(&Instruction::If(BlockType::Empty));
+    f.instruction(&Instruction::Unreachable);
+    f.instruction(&Instruction::End);
+
+    f.instruction(&Instruction::LocalGet(address));
+    f.instruction(&Instruction::I32Load(closure_slot(0)));
+    f.instruction(&Instruction::LocalGet(field));
+    f.instruction(&Instruction::I32Eq);
+    f.instruction(&Instruction::If(BlockType::Empty));
+    f.instruction(&Instruction::LocalGet(address));
+    f.instruction(&Instruction::I32Const(SLOT_SIZE as i32));
+    f.instruction(&Instruction::I32Add);
+    f.instruction(&Instruction::Return);
+    f.instruction(&Instruction::End);
+
+    f.instruction(&Instruction::LocalGet(address));
+    f.instruction(&Instruction::I32Const(2 * SLOT_SIZE as i32));
+    f.instruction(&Instruction::I32Add);
+    f.instruction(&Instruction::LocalSet(address));
+    f.instruction(&Instruction::Br(0));
+
+    f.instruction(&Instruction::End);
+
+    f.instruction(&Instruction::Unreachable);
     f.instruction(&Instruction::End);
+    f
+}
+
+// the lifted function for a builtin, which like any lifted function takes
+// its closure pointer and then its argument
+fn builtin_function(builtin: Builtin) -> Function {
+    let argument = 1;
+    let mut f = Function::new(vec![]);
+
+    match builtin {
+        Builtin::Length => {
+            f.instruction(&Instruction::LocalGet(argument));
+            f.instruction(&Instruction::I32Load(STRING_LENGTH));
+        }
+    }
 
+    f.instruction(&Instruction::End);
     f
 }
 
+// state for the function we're currently compiling
+// we don't know how many locals we need until we've seen every `let`, so we
+// collect instructions here and only create the `Function` at the end
+struct FunctionBuilder {
+    // number of parameters, these come before any locals
+    param_count: u32,
+    // type of each local we've allocated, not including parameters
+    locals: Vec<ValType>,
+    // variables currently in scope and the local they live in, innermost last
+    scope: Vec<(String, u32)>,
+    instructions: Vec<Instruction<'static>>,
+    // how many blocks the next instruction is inside, so we know how far to
+    // branch to get out of them
+    depth: u32,
+    // set while compiling the body of a function that calls itself
+    self_call: Option<SelfCall>,
+}
+
+// the body of a `let rec` or top level function is wrapped in a `loop`, and
+// calls to itself in tail position that pass every argument set each
+// parameter and branch back to the start rather than calling
+struct SelfCall {
+    // the depth just inside the `loop`
+    loop_depth: u32,
+    name: String,
+    // the local its closure is in, or `None` for a top level definition,
+    // which we refer to by name instead
+    closure: Option<u32>,
+    // the local each of its parameters is in, outermost first, or `None` for
+    // ones the body never uses
+    params: Vec<Option<u32>>,
+}
+
+// a function that may call itself, passed down the lambdas it's made of so
+// the innermost one, which has every argument, can set up its `SelfCall`
+#[derive(Clone)]
+struct Recursive {
+    name: String,
+    top_level: bool,
+    // parameters of the lambdas around the one we're compiling, outermost
+    // first
+    outer_params: Vec<String>,
+}
+
+impl FunctionBuilder {
+    fn new(param_count: u32) -> Self {
+        FunctionBuilder {
+            param_count,
+            locals: vec![],
+            scope: vec![],
+            instructions: vec![],
+            depth: 0,
+            self_call: None,
+        }
+    }
+
+    fn instruction(&mut self, instruction: Instruction<'static>) {
+        match instruction {
+            Instruction::Block(_) | Instruction::Loop(_) | Instruction::If(_) => self.depth += 1,
+            Instruction::End => self.depth -= 1,
+            _ => {}
+        }
+        self.instructions.push(instruction)
+    }
+
+    // allocate a fresh local that isn't bound to any variable
+    fn fresh_local(&mut self, ty: ValType) -> u32 {
+        let index = self.param_count + self.locals.len() as u32;
+        self.locals.push(ty);
+        index
+    }
+
+    // allocate a fresh local and bring `identifier` into scope
+    // every `let` gets its own local, so shadowed values are never overwritten
+    fn bind_local(&mut self, identifier: String, ty: ValType) -> u32 {
+        let index = self.fresh_local(ty);
+        self.scope.push((identifier, index));
+        index
+    }
+
+    // bring a parameter into scope
+    fn bind_param(&mut self, identifier: String, index: u32) {
+        self.scope.push((identifier, index));
+    }
+
+    fn unbind_local(&mut self) {
+        self.scope.pop();
+    }
+
+    fn lookup_local(&self, identifier: &str) -> Option<u32> {
+        self.scope
+            .iter()
+            .rev()
+            .find(|(name, _)| name == identifier)
+            .map(|(_, index)| *index)
+    }
+
+    fn finish(self) -> Function {
+        let mut f = Function::new_with_locals_types(self.locals);
+
+        for instruction in self.instructions {
+            f.instruction(&instruction);
+        }
+
+        f.instruction(&Instruction::End);
+
+        f
+    }
+}
+
+// address of slot `index` in a closure
+fn closure_slot(index: u32) -> MemArg {
+    MemArg {
+        offset: u64::from(index * SLOT_SIZE),
+        align: 2,
+        memory_index: 0,
+    }
+}
+
 fn expr_to_instructions<Ann>(
-    f: &mut wasm_encoder::Function,
-    expr: Expr<Ann>,
-) -> &mut wasm_encoder::Function {
+    module: &mut ModuleBuilder,
+    f: &mut FunctionBuilder,
+    expr: Expr<Type<Ann>>,
+) where
+    Ann: Clone + Copy,
+{
+    compile_expr(module, f, expr, false)
+}
+
+// compile an expression whose value the function returns, so calls in it
+// can be tail calls
+fn tail_expr_to_instructions<Ann>(
+    module: &mut ModuleBuilder,
+    f: &mut FunctionBuilder,
+    expr: Expr<Type<Ann>>,
+) where
+    Ann: Clone + Copy,
+{
+    compile_expr(module, f, expr, true)
+}
+
+fn compile_expr<Ann>(
+    module: &mut ModuleBuilder,
+    f: &mut FunctionBuilder,
+    expr: Expr<Type<Ann>>,
+    tail: bool,
+) where
+    Ann: Clone + Copy,
+{
     match expr {
-        Expr::EPrim { prim, .. } => f.instruction(&prim_to_const(prim.clone())),
+        Expr::EPrim { prim, .. } => f.instruction(prim_to_const(module, prim)),
         Expr::EIf {
+            ann,
             pred_expr,
             then_expr,
             else_expr,
+        } => {
+            // only the branch we take is evaluated, so side effects and
+            // traps in the other branch never happen
+            expr_to_instructions(module, f, *pred_expr);
+            f.instruction(Instruction::If(BlockType::Result(type_to_val_type(ann))));
+            compile_expr(module, f, *then_expr, tail);
+            f.instruction(Instruction::Else);
+            compile_expr(module, f, *else_expr, tail);
+            f.instruction(Instruction::End)
+        }
+        Expr::ELet {
+            identifier,
+            bound_expr,
+            rest_expr,
+            ..
+        } => {
+            // evaluate the bound expression before `identifier` is in scope
+            let bound_type = get_expr_annotation(*bound_expr.clone());
+            expr_to_instructions(module, f, *bound_expr);
+            let index = f.bind_local(identifier, type_to_val_type(bound_type));
+            f.instruction(Instruction::LocalSet(index));
+            compile_expr(module, f, *rest_expr, tail);
+            f.unbind_local()
+        }
+        Expr::ELetRec {
+            identifier,
+            bound_expr,
+            rest_expr,
+            ..
+        } => {
+            let recursive = Recursive {
+                name: identifier.clone(),
+                top_level: false,
+                outer_params: vec![],
+            };
+            lambda_to_instructions(module, f, *bound_expr, Some(recursive));
+            let index = f.bind_local(identifier, ValType::I32);
+            f.instruction(Instruction::LocalSet(index));
+            compile_expr(module, f, *rest_expr, tail);
+            f.unbind_local()
+        }
+        // locals shadow top level definitions, which shadow builtins
+        Expr::EVar { identifier, .. } => {
+            match (
+                f.lookup_local(&identifier),
+                module.lookup_global(&identifier),
+            ) {
+                (Some(index), _) => f.instruction(Instruction::LocalGet(index)),
+                (None, Some(function_index)) => f.instruction(Instruction::Call(function_index)),
+                (None, None) => match lookup_builtin(&identifier) {
+                    Some(builtin) => builtin_closure(module, f, builtin),
+                    None => panic!(
+                        "variable `{}` is not in scope, did the program typecheck?",
+                        identifier
+                    ),
+                },
+            }
+        }
+        Expr::EBinOp {
+            op: Op::And,
+            left_expr,
+            right_expr,
             ..
         } => {
-            expr_to_instructions(f, *then_expr);
-            expr_to_instructions(f, *else_expr);
-            expr_to_instructions(f, *pred_expr);
-            f.instruction(&Instruction::Select)
+            // only evaluate the right hand side if the left is true
+            expr_to_instructions(module, f, *left_expr);
+            f.instruction(Instruction::If(BlockType::Result(ValType::I32)));
+            expr_to_instructions(module, f, *right_expr);
+            f.instruction(Instruction::Else);
+            f.instruction(Instruction::I32Const(0));
+            f.instruction(Instruction::End)
         }
-        _ => f,
+        Expr::EBinOp {
+            op: Op::Or,
+            left_expr,
+            right_expr,
+            ..
+        } => {
+            // only evaluate the right hand side if the left is false
+            expr_to_instructions(module, f, *left_expr);
+            f.instruction(Instruction::If(BlockType::Result(ValType::I32)));
+            f.instruction(Instruction::I32Const(1));
+            f.instruction(Instruction::Else);
+            expr_to_instructions(module, f, *right_expr);
+            f.instruction(Instruction::End)
+        }
+        Expr::EBinOp {
+            op: Op::Concat,
+            left_expr,
+            right_expr,
+            ..
+        } => {
+            expr_to_instructions(module, f, *left_expr);
+            expr_to_instructions(module, f, *right_expr);
+            f.instruction(Instruction::Call(CONCAT_FUNCTION_INDEX))
+        }
+        // strings are pointers, so we compare what they point to
+        // the typechecker makes sure everything else we compare is an int or
+        // a bool
+        Expr::EBinOp {
+            op: op @ (Op::Equals | Op::NotEquals),
+            left_expr,
+            right_expr,
+            ..
+        } if matches!(
+            get_expr_annotation(*left_expr.clone()),
+            Type::TString { .. }
+        ) =>
+        {
+            expr_to_instructions(module, f, *left_expr);
+            expr_to_instructions(module, f, *right_expr);
+            f.instruction(Instruction::Call(STRING_EQUALS_FUNCTION_INDEX));
+            if op == Op::NotEquals {
+                f.instruction(Instruction::I32Eqz)
+            }
+        }
+        Expr::EBinOp {
+            op,
+            left_expr,
+            right_expr,
+            ..
+        } => {
+            expr_to_instructions(module, f, *left_expr);
+            expr_to_instructions(module, f, *right_expr);
+            f.instruction(op_to_instruction(op))
+        }
+        Expr::ELambda { .. } => lambda_to_instructions(module, f, expr, None),
+        // a function calling itself in tail position with every argument
+        // sets its parameters and jumps back to the start of its body
+        Expr::EApply { .. } if tail && is_self_call(f, &expr) => {
+            let (_, args) = application_spine(expr);
+            let self_call = f.self_call.as_ref().expect("checked above");
+            let params = self_call.params.clone();
+            let loop_depth = self_call.loop_depth;
+
+            // every argument is evaluated before any parameter changes, as
+            // they may refer to the current ones
+            for arg in args {
+                expr_to_instructions(module, f, arg);
+            }
+            for param in params.into_iter().rev() {
+                match param {
+                    Some(index) => f.instruction(Instruction::LocalSet(index)),
+                    None => f.instruction(Instruction::Drop),
+                }
+            }
+            f.instruction(Instruction::Br(f.depth - loop_depth))
+        }
+        Expr::EApply {
+            function_expr,
+            argument_expr,
+            ..
+        } => {
+            let function_type = get_expr_annotation(*function_expr.clone());
+
+            // keep the closure pointer around, it's both the first argument
+            // and where we find the table index
+            expr_to_instructions(module, f, *function_expr);
+            let closure = f.fresh_local(ValType::I32);
+            f.instruction(Instruction::LocalTee(closure));
+
+            expr_to_instructions(module, f, *argument_expr);
+
+            let tail_call = tail && module.options.tail_calls;
+            call_closure(module, f, closure, function_type, tail_call)
+        }
+        Expr::EConstructor {
+            constructor, args, ..
+        } => {
+            let tag = module.lookup_constructor_tag(&constructor);
+            let data = f.fresh_local(ValType::I32);
+            let data_size = (args.len() as u32 + 1) * SLOT_SIZE;
+            f.instruction(Instruction::I32Const(data_size as i32));
+            f.instruction(Instruction::Call(ALLOC_FUNCTION_INDEX));
+            f.instruction(Instruction::LocalSet(data));
+
+            f.instruction(Instruction::LocalGet(data));
+            f.instruction(Instruction::I32Const(tag as i32));
+            f.instruction(Instruction::I32Store(closure_slot(CONSTRUCTOR_TAG_SLOT)));
+
+            for (slot, arg) in args.into_iter().enumerate() {
+                f.instruction(Instruction::LocalGet(data));
+                expr_to_instructions(module, f, arg);
+                f.instruction(Instruction::I32Store(closure_slot(slot as u32 + 1)));
+            }
+
+            f.instruction(Instruction::LocalGet(data))
+        }
+        // each arm is a block we break out of as soon as its pattern fails
+        // to match, falling through to the next arm. If the pattern matches
+        // we run the body and break out of the whole `case`
+        Expr::ECase {
+            ann,
+            scrutinee_expr,
+            arms,
+        } => {
+            expr_to_instructions(module, f, *scrutinee_expr);
+            let scrutinee = f.fresh_local(ValType::I32);
+            f.instruction(Instruction::LocalSet(scrutinee));
+
+            f.instruction(Instruction::Block(BlockType::Result(type_to_val_type(ann))));
+            for arm in arms {
+                let binding_count = pattern_variables(&arm.pattern).len();
+                f.instruction(Instruction::Block(BlockType::Empty));
+                pattern_to_instructions(module, f, arm.pattern, scrutinee);
+                compile_expr(module, f, arm.body_expr, tail);
+                f.instruction(Instruction::Br(1));
+                f.instruction(Instruction::End);
+                for _ in 0..binding_count {
+                    f.unbind_local();
+                }
+            }
+            // no arm matched
+            f.instruction(Instruction::Unreachable);
+            f.instruction(Instruction::End)
+        }
+        // like a constructor without a tag, there's only one kind of tuple
+        // of each type
+        Expr::ETuple { items, .. } => {
+            let tuple = f.fresh_local(ValType::I32);
+            f.instruction(Instruction::I32Const(
+                (items.len() as u32 * SLOT_SIZE) as i32,
+            ));
+            f.instruction(Instruction::Call(ALLOC_FUNCTION_INDEX));
+            f.instruction(Instruction::LocalSet(tuple));
+
+            for (slot, item) in items.into_iter().enumerate() {
+                f.instruction(Instruction::LocalGet(tuple));
+                expr_to_instructions(module, f, item);
+                f.instruction(Instruction::I32Store(closure_slot(slot as u32)));
+            }
+
+            f.instruction(Instruction::LocalGet(tuple))
+        }
+        Expr::ERecord { fields, .. } => {
+            let record = f.fresh_local(ValType::I32);
+            let record_size = (2 * fields.len() as u32 + 1) * SLOT_SIZE;
+            f.instruction(Instruction::I32Const(record_size as i32));
+            f.instruction(Instruction::Call(ALLOC_FUNCTION_INDEX));
+            f.instruction(Instruction::LocalSet(record));
+
+            f.instruction(Instruction::LocalGet(record));
+            f.instruction(Instruction::I32Const(fields.len() as i32));
+            f.instruction(Instruction::I32Store(closure_slot(RECORD_FIELD_COUNT_SLOT)));
+
+            for (index, (name, field_expr)) in fields.into_iter().enumerate() {
+                let id = module.field_id(&name);
+                f.instruction(Instruction::LocalGet(record));
+                f.instruction(Instruction::I32Const(id as i32));
+                f.instruction(Instruction::I32Store(closure_slot(2 * index as u32 + 1)));
+
+                f.instruction(Instruction::LocalGet(record));
+                expr_to_instructions(module, f, field_expr);
+                f.instruction(Instruction::I32Store(closure_slot(2 * index as u32 + 2)));
+            }
+
+            f.instruction(Instruction::LocalGet(record))
+        }
+        Expr::EFieldAccess {
+            record_expr, field, ..
+        } => {
+            let id = module.field_id(&field);
+            expr_to_instructions(module, f, *record_expr);
+            f.instruction(Instruction::I32Const(id as i32));
+            f.instruction(Instruction::Call(RECORD_FIELD_FUNCTION_INDEX));
+            f.instruction(Instruction::I32Load(closure_slot(0)))
+        }
+        // annotations only matter to the typechecker
+        Expr::EAnnotation { expr, .. } => compile_expr(module, f, *expr, tail),
+        // copy the whole record, then overwrite the fields we're updating
+        Expr::ERecordUpdate {
+            record_expr,
+            fields,
+            ..
+        } => {
+            let (old_record, new_record, record_size) = (
+                f.fresh_local(ValType::I32),
+                f.fresh_local(ValType::I32),
+                f.fresh_local(ValType::I32),
+            );
+            expr_to_instructions(module, f, *record_expr);
+            f.instruction(Instruction::LocalTee(old_record));
+            f.instruction(Instruction::I32Load(closure_slot(RECORD_FIELD_COUNT_SLOT)));
+            f.instruction(Instruction::I32Const(2));
+            f.instruction(Instruction::I32Mul);
+            f.instruction(Instruction::I32Const(1));
+            f.instruction(Instruction::I32Add);
+            f.instruction(Instruction::I32Const(SLOT_SIZE as i32));
+            f.instruction(Instruction::I32Mul);
+            f.instruction(Instruction::LocalTee(record_size));
+            f.instruction(Instruction::Call(ALLOC_FUNCTION_INDEX));
+            f.instruction(Instruction::LocalTee(new_record));
+            f.instruction(Instruction::LocalGet(old_record));
+            f.instruction(Instruction::LocalGet(record_size));
+            f.instruction(Instruction::MemoryCopy {
+                src_mem: 0,
+                dst_mem: 0,
+            });
+
+            for (name, field_expr) in fields {
+                let id = module.field_id(&name);
+                f.instruction(Instruction::LocalGet(new_record));
+                f.instruction(Instruction::I32Const(id as i32));
+                f.instruction(Instruction::Call(RECORD_FIELD_FUNCTION_INDEX));
+                expr_to_instructions(module, f, field_expr);
+                f.instruction(Instruction::I32Store(closure_slot(0)));
+            }
+
+            f.instruction(Instruction::LocalGet(new_record))
+        }
+    }
+}
+
+// check the value in local `value` matches `pattern`, breaking out of the
+// enclosing block if it doesn't, and bring the variables it binds into scope
+fn pattern_to_instructions<Ann>(
+    module: &mut ModuleBuilder,
+    f: &mut FunctionBuilder,
+    pattern: Pattern<Type<Ann>>,
+    value: u32,
+) where
+    Ann: Clone + Copy,
+{
+    match pattern {
+        // locals are never assigned twice, so we can share this one
+        Pattern::PVar { identifier, .. } => f.bind_param(identifier, value),
+        Pattern::PWildcard { .. } => {}
+        Pattern::PLiteral {
+            prim: Prim::PString { string },
+            ..
+        } => {
+            let address = module.string_literal(&string);
+            f.instruction(Instruction::LocalGet(value));
+            f.instruction(Instruction::I32Const(address as i32));
+            f.instruction(Instruction::Call(STRING_EQUALS_FUNCTION_INDEX));
+            f.instruction(Instruction::I32Eqz);
+            f.instruction(Instruction::BrIf(0));
+        }
+        Pattern::PLiteral { prim, .. } => {
+            f.instruction(Instruction::LocalGet(value));
+            f.instruction(prim_to_const(module, prim));
+            f.instruction(Instruction::I32Ne);
+            f.instruction(Instruction::BrIf(0));
+        }
+        // the typechecker made sure the sizes match, so only the items can
+        // fail
+        Pattern::PTuple { items, .. } => {
+            for (slot, item) in items.into_iter().enumerate() {
+                let item_value = f.fresh_local(ValType::I32);
+                f.instruction(Instruction::LocalGet(value));
+                f.instruction(Instruction::I32Load(closure_slot(slot as u32)));
+                f.instruction(Instruction::LocalSet(item_value));
+                pattern_to_instructions(module, f, item, item_value);
+            }
+        }
+        Pattern::PConstructor {
+            constructor, args, ..
+        } => {
+            let tag = module.lookup_constructor_tag(&constructor);
+            f.instruction(Instruction::LocalGet(value));
+            f.instruction(Instruction::I32Load(closure_slot(CONSTRUCTOR_TAG_SLOT)));
+            f.instruction(Instruction::I32Const(tag as i32));
+            f.instruction(Instruction::I32Ne);
+            f.instruction(Instruction::BrIf(0));
+
+            for (slot, arg) in args.into_iter().enumerate() {
+                let field = f.fresh_local(ValType::I32);
+                f.instruction(Instruction::LocalGet(value));
+                f.instruction(Instruction::I32Load(closure_slot(slot as u32 + 1)));
+                f.instruction(Instruction::LocalSet(field));
+                pattern_to_instructions(module, f, arg, field);
+            }
+        }
+    }
+}
+
+// the function being applied and its arguments, outermost application last
+fn application_spine<Ann>(expr: Expr<Ann>) -> (Expr<Ann>, Vec<Expr<Ann>>) {
+    match expr {
+        Expr::EApply {
+            function_expr,
+            argument_expr,
+            ..
+        } => {
+            let (function, mut args) = application_spine(*function_expr);
+            args.push(*argument_expr);
+            (function, args)
+        }
+        other => (other, vec![]),
+    }
+}
+
+// whether `expr` applies the function whose body we're compiling to all of
+// its arguments. It's only the same function if its name finds the same
+// closure, so a shadowed name never matches
+fn is_self_call<Ann>(f: &FunctionBuilder, expr: &Expr<Ann>) -> bool
+where
+    Ann: Clone,
+{
+    let self_call = match &f.self_call {
+        Some(self_call) => self_call,
+        None => return false,
+    };
+    match application_spine(expr.clone()) {
+        (Expr::EVar { identifier, .. }, args) => {
+            identifier == self_call.name
+                && f.lookup_local(&identifier) == self_call.closure
+                && args.len() == self_call.params.len()
+        }
+        _ => false,
+    }
+}
+
+// call the closure in local `closure`, which has type `function_type`
+// the closure pointer and the argument must already be on the stack
+// a tail call returns whatever the closure does, and must only be used in
+// tail position in a function that returns the same type
+fn call_closure<Ann>(
+    module: &mut ModuleBuilder,
+    f: &mut FunctionBuilder,
+    closure: u32,
+    function_type: Type<Ann>,
+    tail_call: bool,
+) where
+    Ann: Clone + Copy,
+{
+    let type_index = function_type_index(module, function_type);
+    f.instruction(Instruction::LocalGet(closure));
+    f.instruction(Instruction::I32Load(closure_slot(0)));
+    if tail_call {
+        f.instruction(Instruction::ReturnCallIndirect {
+            ty: type_index,
+            table: 0,
+        })
+    } else {
+        f.instruction(Instruction::CallIndirect {
+            ty: type_index,
+            table: 0,
+        })
+    }
+}
+
+// where the innermost lambda of `recursive`, with parameter `identifier`,
+// finds itself and each of its parameters, or `None` if a parameter shadows
+// its name. Outer parameters are captured, so this must be called before
+// `identifier` is bound
+fn self_call(lambda: &FunctionBuilder, recursive: Recursive, identifier: &str) -> Option<SelfCall> {
+    let mut names = recursive.outer_params;
+    names.push(identifier.to_string());
+    if names.contains(&recursive.name) {
+        return None;
     }
+
+    let closure = match recursive.top_level {
+        true => None,
+        false => Some(lambda.lookup_local(&recursive.name)?),
+    };
+
+    // a parameter shadowed by a later one with the same name is never used
+    let params = names
+        .iter()
+        .enumerate()
+        .map(|(position, name)| match position + 1 == names.len() {
+            true => Some(ARGUMENT_PARAM),
+            false if names[position + 1..].contains(name) => None,
+            false => lambda.lookup_local(name),
+        })
+        .collect();
+
+    Some(SelfCall {
+        loop_depth: 0,
+        name: recursive.name,
+        closure,
+        params,
+    })
+}
+
+// builtins capture nothing, so their closures are just a table index
+fn builtin_closure(module: &mut ModuleBuilder, f: &mut FunctionBuilder, builtin: Builtin) {
+    let table_index = module
+        .builtins
+        .iter()
+        .find(|(other, _)| *other == builtin)
+        .map(|(_, table_index)| *table_index)
+        .expect("every builtin is in the table");
+
+    let closure = f.fresh_local(ValType::I32);
+    f.instruction(Instruction::I32Const(SLOT_SIZE as i32));
+    f.instruction(Instruction::Call(ALLOC_FUNCTION_INDEX));
+    f.instruction(Instruction::LocalTee(closure));
+    f.instruction(Instruction::I32Const(table_index as i32));
+    f.instruction(Instruction::I32Store(closure_slot(0)));
+    f.instruction(Instruction::LocalGet(closure));
 }
 
-fn prim_to_const(prim: Prim) -> Instruction<'static> {
+// lift the lambda into its own function, then allocate a closure for it
+// the outermost lambda of a `let rec` function finds itself in its closure
+// parameter rather than capturing itself, and the ones inside it capture it
+// like any other variable
+fn lambda_to_instructions<Ann>(
+    module: &mut ModuleBuilder,
+    f: &mut FunctionBuilder,
+    expr: Expr<Type<Ann>>,
+    recursive: Option<Recursive>,
+) where
+    Ann: Clone + Copy,
+{
+    let closure_is_self = matches!(&recursive, Some(recursive)
+        if !recursive.top_level && recursive.outer_params.is_empty());
+
+    // top level definitions aren't captured, the lifted function can call
+    // them itself
+    let captured: Vec<String> = free_variables(&expr)
+        .into_iter()
+        .filter(|name| !(closure_is_self && Some(name) == recursive.as_ref().map(|r| &r.name)))
+        .filter(|name| f.lookup_local(name).is_some())
+        .collect();
+
+    let (ann, identifier, body_expr) = match expr {
+        Expr::ELambda {
+            ann,
+            identifier,
+            body_expr,
+        } => (ann, identifier, body_expr),
+        _ => unreachable!("expected a lambda"),
+    };
+
+    let function_index = module.reserve_function();
+    let table_index = module.add_to_table(function_index);
+
+    let mut lambda = FunctionBuilder::new(2);
+
+    // copy each captured variable out of the closure into a local
+    for (slot, name) in captured.iter().enumerate() {
+        let index = lambda.bind_local(name.clone(), ValType::I32);
+        lambda.instruction(Instruction::LocalGet(CLOSURE_PARAM));
+        lambda.instruction(Instruction::I32Load(closure_slot(slot as u32 + 1)));
+        lambda.instruction(Instruction::LocalSet(index));
+    }
+
+    if let (true, Some(recursive)) = (closure_is_self, &recursive) {
+        lambda.bind_param(recursive.name.clone(), CLOSURE_PARAM);
+    }
+
+    match (recursive, *body_expr) {
+        // not every argument has been passed yet, so the body is the next
+        // lambda, which is compiled knowing about this one's parameter
+        (Some(mut recursive), body_expr @ Expr::ELambda { .. }) => {
+            recursive.outer_params.push(identifier.clone());
+            lambda.bind_param(identifier, ARGUMENT_PARAM);
+            lambda_to_instructions(module, &mut lambda, body_expr, Some(recursive));
+        }
+        // the body is a loop, so calls to itself in tail position can
+        // branch back to the start
+        (Some(recursive), body_expr) => {
+            let result_type = match &ann {
+                Type::TFunction { result, .. } => type_to_val_type(*result.clone()),
+                _ => unreachable!("expected a function type"),
+            };
+            let self_call = self_call(&lambda, recursive, &identifier);
+            lambda.bind_param(identifier, ARGUMENT_PARAM);
+            lambda.instruction(Instruction::Loop(BlockType::Result(result_type)));
+            lambda.self_call = self_call.map(|self_call| SelfCall {
+                loop_depth: lambda.depth,
+                ..self_call
+            });
+            tail_expr_to_instructions(module, &mut lambda, body_expr);
+            lambda.instruction(Instruction::End);
+        }
+        (None, body_expr) => {
+            lambda.bind_param(identifier, ARGUMENT_PARAM);
+            tail_expr_to_instructions(module, &mut lambda, body_expr);
+        }
+    }
+
+    let type_index = function_type_index(module, ann);
+    module.define_function(function_index, type_index, lambda.finish());
+
+    // allocate the closure
+    let closure = f.fresh_local(ValType::I32);
+    let closure_size = (captured.len() as u32 + 1) * SLOT_SIZE;
+    f.instruction(Instruction::I32Const(closure_size as i32));
+    f.instruction(Instruction::Call(ALLOC_FUNCTION_INDEX));
+    f.instruction(Instruction::LocalSet(closure));
+
+    f.instruction(Instruction::LocalGet(closure));
+    f.instruction(Instruction::I32Const(table_index as i32));
+    f.instruction(Instruction::I32Store(closure_slot(0)));
+
+    for (slot, name) in captured.iter().enumerate() {
+        let index = f.lookup_local(name).unwrap_or_else(|| {
+            panic!(
+                "variable `{}` is not in scope, did the program typecheck?",
+                name
+            )
+        });
+        f.instruction(Instruction::LocalGet(closure));
+        f.instruction(Instruction::LocalGet(index));
+        f.instruction(Instruction::I32Store(closure_slot(slot as u32 + 1)));
+    }
+
+    f.instruction(Instruction::LocalGet(closure));
+}
+
+// the wasm type of the lifted function for a function type
+fn function_type_index<Ann>(module: &mut ModuleBuilder, ty: Type<Ann>) -> u32
+where
+    Ann: Clone + Copy,
+{
+    match ty {
+        Type::TFunction {
+            argument, result, ..
+        } => module.function_type(
+            vec![ValType::I32, type_to_val_type(*argument)],
+            vec![type_to_val_type(*result)],
+        ),
+        _ => panic!("expected a function type, did the program typecheck?"),
+    }
+}
+
+// ints and bools are both `i32`, so `==` and `!=` work for either
+fn op_to_instruction(op: Op) -> Instruction<'static> {
+    match op {
+        Op::Add => Instruction::I32Add,
+        Op::Subtract => Instruction::I32Sub,
+        Op::Multiply => Instruction::I32Mul,
+        Op::Divide => Instruction::I32DivS,
+        Op::Modulo => Instruction::I32RemS,
+        Op::Equals => Instruction::I32Eq,
+        Op::NotEquals => Instruction::I32Ne,
+        Op::LessThan => Instruction::I32LtS,
+        Op::LessThanOrEqual => Instruction::I32LeS,
+        Op::GreaterThan => Instruction::I32GtS,
+        Op::GreaterThanOrEqual => Instruction::I32GeS,
+        Op::And | Op::Or => unreachable!("`&&` and `||` are compiled to `if` blocks"),
+        Op::Concat => unreachable!("`++` is compiled to a call to `concat`"),
+    }
+}
+
+// how values of each type are represented in wasm
+// functions are pointers to closures in linear memory
+fn type_to_val_type<Ann>(ty: Type<Ann>) -> ValType
+where
+    Ann: Clone + Copy,
+{
+    match ty {
+        Type::TInt { .. } => ValType::I32,
+        Type::TBool { .. } => ValType::I32,
+        Type::TString { .. } => ValType::I32,
+        Type::TFunction { .. } => ValType::I32,
+        Type::TConstructor { .. } => ValType::I32,
+        Type::TTuple { .. } => ValType::I32,
+        Type::TRecord { .. } => ValType::I32,
+        // anything we know nothing about is only passed around, never
+        // inspected, as the typechecker won't let us compare it with `==`,
+        // so its representation doesn't matter as long as it's consistent
+        Type::TVar { .. } => ValType::I32,
+    }
+}
+
+fn prim_to_const(module: &mut ModuleBuilder, prim: Prim) -> Instruction<'static> {
     match prim {
+        Prim::PString { string } => Instruction::I32Const(module.string_literal(&string) as i32),
         Prim::PInt { int } => Instruction::I32Const(int),
         Prim::PBool { bool: true } => Instruction::I32Const(1),
         Prim::PBool { bool: false } => Instruction::I32Const(0),
     }
 }
 
+// typecheck, compile and run an expression
+#[cfg(test)]
+fn run_expr(expr: Expr<()>) -> i32 {
+    let wasm = expr_to_wasm(elaborate_expr(expr).unwrap());
+    super::run_wasm::run_wasm_from_ast(wasm).unwrap()
+}
+
 #[test]
 fn test_run_wasm_eq_from_ast() {
     // 100 == 1
-    let (_, input) = crate::parser::parse_expr::parse_my_expr("if True then 42 else 41")
-        .finish()
-        .unwrap();
-    let wasm = expr_to_wasm(input);
+    let (_, input) = crate::parser::parse_expr::parse_my_expr(
+        crate::parser::span::ParseInput::new("if True then 42 else 41"),
+    )
+    .finish()
+    .unwrap();
+    let wasm = expr_to_wasm(elaborate_expr(input).unwrap());
 
     let result = super::run_wasm::run_wasm_from_ast(wasm).unwrap();
     assert_eq!(result, 42)
 }
+
+#[test]
+fn test_run_wasm_let_and_var() {
+    // let a = 1 in a
+    assert_eq!(run_expr(mk_let((), "a", int((), 1), var((), "a"))), 1);
+
+    // let a = 1 in let b = 2 in a
+    assert_eq!(
+        run_expr(mk_let(
+            (),
+            "a",
+            int((), 1),
+            mk_let((), "b", int((), 2), var((), "a"))
+        )),
+        1
+    );
+}
+
+#[test]
+fn test_run_wasm_let_shadowing() {
+    // let a = 1 in let a = 2 in a
+    assert_eq!(
+        run_expr(mk_let(
+            (),
+            "a",
+            int((), 1),
+            mk_let((), "a", int((), 2), var((), "a"))
+        )),
+        2
+    );
+
+    // let a = 1 in let a = a in a
+    assert_eq!(
+        run_expr(mk_let(
+            (),
+            "a",
+            int((), 1),
+            mk_let((), "a", var((), "a"), var((), "a"))
+        )),
+        1
+    );
+}
+
+#[test]
+fn test_run_wasm_let_nested_scopes() {
+    // let a = 1 in let b = (let a = 2 in a) in a
+    assert_eq!(
+        run_expr(mk_let(
+            (),
+            "a",
+            int((), 1),
+            mk_let(
+                (),
+                "b",
+                mk_let((), "a", int((), 2), var((), "a")),
+                var((), "a")
+            ),
+        )),
+        1
+    );
+
+    // let a = 1 in if True then (let a = 2 in a) else a
+    assert_eq!(
+        run_expr(mk_let(
+            (),
+            "a",
+            int((), 1),
+            mk_if(
+                (),
+                bool((), true),
+                mk_let((), "a", int((), 2), var((), "a")),
+                var((), "a"),
+            ),
+        )),
+        2
+    );
+}
+
+#[test]
+fn test_run_wasm_if_only_runs_taken_branch() {
+    // compile `if pred then x else y` then replace any `-1` with a trap,
+    // which would fire if both branches were evaluated
+    let run_with_trap = |pred: bool, then_value: i32, else_value: i32| {
+        let expr = elaborate_expr(mk_if(
+            (),
+            bool((), pred),
+            int((), then_value),
+            int((), else_value),
+        ))
+        .unwrap();
+
+        let mut module = ModuleBuilder::new(CompileOptions::default());
+        let mut builder = FunctionBuilder::new(0);
+        expr_to_instructions(&mut module, &mut builder, expr);
+
+        for instruction in builder.instructions.iter_mut() {
+            if matches!(instruction, Instruction::I32Const(-1)) {
+                *instruction = Instruction::Unreachable;
+            }
+        }
+
+        let main_index = module.reserve_function();
+        let main_type = module.function_type(vec![], vec![ValType::I32]);
+        module.define_function(main_index, main_type, builder.finish());
+        module.export("main".to_string(), main_index);
+
+        super::run_wasm::run_wasm_from_ast(module.finish())
+    };
+
+    assert_eq!(run_with_trap(true, 42, -1).unwrap(), 42);
+    assert_eq!(run_with_trap(false, -1, 42).unwrap(), 42);
+
+    // and check the trap does fire when we take that branch
+    assert!(run_with_trap(true, -1, 42).is_err());
+}
+
+#[test]
+fn test_run_wasm_matches_interpreter() {
+    use crate::interpret::interpreter::interpret_expr;
+    use crate::types::value::Value;
+
+    let programs = vec![
+        ("1 + 2 * 3", Some(7)),
+        ("(1 + 2) * 3", Some(9)),
+        ("10 - 2 - 3", Some(5)),
+        ("-7 / 2", Some(-3)),
+        ("-7 % 2", Some(-1)),
+        ("2147483647 + 1", Some(i32::MIN)),
+        ("1 / 0", None),
+        ("1 % 0", None),
+        ("-2147483648 / -1", None),
+        ("-2147483648 % -1", Some(0)),
+        ("1 < 2 && 2 <= 2", Some(1)),
+        ("1 > 2 || 3 >= 4", Some(0)),
+        ("True == False", Some(0)),
+        ("1 != 2", Some(1)),
+        ("False && 1 / 0 == 1", Some(0)),
+        ("True || 1 / 0 == 1", Some(1)),
+        ("True && 1 / 0 == 1", None),
+        (
+            "let a = 10 in if a % 2 == 0 then a / 2 else a * 3 + 1",
+            Some(5),
+        ),
+        ("let x = 3 in -x", Some(-3)),
+        ("-(1 + 2) * 2", Some(-6)),
+        ("let x = -2147483648 in -x", Some(i32::MIN)),
+    ];
+
+    for (source, expected) in programs {
+        let (_, parsed) =
+            crate::parser::parse_expr::parse_my_expr(crate::parser::span::ParseInput::new(source))
+                .finish()
+                .unwrap();
+        let typed = elaborate_expr(parsed).unwrap();
+
+        let interpreted = interpret_expr(typed.clone()).ok().map(|value| match value {
+            Value::VInt { int } => int,
+            Value::VBool { bool } => i32::from(bool),
+            other => panic!("expected an int or bool, got {:?}", other),
+        });
+        let compiled = super::run_wasm::run_wasm_from_ast(expr_to_wasm(typed)).ok();
+
+        assert_eq!(interpreted, expected, "interpreting {}", source);
+        assert_eq!(compiled, expected, "compiling {}", source);
+    }
+}
+
+// typecheck, compile and run a program, checking the interpreter agrees
+#[cfg(test)]
+fn run_source(source: &str) -> i32 {
+    let (_, parsed) =
+        crate::parser::parse_expr::parse_my_expr(crate::parser::span::ParseInput::new(source))
+            .finish()
+            .unwrap();
+    let typed = elaborate_expr(parsed).unwrap();
+
+    let wasm = expr_to_wasm(typed.clone());
+    let compiled = super::run_wasm::run_wasm_from_ast(wasm).unwrap();
+
+    match crate::interpret::interpreter::interpret_expr(typed) {
+        Ok(crate::types::value::Value::VInt { int }) => assert_eq!(compiled, int),
+        other => panic!("interpreter returned {:?}", other),
+    }
+
+    compiled
+}
+
+#[test]
+fn test_run_wasm_lambdas() {
+    assert_eq!(run_source("(\\a -> a + 1) 41"), 42);
+    assert_eq!(run_source("let add = \\a -> \\b -> a + b in add 1 2"), 3);
+    assert_eq!(
+        run_source("let a = 1 in let b = 2 in (\\c -> a + b + c) 3"),
+        6
+    );
+}
+
+#[test]
+fn test_run_wasm_polymorphic_functions() {
+    assert_eq!(
+        run_source("let id = \\x -> x in if id True then id 1 else 2"),
+        1
+    );
+    assert_eq!(
+        run_source("let const = \\a -> \\b -> a in const 1 True + const 2 (\\x -> x)"),
+        3
+    );
+}
+
+#[test]
+fn test_run_wasm_closures_capture_environment() {
+    // the closure sees the `a` from where it was created
+    assert_eq!(
+        run_source("let a = 1 in let f = \\b -> a + b in let a = 100 in f 10"),
+        11
+    );
+
+    // partially applied functions keep their own copy of the argument
+    assert_eq!(
+        run_source(
+            "let add = \\a -> \\b -> a + b in let addOne = add 1 in let addTen = add 10 in addOne 1 + addTen 1"
+        ),
+        13
+    );
+}
+
+#[test]
+fn test_run_wasm_higher_order_functions() {
+    assert_eq!(
+        run_source("let apply = \\f -> f 10 in let k = 5 in apply (\\a -> a + k)"),
+        15
+    );
+
+    assert_eq!(
+        run_source(
+            "let compose = \\f -> \\g -> \\x -> f (g x) in compose (\\a -> a * 2) (\\a -> a + 1) 10"
+        ),
+        22
+    );
+
+    assert_eq!(
+        run_source("let twice = \\f -> \\x -> f (f x) in twice (twice (\\a -> a * 2)) 1"),
+        16
+    );
+
+    // functions returned from `if`
+    assert_eq!(
+        run_source("(if 1 < 2 then \\a -> a + 1 else \\a -> a - 1) 10"),
+        11
+    );
+}
+
+#[test]
+fn test_run_wasm_module() {
+    use super::run_wasm::run_wasm_function;
+    use crate::parser::parse_module::parse_my_module;
+    use crate::parser::span::ParseInput;
+    use crate::typecheck::elaborate::elaborate_module;
+
+    let (_, parsed) = parse_my_module(ParseInput::new(
+        "def main = if isEven 10 then add one 41 else 0\n\
+         def isEven = \\n -> if n == 0 then True else isOdd (n - 1)\n\
+         def isOdd = \\n -> if n == 0 then False else isEven (n - 1)\n\
+         def add : Int -> Int -> Int = \\a -> \\b -> a + b\n\
+         def one = let isEven = 1 in isEven\n\
+         def id = \\x -> x\n\
+         def addOne = add (id one)",
+    ))
+    .finish()
+    .unwrap();
+    let wasm = module_to_wasm(elaborate_module(parsed).unwrap());
+
+    let run = |name, args| run_wasm_function(wasm.clone(), name, args).unwrap();
+
+    // every definition is exported, and functions take their arguments
+    // directly
+    assert_eq!(run("main", vec![]), 42);
+    assert_eq!(run("one", vec![]), 1);
+    assert_eq!(run("isEven", vec![7]), 0);
+    assert_eq!(run("isOdd", vec![7]), 1);
+    assert_eq!(run("add", vec![20, 22]), 42);
+    assert_eq!(run("id", vec![5]), 5);
+    assert_eq!(run("addOne", vec![9]), 10);
+}
+
+#[test]
+fn test_run_wasm_strings() {
+    use super::run_wasm::run_wasm_string;
+
+    let run_string = |source| {
+        let (_, parsed) =
+            crate::parser::parse_expr::parse_my_expr(crate::parser::span::ParseInput::new(source))
+                .finish()
+                .unwrap();
+        let typed = elaborate_expr(parsed).unwrap();
+
+        let compiled = run_wasm_string(expr_to_wasm(typed.clone()), "main", vec![]).unwrap();
+
+        // check the interpreter agrees
+        match crate::interpret::interpreter::interpret_expr(typed) {
+            Ok(crate::types::value::Value::VString { string }) => assert_eq!(compiled, string),
+            other => panic!("interpreter returned {:?}", other),
+        }
+
+        compiled
+    };
+
+    assert_eq!(run_string("\"horse\""), "horse");
+    assert_eq!(run_string("\"\""), "");
+    assert_eq!(run_string("\"a\\tb\\n\\\"c\\\"\""), "a\tb\n\"c\"");
+    assert_eq!(run_string("\"horse\" ++ \" \" ++ \"shoe\""), "horse shoe");
+    assert_eq!(
+        run_string("let twice = \\s -> s ++ s in twice (twice \"ab\") ++ \"é\""),
+        "ababababé"
+    );
+
+    assert_eq!(run_source("length \"horse\""), 5);
+    assert_eq!(run_source("length (\"horse\" ++ \"é\")"), 7);
+    assert_eq!(run_source("let f = length in f \"\" + f \"abc\""), 3);
+
+    // strings are compared by value, not by where they live
+    assert_eq!(run_source("if \"ab\" == \"a\" ++ \"b\" then 1 else 0"), 1);
+    assert_eq!(run_source("if \"ab\" != \"a\" ++ \"c\" then 1 else 0"), 1);
+    assert_eq!(run_source("if \"ab\" == \"abc\" then 1 else 0"), 0);
+    // even inside a function, once we know they're strings
+    assert_eq!(
+        run_source(
+            "let eq = \\a -> \\b -> (a : String) == b in if eq \"x\" (\"\" ++ \"x\") then 1 else 0"
+        ),
+        1
+    );
+    // without knowing, wasm would compare pointers, so it's a type error
+    let (_, parsed) = crate::parser::parse_expr::parse_my_expr(
+        crate::parser::span::ParseInput::new("let eq = \\a -> \\b -> a == b in eq \"x\" \"x\""),
+    )
+    .finish()
+    .unwrap();
+    assert!(matches!(
+        elaborate_expr(parsed),
+        Err(crate::types::typeerror::TypeError::NotComparable { .. })
+    ));
+}
+
+#[test]
+fn test_run_wasm_data() {
+    use super::run_wasm::run_wasm_function;
+    use crate::interpret::interpreter::interpret_module;
+    use crate::parser::parse_module::parse_my_module;
+    use crate::parser::span::ParseInput;
+    use crate::typecheck::elaborate::elaborate_module;
+    use crate::types::value::Value;
+
+    let (_, parsed) = parse_my_module(ParseInput::new(
+        "data Maybe a = Just a | Nothing\n\
+         data Pair a b = Pair a b\n\
+         def withDefault = \\default -> \\maybe -> case maybe of Just a -> a | Nothing -> default\n\
+         def swap = \\pair -> case pair of Pair a b -> Pair b a\n\
+         def first = \\pair -> case pair of Pair a b -> a\n\
+         def just = withDefault 0 (Just 42)\n\
+         def nothing = withDefault 7 Nothing\n\
+         def swapped = first (swap (Pair True 2))\n\
+         def nested = case Just (Pair 1 (Just 2)) of Just (Pair a (Just b)) -> a + b | Just (Pair a Nothing) -> a | Nothing -> 0\n\
+         def captured = case Just 10 of Just a -> (\\b -> a + b) 5 | Nothing -> 0",
+    ))
+    .finish()
+    .unwrap();
+    let typed = elaborate_module(parsed).unwrap();
+    let wasm = module_to_wasm(typed.clone());
+
+    for (name, expected) in [
+        ("just", 42),
+        ("nothing", 7),
+        ("swapped", 2),
+        ("nested", 3),
+        ("captured", 15),
+    ] {
+        assert_eq!(
+            run_wasm_function(wasm.clone(), name, vec![]).unwrap(),
+            expected
+        );
+        // check the interpreter agrees
+        assert_eq!(
+            interpret_module(typed.clone(), name),
+            Ok(Value::VInt { int: expected })
+        );
+    }
+}
+
+#[test]
+fn test_run_wasm_literal_patterns() {
+    assert_eq!(
+        run_source("let f = \\n -> case n of 0 -> 10 | 1 -> 20 | n -> n in f 0 + f 1 + f 5"),
+        35
+    );
+    assert_eq!(
+        run_source("let f = \\b -> case b of True -> 1 | False -> 2 in f False"),
+        2
+    );
+    assert_eq!(
+        run_source(
+            "let f = \\s -> case s of \"horse\" -> 1 | _ -> 2 in f (\"hor\" ++ \"se\") + f \"cow\""
+        ),
+        3
+    );
+}
+
+#[test]
+fn test_run_wasm_records() {
+    use super::run_wasm::run_wasm_function;
+    use crate::interpret::interpreter::interpret_module;
+    use crate::parser::parse_module::parse_my_module;
+    use crate::parser::span::ParseInput;
+    use crate::typecheck::elaborate::elaborate_module;
+    use crate::types::value::Value;
+
+    assert_eq!(run_source("{ a: 1, b: 2 }.b"), 2);
+    assert_eq!(run_source("({ a: 1, b: 2 } : { b: Int }).b"), 2);
+    assert_eq!(
+        run_source("let r = { a: 1, b: { c: 3 } } in r.a + r.b.c"),
+        4
+    );
+    assert_eq!(
+        run_source(
+            "let r = { a: 1, b: 2 } in let s = { r | b: 20, a: 10 } in r.a + r.b + s.a + s.b"
+        ),
+        33
+    );
+
+    // records with extra fields, in any order, work where fewer are expected
+    let (_, parsed) = parse_my_module(ParseInput::new(
+        "def getName : { name: Int } -> Int = \\r -> r.name\n\
+         def bump : { name: Int, ok: Bool } -> Int = \\r -> getName { r | name: r.name + 1 }\n\
+         def main = getName { ok: True, name: 40 } + bump { name: 1, ok: False }",
+    ))
+    .finish()
+    .unwrap();
+    let typed = elaborate_module(parsed).unwrap();
+    let wasm = module_to_wasm(typed.clone());
+
+    assert_eq!(run_wasm_function(wasm, "main", vec![]).unwrap(), 42);
+    assert_eq!(interpret_module(typed, "main"), Ok(Value::VInt { int: 42 }));
+}
+
+#[test]
+fn test_run_wasm_tuples() {
+    use super::run_wasm::{run_wasm_function, run_wasm_value, HostValue};
+    use crate::parser::parse_module::parse_my_module;
+    use crate::parser::span::ParseInput;
+    use crate::typecheck::elaborate::elaborate_module;
+
+    assert_eq!(
+        run_source("let swap = \\t -> case t of (a, b) -> (b, a) in case swap (1, 2) of (a, b) -> a * 10 + b"),
+        21
+    );
+    assert_eq!(
+        run_source("case (1, (2, 3)) of (a, (b, c)) -> a + b + c"),
+        6
+    );
+
+    // a tuple `main` returns each item as its own result
+    let run_expr_value = |source| {
+        let (_, parsed) = crate::parser::parse_expr::parse_my_expr(ParseInput::new(source))
+            .finish()
+            .unwrap();
+        let wasm = expr_to_wasm(elaborate_expr(parsed).unwrap());
+        run_wasm_value(wasm, "main", vec![]).unwrap()
+    };
+    assert_eq!(
+        run_expr_value("(1 + 1, True, 3)"),
+        HostValue::Tuple(vec![
+            HostValue::I32(2),
+            HostValue::I32(1),
+            HostValue::I32(3)
+        ])
+    );
+    assert_eq!(
+        run_expr_value("let t = (4, 5) in t"),
+        HostValue::Tuple(vec![HostValue::I32(4), HostValue::I32(5)])
+    );
+    assert_eq!(run_expr_value("42"), HostValue::I32(42));
+
+    // as do exported definitions, including functions
+    let (_, parsed) = parse_my_module(ParseInput::new(
+        "def pair = (1, 2)\n\
+         def dup = \\a -> (a, a + 1)\n\
+         def big = (1, 2, 3, 4, 5)",
+    ))
+    .finish()
+    .unwrap();
+    let wasm = module_to_wasm(elaborate_module(parsed).unwrap());
+
+    assert_eq!(
+        run_wasm_value(wasm.clone(), "pair", vec![]).unwrap(),
+        HostValue::Tuple(vec![HostValue::I32(1), HostValue::I32(2)])
+    );
+    assert_eq!(
+        run_wasm_value(wasm.clone(), "dup", vec![7]).unwrap(),
+        HostValue::Tuple(vec![HostValue::I32(7), HostValue::I32(8)])
+    );
+    // too big to return on the stack, so we get a pointer
+    assert!(matches!(
+        run_wasm_value(wasm, "big", vec![]).unwrap(),
+        HostValue::I32(_)
+    ));
+
+    // as are tuples holding anything the host would have to read out of
+    // memory, rather than a mix of items and pointers
+    assert!(matches!(run_expr_value("(1, (2, 3))"), HostValue::I32(_)));
+    assert!(matches!(
+        run_expr_value("(1, \"horse\")"),
+        HostValue::I32(_)
+    ));
+    let (_, parsed) = parse_my_module(ParseInput::new(
+        "def nested = \\a -> (a, (a, a))\ndef main = case nested 1 of (a, (b, c)) -> a + b + c",
+    ))
+    .finish()
+    .unwrap();
+    let wasm = module_to_wasm(elaborate_module(parsed).unwrap());
+    assert!(matches!(
+        run_wasm_value(wasm.clone(), "nested", vec![1]).unwrap(),
+        HostValue::I32(_)
+    ));
+    assert_eq!(run_wasm_function(wasm, "main", vec![]).unwrap(), 3);
+}
+
+#[test]
+fn test_run_wasm_let_rec() {
+    assert_eq!(
+        run_source("let rec fact = \\n -> if n == 0 then 1 else n * fact (n - 1) in fact 10"),
+        3628800
+    );
+    assert_eq!(
+        run_source("let rec fib = \\n -> if n < 2 then n else fib (n - 1) + fib (n - 2) in fib 15"),
+        610
+    );
+
+    // calls to itself in tail position are loops, so these don't run out of
+    // stack in either version
+    assert_eq!(
+        run_source("let rec count = \\n -> if n == 0 then 0 else count (n - 1) in count 100000"),
+        0
+    );
+    assert_eq!(
+        run_source(
+            "let rec sum = \\t -> case t of (0, total) -> total | (n, total) -> sum (n - 1, total + n % 3) \
+             in sum (100000, 0)"
+        ),
+        100000
+    );
+
+    // a shadowed name isn't a call to itself
+    assert_eq!(
+        run_source("let rec f = \\n -> if n == 0 then 0 else (let f = \\x -> 100 in f n) in f 5"),
+        100
+    );
+    // recursive functions capture like any other, and are values too
+    assert_eq!(
+        run_source(
+            "let k = 3 in let rec f = \\n -> if n == 0 then k else f (n - 1) in let g = f in g 5"
+        ),
+        3
+    );
+    assert_eq!(
+        run_source(
+            "let rec add = \\n -> \\m -> if n == 0 then m else add (n - 1) (m + 1) in add 10 5"
+        ),
+        15
+    );
+
+    // as are calls that pass every argument of a curried function
+    assert_eq!(
+        run_source(
+            "let rec f = \\n -> \\acc -> if n == 0 then acc else f (n - 1) (acc + 1) in f 1000000 0"
+        ),
+        1000000
+    );
+    // the arguments are all evaluated before any parameter changes
+    assert_eq!(
+        run_source(
+            "let rec gcd = \\a -> \\b -> if b == 0 then a else gcd b (a % b) in gcd 1071 462"
+        ),
+        21
+    );
+    // parameters the body doesn't use, or that are shadowed, still loop
+    assert_eq!(
+        run_source(
+            "let rec f = \\unused -> \\n -> \\n -> if n == 0 then 7 else f 1 2 (n - 1) in f 0 0 100000"
+        ),
+        7
+    );
+    // but a call with fewer arguments returns a closure as usual
+    assert_eq!(
+        run_source(
+            "let rec f = \\n -> \\m -> if n == 0 then m else (let g = f (n - 1) in g (m + 1)) in f 10 5"
+        ),
+        15
+    );
+    // and a parameter with the function's name shadows it
+    assert_eq!(run_source("let rec f = \\n -> \\f -> f + n in f 1 2"), 3);
+}
+
+#[test]
+fn test_run_wasm_top_level_self_calls() {
+    use super::run_wasm::run_wasm_function;
+    use crate::interpret::interpreter::interpret_module;
+    use crate::parser::parse_module::parse_my_module;
+    use crate::parser::span::ParseInput;
+    use crate::typecheck::elaborate::elaborate_module;
+    use crate::types::value::Value;
+
+    // top level functions calling themselves in tail position loop too
+    let (_, parsed) = parse_my_module(ParseInput::new(
+        "def count = \\n -> \\acc -> if n == 0 then acc else count (n - 1) (acc + 1)\n\
+         def main = count 100000 0",
+    ))
+    .finish()
+    .unwrap();
+    let typed = elaborate_module(parsed).unwrap();
+    let wasm = module_to_wasm(typed.clone());
+
+    assert_eq!(
+        run_wasm_function(wasm.clone(), "count", vec![1000000, 0]).unwrap(),
+        1000000
+    );
+    assert_eq!(run_wasm_function(wasm, "main", vec![]).unwrap(), 100000);
+    assert_eq!(
+        interpret_module(typed, "main"),
+        Ok(Value::VInt { int: 100000 })
+    );
+}
+
+#[test]
+fn test_tail_calls() {
+    use super::run_wasm::run_wasm_function;
+    use crate::parser::parse_module::parse_my_module;
+    use crate::parser::span::ParseInput;
+    use crate::typecheck::elaborate::elaborate_module;
+    use wasmparser::{Validator, WasmFeatures};
+
+    // the wasmtime we run these tests with can't run tail calls, so modules
+    // that use them are only checked to be valid for engines that can
+    assert!(!CompileOptions::for_engine(&wasmtime::Engine::default()).tail_calls);
+
+    // calls between mutually recursive definitions aren't loops, so they
+    // need the tail call proposal to run in constant stack
+    let (_, parsed) = parse_my_module(ParseInput::new(
+        "def isEven = \\n -> if n == 0 then True else isOdd (n - 1)\n\
+         def isOdd = \\n -> if n == 0 then False else isEven (n - 1)",
+    ))
+    .finish()
+    .unwrap();
+    let typed = elaborate_module(parsed).unwrap();
+    let with_tail_calls =
+        module_to_wasm_with_options(typed.clone(), CompileOptions { tail_calls: true });
+    let without_tail_calls = module_to_wasm(typed);
+
+    let validate = |wasm: &[u8], tail_call| {
+        Validator::new_with_features(WasmFeatures {
+            tail_call,
+            ..WasmFeatures::default()
+        })
+        .validate_all(wasm)
+        .is_ok()
+    };
+    assert!(validate(&with_tail_calls, true));
+    assert!(!validate(&with_tail_calls, false));
+    assert!(validate(&without_tail_calls, false));
+
+    assert_eq!(
+        run_wasm_function(without_tail_calls, "isEven", vec![10]).unwrap(),
+        1
+    );
+}
@@ -0,0 +1,146 @@
+use crate::types::expr::{pattern_variables, Expr};
+
+// variables used in `expr` that aren't bound inside it, in the order they
+// first appear
+pub fn free_variables<Ann>(expr: &Expr<Ann>) -> Vec<String> {
+    let mut found = vec![];
+    collect_free_variables(expr, &mut vec![], &mut found);
+    found
+}
+
+fn collect_free_variables<Ann>(expr: &Expr<Ann>, bound: &mut Vec<String>, found: &mut Vec<String>) {
+    match expr {
+        Expr::EPrim { .. } => {}
+        Expr::EVar { identifier, .. } => {
+            if !bound.contains(identifier) && !found.contains(identifier) {
+                found.push(identifier.clone())
+            }
+        }
+        Expr::EIf {
+            pred_expr,
+            then_expr,
+            else_expr,
+            ..
+        } => {
+            collect_free_variables(pred_expr, bound, found);
+            collect_free_variables(then_expr, bound, found);
+            collect_free_variables(else_expr, bound, found);
+        }
+        Expr::ELet {
+            identifier,
+            bound_expr,
+            rest_expr,
+            ..
+        } => {
+            collect_free_variables(bound_expr, bound, found);
+            bound.push(identifier.clone());
+            collect_free_variables(rest_expr, bound, found);
+            bound.pop();
+        }
+        // the function can see itself, as well as the rest
+        Expr::ELetRec {
+            identifier,
+            bound_expr,
+            rest_expr,
+            ..
+        } => {
+            bound.push(identifier.clone());
+            collect_free_variables(bound_expr, bound, found);
+            collect_free_variables(rest_expr, bound, found);
+            bound.pop();
+        }
+        Expr::EBinOp {
+            left_expr,
+            right_expr,
+            ..
+        } => {
+            collect_free_variables(left_expr, bound, found);
+            collect_free_variables(right_expr, bound, found);
+        }
+        Expr::ELambda {
+            identifier,
+            body_expr,
+            ..
+        } => {
+            bound.push(identifier.clone());
+            collect_free_variables(body_expr, bound, found);
+            bound.pop();
+        }
+        Expr::EApply {
+            function_expr,
+            argument_expr,
+            ..
+        } => {
+            collect_free_variables(function_expr, bound, found);
+            collect_free_variables(argument_expr, bound, found);
+        }
+        Expr::EConstructor { args, .. } => {
+            for arg in args {
+                collect_free_variables(arg, bound, found);
+            }
+        }
+        Expr::ECase {
+            scrutinee_expr,
+            arms,
+            ..
+        } => {
+            collect_free_variables(scrutinee_expr, bound, found);
+            for arm in arms {
+                let pattern_bound = pattern_variables(&arm.pattern);
+                let count = pattern_bound.len();
+                bound.extend(pattern_bound);
+                collect_free_variables(&arm.body_expr, bound, found);
+                bound.truncate(bound.len() - count);
+            }
+        }
+        Expr::ETuple { items, .. } => {
+            for item in items {
+                collect_free_variables(item, bound, found);
+            }
+        }
+        Expr::ERecord { fields, .. } => {
+            for (_, field_expr) in fields {
+                collect_free_variables(field_expr, bound, found);
+            }
+        }
+        Expr::EFieldAccess { record_expr, .. } => {
+            collect_free_variables(record_expr, bound, found);
+        }
+        Expr::ERecordUpdate {
+            record_expr,
+            fields,
+            ..
+        } => {
+            collect_free_variables(record_expr, bound, found);
+            for (_, field_expr) in fields {
+                collect_free_variables(field_expr, bound, found);
+            }
+        }
+        Expr::EAnnotation { expr, .. } => collect_free_variables(expr, bound, found),
+    }
+}
+
+#[test]
+fn test_free_variables() {
+    use crate::parser::parse_expr::parse_my_expr;
+    use crate::parser::span::ParseInput;
+
+    let free = |input| {
+        let (_, expr) = parse_my_expr(ParseInput::new(input)).unwrap();
+        free_variables(&expr)
+    };
+
+    assert_eq!(free("1"), Vec::<String>::new());
+    assert_eq!(free("a + b + a"), vec!["a", "b"]);
+    assert_eq!(free("\\a -> a + b"), vec!["b"]);
+    assert_eq!(free("let a = a in a + b"), vec!["a", "b"]);
+    assert_eq!(free("let a = 1 in \\b -> a + b + c"), vec!["c"]);
+    assert_eq!(free("(\\a -> a) a"), vec!["a"]);
+    assert_eq!(free("let rec f = \\a -> f a + b in f c"), vec!["b", "c"]);
+    assert_eq!(free("{ r | a: b.c }.d"), vec!["r", "b"]);
+    // pattern variables are only bound in their own arm
+    assert_eq!(
+        free("case m of Just a -> a + b | Nothing -> a"),
+        vec!["m", "b", "a"]
+    );
+}
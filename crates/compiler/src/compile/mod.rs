@@ -1,2 +1,3 @@
 pub mod expr_to_wasm;
+pub mod free_variables;
 pub mod run_wasm;
@@ -1,10 +1,36 @@
+#[cfg(test)]
 use anyhow::Result;
+#[cfg(test)]
 use wasmtime::*;
 
-// all wasm we pass in must take no args and return an `i32`
-// we run the `main` function
+// what an exported function gave back, decoded from its wasm results
+#[cfg(test)]
+#[derive(Debug, PartialEq, Clone)]
+pub enum HostValue {
+    I32(i32),
+    // a function with more than one result, ie a small tuple of ints and bools
+    Tuple(Vec<HostValue>),
+}
+
+// we run the `main` function, which must take no args and return an `i32`
 #[cfg(test)]
 pub fn run_wasm_from_ast(wasm_bytes: Vec<u8>) -> Result<i32> {
+    run_wasm_function(wasm_bytes, "main", vec![])
+}
+
+// run the exported function `name` with `args`, it must return an `i32`
+#[cfg(test)]
+pub fn run_wasm_function(wasm_bytes: Vec<u8>, name: &str, args: Vec<i32>) -> Result<i32> {
+    match run_wasm_value(wasm_bytes, name, args)? {
+        HostValue::I32(int) => Ok(int),
+        other => anyhow::bail!("`{}` returned {:?} rather than an i32", name, other),
+    }
+}
+
+// run the exported function `name` with `args`, decoding however many
+// results its type says it has
+#[cfg(test)]
+pub fn run_wasm_value(wasm_bytes: Vec<u8>, name: &str, args: Vec<i32>) -> Result<HostValue> {
     // Modules can be compiled through either the text or binary format
     let engine = Engine::default();
     let module = Module::new(&engine, wasm_bytes)?;
@@ -18,8 +44,59 @@ pub fn run_wasm_from_ast(wasm_bytes: Vec<u8>) -> Result<i32> {
     // this case we're using `4` for.
     let mut store = Store::new(&engine, 4);
     let instance = linker.instantiate(&mut store, &module)?;
-    let main_fn = instance.get_typed_func::<(), i32>(&mut store, "main")?;
+
+    let function = instance
+        .get_func(&mut store, name)
+        .ok_or_else(|| anyhow::anyhow!("no function called `{}`", name))?;
+    let params: Vec<Val> = args.into_iter().map(Val::I32).collect();
+    let mut results = vec![Val::I32(0); function.ty(&store).results().len()];
 
     // And finally we can call the wasm!
-    main_fn.call(&mut store, ())
+    function.call(&mut store, &params, &mut results)?;
+
+    let mut values = results
+        .into_iter()
+        .map(|result| {
+            result
+                .i32()
+                .map(HostValue::I32)
+                .ok_or_else(|| anyhow::anyhow!("`{}` returned a non-i32 result", name))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    match values.len() {
+        1 => Ok(values.remove(0)),
+        _ => Ok(HostValue::Tuple(values)),
+    }
+}
+
+// run the exported function `name` with `args`, it must return a string as
+// a pointer to its bytes and their length, which we copy out of memory
+#[cfg(test)]
+pub fn run_wasm_string(wasm_bytes: Vec<u8>, name: &str, args: Vec<i32>) -> Result<String> {
+    let engine = Engine::default();
+    let module = Module::new(&engine, wasm_bytes)?;
+    let linker = Linker::new(&engine);
+    let mut store = Store::new(&engine, 4);
+    let instance = linker.instantiate(&mut store, &module)?;
+
+    let function = instance
+        .get_func(&mut store, name)
+        .ok_or_else(|| anyhow::anyhow!("no function called `{}`", name))?;
+    let params: Vec<Val> = args.into_iter().map(Val::I32).collect();
+    let mut results = [Val::I32(0), Val::I32(0)];
+    function.call(&mut store, &params, &mut results)?;
+
+    let (pointer, length) = match results {
+        [Val::I32(pointer), Val::I32(length)] => (pointer as usize, length as usize),
+        _ => anyhow::bail!("`{}` didn't return a string", name),
+    };
+
+    let memory = instance
+        .get_memory(&mut store, "memory")
+        .ok_or_else(|| anyhow::anyhow!("no memory exported"))?;
+    let mut bytes = vec![0; length];
+    memory.read(&store, pointer, &mut bytes)?;
+
+    Ok(String::from_utf8(bytes)?)
 }
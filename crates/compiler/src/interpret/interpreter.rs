@@ -1,45 +1,569 @@
-use crate::parser::parse_constructors::{bool, int};
-use crate::types::expr::{Expr, Prim};
+#[cfg(test)]
+use crate::parser::parse_constructors::{
+    bool, int, mk_apply, mk_bin_op, mk_if, mk_lambda, mk_let, var,
+};
+use crate::types::builtin::{lookup_builtin, Builtin};
+use crate::types::expr::{Expr, Op, Pattern, Prim};
+use crate::types::module::Module;
+use crate::types::runtimeerror::RuntimeError;
+use crate::types::value::Value;
 
-pub fn interpret_expr<Ann>(expr: Expr<Ann>) -> Expr<Ann> {
-    match expr {
-        Expr::EPrim { ann, prim } => Expr::EPrim { ann, prim },
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+// values of the variables in scope
+// we clone this when adding a binding, so each expression only sees the
+// variables bound above it
+type Env<Ann> = HashMap<String, Value<Ann>>;
+
+// the top level definitions of a module
+// these can refer to each other in any order, so rather than evaluating them
+// up front we evaluate a definition each time it's used
+type Globals<Ann> = HashMap<String, Expr<Ann>>;
+
+// entry point, evaluate an expression with nothing in scope
+pub fn interpret_expr<Ann>(expr: Expr<Ann>) -> Result<Value<Ann>, RuntimeError<Ann>>
+where
+    Ann: Clone,
+{
+    interpret(&Globals::new(), &Env::new(), &expr)
+}
+
+// evaluate the definition called `identifier` with the rest of `module` in
+// scope
+pub fn interpret_module<Ann>(
+    module: Module<Ann>,
+    identifier: &str,
+) -> Result<Value<Ann>, RuntimeError<Ann>>
+where
+    Ann: Clone,
+{
+    let globals: Globals<Ann> = module
+        .defs
+        .into_iter()
+        .map(|def| (def.identifier, def.expr))
+        .collect();
+
+    match globals.get(identifier) {
+        Some(expr) => interpret(&globals, &Env::new(), expr),
+        None => Err(RuntimeError::UnknownDefinition {
+            identifier: identifier.to_string(),
+        }),
+    }
+}
+
+// what's left to do after one step of evaluation
+enum Step<'a, Ann>
+where
+    Ann: Clone,
+{
+    // we have the value
+    Done(Value<Ann>),
+    // the value is whatever `expr` evaluates to, in `env` if we have a new
+    // one or the current one otherwise
+    Continue {
+        env: Option<Env<Ann>>,
+        expr: Cow<'a, Expr<Ann>>,
+    },
+}
+
+impl<Ann> Step<'_, Ann>
+where
+    Ann: Clone,
+{
+    // stop borrowing the expression we stepped through
+    fn into_owned<'b>(self) -> Step<'b, Ann> {
+        match self {
+            Step::Done(value) => Step::Done(value),
+            Step::Continue { env, expr } => Step::Continue {
+                env,
+                expr: Cow::Owned(expr.into_owned()),
+            },
+        }
+    }
+}
+
+// expressions in tail position replace the one we're evaluating rather than
+// being evaluated recursively, so a function calling itself in tail position
+// runs in constant stack like the compiled `loop` does
+fn interpret<Ann>(
+    globals: &Globals<Ann>,
+    env: &Env<Ann>,
+    expr: &Expr<Ann>,
+) -> Result<Value<Ann>, RuntimeError<Ann>>
+where
+    Ann: Clone,
+{
+    let mut env = Cow::Borrowed(env);
+    let mut expr = Cow::Borrowed(expr);
+
+    loop {
+        let step = match &expr {
+            Cow::Borrowed(expr) => interpret_step(globals, &env, expr)?,
+            // a closure's body, which we own, so anything we continue with
+            // must be copied out of it
+            Cow::Owned(expr) => interpret_step(globals, &env, expr)?.into_owned(),
+        };
+
+        match step {
+            Step::Done(value) => return Ok(value),
+            Step::Continue {
+                env: new_env,
+                expr: next_expr,
+            } => {
+                if let Some(new_env) = new_env {
+                    env = Cow::Owned(new_env);
+                }
+                expr = next_expr;
+            }
+        }
+    }
+}
+
+fn interpret_step<'a, Ann>(
+    globals: &Globals<Ann>,
+    env: &Env<Ann>,
+    expr: &'a Expr<Ann>,
+) -> Result<Step<'a, Ann>, RuntimeError<Ann>>
+where
+    Ann: Clone,
+{
+    let value = match expr {
+        Expr::EPrim { prim, .. } => prim_to_value(prim),
         Expr::EIf {
+            ann,
             pred_expr,
             then_expr,
             else_expr,
+        } => {
+            let next_expr = match interpret(globals, env, pred_expr)? {
+                Value::VBool { bool: true } => then_expr,
+                Value::VBool { bool: false } => else_expr,
+                other => {
+                    return Err(RuntimeError::PredicateShouldBeBool {
+                        ann: ann.clone(),
+                        found: other,
+                    })
+                }
+            };
+            return Ok(Step::Continue {
+                env: None,
+                expr: Cow::Borrowed(next_expr),
+            });
+        }
+        Expr::ELet {
+            identifier,
+            bound_expr,
+            rest_expr,
             ..
         } => {
-            let interpreted_pred = interpret_expr(*pred_expr);
-            match interpreted_pred {
-                Expr::EPrim { prim, .. } => match prim {
-                    Prim::PBool { bool: true, .. } => interpret_expr(*then_expr),
-                    Prim::PBool { bool: false, .. } => interpret_expr(*else_expr),
-                    _ => todo!(),
+            // evaluate the bound expression once, then share the result
+            let bound_value = interpret(globals, env, bound_expr)?;
+            let mut new_env = env.clone();
+            new_env.insert(identifier.clone(), bound_value);
+            return Ok(Step::Continue {
+                env: Some(new_env),
+                expr: Cow::Borrowed(rest_expr),
+            });
+        }
+        // the closure can't contain itself, so it remembers its name and
+        // adds itself to its environment each time it's called
+        Expr::ELetRec {
+            identifier,
+            bound_expr,
+            rest_expr,
+            ..
+        } => {
+            let closure = match interpret(globals, env, bound_expr)? {
+                Value::VClosure {
+                    env: closure_env,
+                    identifier: argument,
+                    body_expr,
+                } => Value::VRecursiveClosure {
+                    env: closure_env,
+                    name: identifier.clone(),
+                    identifier: argument,
+                    body_expr,
                 },
-
-                _other => todo!(),
+                other => other,
+            };
+            let mut new_env = env.clone();
+            new_env.insert(identifier.clone(), closure);
+            return Ok(Step::Continue {
+                env: Some(new_env),
+                expr: Cow::Borrowed(rest_expr),
+            });
+        }
+        // local variables shadow top level definitions, which shadow builtins
+        Expr::EVar { ann, identifier } => match (env.get(identifier), globals.get(identifier)) {
+            (Some(value), _) => value.clone(),
+            (None, Some(def_expr)) => interpret(globals, &Env::new(), def_expr)?,
+            (None, None) => match lookup_builtin(identifier) {
+                Some(builtin) => Value::VBuiltin { builtin },
+                None => {
+                    return Err(RuntimeError::UnboundVariable {
+                        ann: ann.clone(),
+                        identifier: identifier.clone(),
+                    })
+                }
+            },
+        },
+        Expr::EBinOp {
+            ann,
+            op,
+            left_expr,
+            right_expr,
+        } => {
+            let left = interpret(globals, env, left_expr)?;
+            // `&&` and `||` only evaluate the right hand side if they need it
+            match (op, &left) {
+                (Op::And, Value::VBool { bool: false }) => left,
+                (Op::Or, Value::VBool { bool: true }) => left,
+                _ => {
+                    let right = interpret(globals, env, right_expr)?;
+                    interpret_bin_op(ann.clone(), *op, left, right)?
+                }
+            }
+        }
+        // capture the current environment so the body can see the variables
+        // that were in scope where the lambda was written
+        Expr::ELambda {
+            identifier,
+            body_expr,
+            ..
+        } => Value::VClosure {
+            env: env.clone(),
+            identifier: identifier.clone(),
+            body_expr: *body_expr.clone(),
+        },
+        Expr::EApply {
+            ann,
+            function_expr,
+            argument_expr,
+        } => match interpret(globals, env, function_expr)? {
+            Value::VClosure {
+                env: closure_env,
+                identifier,
+                body_expr,
+            } => {
+                let argument = interpret(globals, env, argument_expr)?;
+                let mut new_env = closure_env;
+                new_env.insert(identifier, argument);
+                return Ok(Step::Continue {
+                    env: Some(new_env),
+                    expr: Cow::Owned(body_expr),
+                });
+            }
+            closure @ Value::VRecursiveClosure { .. } => {
+                let argument = interpret(globals, env, argument_expr)?;
+                let (mut new_env, name, identifier, body_expr) = match closure.clone() {
+                    Value::VRecursiveClosure {
+                        env,
+                        name,
+                        identifier,
+                        body_expr,
+                    } => (env, name, identifier, body_expr),
+                    _ => unreachable!("just matched a recursive closure"),
+                };
+                new_env.insert(name, closure);
+                new_env.insert(identifier, argument);
+                return Ok(Step::Continue {
+                    env: Some(new_env),
+                    expr: Cow::Owned(body_expr),
+                });
+            }
+            Value::VBuiltin { builtin } => {
+                let argument = interpret(globals, env, argument_expr)?;
+                interpret_builtin(ann.clone(), builtin, argument)?
+            }
+            other => {
+                return Err(RuntimeError::ApplyingNonFunction {
+                    ann: ann.clone(),
+                    found: other,
+                })
+            }
+        },
+        Expr::EConstructor {
+            constructor, args, ..
+        } => Value::VData {
+            constructor: constructor.clone(),
+            fields: args
+                .iter()
+                .map(|arg| interpret(globals, env, arg))
+                .collect::<Result<_, _>>()?,
+        },
+        // the first arm whose pattern matches wins
+        Expr::ECase {
+            ann,
+            scrutinee_expr,
+            arms,
+        } => {
+            let scrutinee = interpret(globals, env, scrutinee_expr)?;
+            for arm in arms {
+                let mut new_env = env.clone();
+                if match_pattern(&arm.pattern, &scrutinee, &mut new_env) {
+                    return Ok(Step::Continue {
+                        env: Some(new_env),
+                        expr: Cow::Borrowed(&arm.body_expr),
+                    });
+                }
+            }
+            return Err(RuntimeError::NoMatchingPattern {
+                ann: ann.clone(),
+                found: scrutinee,
+            });
+        }
+        Expr::EAnnotation { expr, .. } => {
+            return Ok(Step::Continue {
+                env: None,
+                expr: Cow::Borrowed(expr),
+            })
+        }
+        Expr::ETuple { items, .. } => Value::VTuple {
+            items: items
+                .iter()
+                .map(|item| interpret(globals, env, item))
+                .collect::<Result<Vec<_>, _>>()?,
+        },
+        Expr::ERecord { fields, .. } => {
+            let mut values = fields
+                .iter()
+                .map(|(name, field_expr)| Ok((name.clone(), interpret(globals, env, field_expr)?)))
+                .collect::<Result<Vec<_>, _>>()?;
+            values.sort_by(|(a, _), (b, _)| a.cmp(b));
+            Value::VRecord { fields: values }
+        }
+        Expr::EFieldAccess {
+            ann,
+            record_expr,
+            field,
+        } => {
+            let record = interpret(globals, env, record_expr)?;
+            let found = match &record {
+                Value::VRecord { fields } => fields.iter().find(|(name, _)| name == field),
+                _ => None,
+            };
+            match found {
+                Some((_, value)) => value.clone(),
+                None => {
+                    return Err(RuntimeError::MissingField {
+                        ann: ann.clone(),
+                        field: field.clone(),
+                        found: record.clone(),
+                    })
+                }
             }
         }
-        Expr::ELet { rest_expr, .. } => interpret_expr(*rest_expr),
-        Expr::EVar { .. } => todo!(),
+        Expr::ERecordUpdate {
+            ann,
+            record_expr,
+            fields,
+        } => {
+            let record = interpret(globals, env, record_expr)?;
+            let mut values = match &record {
+                Value::VRecord { fields } => fields.clone(),
+                _ => {
+                    return Err(RuntimeError::MissingField {
+                        ann: ann.clone(),
+                        field: fields
+                            .first()
+                            .map(|(name, _)| name.clone())
+                            .unwrap_or_default(),
+                        found: record,
+                    })
+                }
+            };
+            for (name, field_expr) in fields {
+                let value = interpret(globals, env, field_expr)?;
+                match values.iter_mut().find(|(other, _)| other == name) {
+                    Some((_, old_value)) => *old_value = value,
+                    None => {
+                        return Err(RuntimeError::MissingField {
+                            ann: ann.clone(),
+                            field: name.clone(),
+                            found: record,
+                        })
+                    }
+                }
+            }
+            Value::VRecord { fields: values }
+        }
+    };
+    Ok(Step::Done(value))
+}
+
+// whether `value` matches `pattern`, adding any variables it binds to `env`
+fn match_pattern<Ann>(pattern: &Pattern<Ann>, value: &Value<Ann>, env: &mut Env<Ann>) -> bool
+where
+    Ann: Clone,
+{
+    match (pattern, value) {
+        (Pattern::PVar { identifier, .. }, value) => {
+            env.insert(identifier.clone(), value.clone());
+            true
+        }
+        (Pattern::PWildcard { .. }, _) => true,
+        (Pattern::PLiteral { prim, .. }, value) => match (prim, value) {
+            (Prim::PInt { int: a }, Value::VInt { int: b }) => a == b,
+            (Prim::PBool { bool: a }, Value::VBool { bool: b }) => a == b,
+            (Prim::PString { string: a }, Value::VString { string: b }) => a == b,
+            _ => false,
+        },
+        (Pattern::PTuple { items, .. }, Value::VTuple { items: values }) => {
+            items.len() == values.len()
+                && items
+                    .iter()
+                    .zip(values)
+                    .all(|(item, value)| match_pattern(item, value, env))
+        }
+        (
+            Pattern::PConstructor {
+                constructor, args, ..
+            },
+            Value::VData {
+                constructor: found,
+                fields,
+            },
+        ) => {
+            constructor == found
+                && args.len() == fields.len()
+                && args
+                    .iter()
+                    .zip(fields)
+                    .all(|(arg, field)| match_pattern(arg, field, env))
+        }
+        _ => false,
+    }
+}
+
+fn interpret_builtin<Ann>(
+    ann: Ann,
+    builtin: Builtin,
+    argument: Value<Ann>,
+) -> Result<Value<Ann>, RuntimeError<Ann>>
+where
+    Ann: Clone,
+{
+    match (builtin, argument) {
+        // bytes rather than chars, to match the compiled version
+        (Builtin::Length, Value::VString { string }) => Ok(Value::VInt {
+            int: string.len() as i32,
+        }),
+        (builtin, argument) => Err(RuntimeError::InvalidBuiltinArgument {
+            ann,
+            builtin,
+            argument,
+        }),
+    }
+}
+
+// arithmetic wraps on overflow like wasm's `i32` instructions do
+fn interpret_bin_op<Ann>(
+    ann: Ann,
+    op: Op,
+    left: Value<Ann>,
+    right: Value<Ann>,
+) -> Result<Value<Ann>, RuntimeError<Ann>>
+where
+    Ann: Clone,
+{
+    match (op, &left, &right) {
+        (Op::Add, Value::VInt { int: a }, Value::VInt { int: b }) => Ok(Value::VInt {
+            int: a.wrapping_add(*b),
+        }),
+        (Op::Subtract, Value::VInt { int: a }, Value::VInt { int: b }) => Ok(Value::VInt {
+            int: a.wrapping_sub(*b),
+        }),
+        (Op::Multiply, Value::VInt { int: a }, Value::VInt { int: b }) => Ok(Value::VInt {
+            int: a.wrapping_mul(*b),
+        }),
+        (Op::Divide, Value::VInt { .. }, Value::VInt { int: 0 }) => {
+            Err(RuntimeError::DivisionByZero { ann })
+        }
+        // `i32::MIN / -1` doesn't fit in an `i32`
+        (Op::Divide, Value::VInt { int: a }, Value::VInt { int: b }) => match a.checked_div(*b) {
+            Some(int) => Ok(Value::VInt { int }),
+            None => Err(RuntimeError::IntegerOverflow { ann }),
+        },
+        (Op::Modulo, Value::VInt { .. }, Value::VInt { int: 0 }) => {
+            Err(RuntimeError::DivisionByZero { ann })
+        }
+        // unlike division, `i32::MIN % -1` is fine and gives `0`
+        (Op::Modulo, Value::VInt { int: a }, Value::VInt { int: b }) => Ok(Value::VInt {
+            int: a.wrapping_rem(*b),
+        }),
+        (Op::LessThan, Value::VInt { int: a }, Value::VInt { int: b }) => {
+            Ok(Value::VBool { bool: a < b })
+        }
+        (Op::LessThanOrEqual, Value::VInt { int: a }, Value::VInt { int: b }) => {
+            Ok(Value::VBool { bool: a <= b })
+        }
+        (Op::GreaterThan, Value::VInt { int: a }, Value::VInt { int: b }) => {
+            Ok(Value::VBool { bool: a > b })
+        }
+        (Op::GreaterThanOrEqual, Value::VInt { int: a }, Value::VInt { int: b }) => {
+            Ok(Value::VBool { bool: a >= b })
+        }
+        (Op::And | Op::Or, Value::VBool { .. }, Value::VBool { bool }) => {
+            // the left hand side didn't decide it, so the right hand side does
+            Ok(Value::VBool { bool: *bool })
+        }
+        (Op::Equals, Value::VInt { int: a }, Value::VInt { int: b }) => {
+            Ok(Value::VBool { bool: a == b })
+        }
+        (Op::Equals, Value::VBool { bool: a }, Value::VBool { bool: b }) => {
+            Ok(Value::VBool { bool: a == b })
+        }
+        (Op::NotEquals, Value::VInt { int: a }, Value::VInt { int: b }) => {
+            Ok(Value::VBool { bool: a != b })
+        }
+        (Op::NotEquals, Value::VBool { bool: a }, Value::VBool { bool: b }) => {
+            Ok(Value::VBool { bool: a != b })
+        }
+        (Op::Equals, Value::VString { string: a }, Value::VString { string: b }) => {
+            Ok(Value::VBool { bool: a == b })
+        }
+        (Op::NotEquals, Value::VString { string: a }, Value::VString { string: b }) => {
+            Ok(Value::VBool { bool: a != b })
+        }
+        (Op::Concat, Value::VString { string: a }, Value::VString { string: b }) => {
+            Ok(Value::VString {
+                string: format!("{}{}", a, b),
+            })
+        }
+        _ => Err(RuntimeError::InvalidOperands {
+            ann,
+            op,
+            left,
+            right,
+        }),
+    }
+}
+
+fn prim_to_value<Ann>(prim: &Prim) -> Value<Ann> {
+    match prim {
+        Prim::PInt { int } => Value::VInt { int: *int },
+        Prim::PBool { bool } => Value::VBool { bool: *bool },
+        Prim::PString { string } => Value::VString {
+            string: string.clone(),
+        },
     }
 }
 
 #[test]
 fn test_interpret_if() {
-    let int_one = int(1);
+    let int_one = int((), 1);
 
-    let int_two = int(2);
+    let int_two = int((), 2);
 
     let if_expr = Expr::EIf {
         ann: (),
-        pred_expr: Box::new(bool(true)),
+        pred_expr: Box::new(bool((), true)),
         then_expr: Box::new(int_one.clone()),
         else_expr: Box::new(int_two.clone()),
     };
 
-    assert_eq!(interpret_expr(if_expr), int_one);
+    assert_eq!(interpret_expr(if_expr), Ok(Value::VInt { int: 1 }));
 
     let if_expr_2 = Expr::EIf {
         ann: (),
@@ -51,5 +575,455 @@ fn test_interpret_if() {
         else_expr: Box::new(int_two.clone()),
     };
 
-    assert_eq!(interpret_expr(if_expr_2), int_two);
+    assert_eq!(interpret_expr(if_expr_2), Ok(Value::VInt { int: 2 }));
+
+    let if_with_int_pred = mk_if((), int((), 1), int_one, int_two);
+
+    assert_eq!(
+        interpret_expr(if_with_int_pred),
+        Err(RuntimeError::PredicateShouldBeBool {
+            ann: (),
+            found: Value::VInt { int: 1 }
+        })
+    );
+}
+
+#[test]
+fn test_interpret_let_and_var() {
+    // let a = 1 in a
+    assert_eq!(
+        interpret_expr(mk_let((), "a", int((), 1), var((), "a"))),
+        Ok(Value::VInt { int: 1 })
+    );
+
+    // let a = True in if a then 1 else 2
+    assert_eq!(
+        interpret_expr(mk_let(
+            (),
+            "a",
+            bool((), true),
+            mk_if((), var((), "a"), int((), 1), int((), 2))
+        )),
+        Ok(Value::VInt { int: 1 })
+    );
+
+    // let a = 1 in let a = 2 in a
+    assert_eq!(
+        interpret_expr(mk_let(
+            (),
+            "a",
+            int((), 1),
+            mk_let((), "a", int((), 2), var((), "a"))
+        )),
+        Ok(Value::VInt { int: 2 })
+    );
+
+    // let a = 1 in let b = (let a = 2 in a) in a
+    assert_eq!(
+        interpret_expr(mk_let(
+            (),
+            "a",
+            int((), 1),
+            mk_let(
+                (),
+                "b",
+                mk_let((), "a", int((), 2), var((), "a")),
+                var((), "a")
+            )
+        )),
+        Ok(Value::VInt { int: 1 })
+    );
+
+    // let b = (let a = 2 in a) in a
+    assert_eq!(
+        interpret_expr(mk_let(
+            (),
+            "b",
+            mk_let((), "a", int((), 2), var((), "a")),
+            var((), "a")
+        )),
+        Err(RuntimeError::UnboundVariable {
+            ann: (),
+            identifier: "a".to_string()
+        })
+    );
+}
+
+#[test]
+fn test_interpret_bin_op() {
+    let run = |op, left, right| interpret_expr(mk_bin_op((), op, left, right));
+
+    assert_eq!(
+        run(Op::Add, int((), 1), int((), 2)),
+        Ok(Value::VInt { int: 3 })
+    );
+    assert_eq!(
+        run(Op::Add, int((), i32::MAX), int((), 1)),
+        Ok(Value::VInt { int: i32::MIN })
+    );
+    assert_eq!(
+        run(Op::Modulo, int((), -7), int((), 2)),
+        Ok(Value::VInt { int: -1 })
+    );
+    assert_eq!(
+        run(Op::Divide, int((), -7), int((), 2)),
+        Ok(Value::VInt { int: -3 })
+    );
+    assert_eq!(
+        run(Op::Divide, int((), 1), int((), 0)),
+        Err(RuntimeError::DivisionByZero { ann: () })
+    );
+    assert_eq!(
+        run(Op::Modulo, int((), 1), int((), 0)),
+        Err(RuntimeError::DivisionByZero { ann: () })
+    );
+    assert_eq!(
+        run(Op::Divide, int((), i32::MIN), int((), -1)),
+        Err(RuntimeError::IntegerOverflow { ann: () })
+    );
+    assert_eq!(
+        run(Op::Modulo, int((), i32::MIN), int((), -1)),
+        Ok(Value::VInt { int: 0 })
+    );
+    assert_eq!(
+        run(Op::GreaterThanOrEqual, int((), 2), int((), 2)),
+        Ok(Value::VBool { bool: true })
+    );
+    assert_eq!(
+        run(Op::NotEquals, bool((), true), bool((), false)),
+        Ok(Value::VBool { bool: true })
+    );
+    assert_eq!(
+        run(Op::Equals, int((), 1), bool((), true)),
+        Err(RuntimeError::InvalidOperands {
+            ann: (),
+            op: Op::Equals,
+            left: Value::VInt { int: 1 },
+            right: Value::VBool { bool: true }
+        })
+    );
+}
+
+#[test]
+fn test_interpret_negate() {
+    use crate::parser::parse_expr::parse_my_expr;
+    use crate::parser::span::ParseInput;
+
+    let run = |input| {
+        let (_, expr) = parse_my_expr(ParseInput::new(input)).unwrap();
+        interpret_expr(expr)
+    };
+
+    assert_eq!(run("let x = 3 in -x"), Ok(Value::VInt { int: -3 }));
+    assert_eq!(run("-(1 + 2) * 2"), Ok(Value::VInt { int: -6 }));
+    assert_eq!(run("- -4"), Ok(Value::VInt { int: 4 }));
+}
+
+#[test]
+fn test_interpret_bin_op_short_circuits() {
+    let divide_by_zero = || {
+        mk_bin_op(
+            (),
+            Op::Equals,
+            mk_bin_op((), Op::Divide, int((), 1), int((), 0)),
+            int((), 1),
+        )
+    };
+
+    assert_eq!(
+        interpret_expr(mk_bin_op((), Op::And, bool((), false), divide_by_zero())),
+        Ok(Value::VBool { bool: false })
+    );
+    assert_eq!(
+        interpret_expr(mk_bin_op((), Op::Or, bool((), true), divide_by_zero())),
+        Ok(Value::VBool { bool: true })
+    );
+    assert_eq!(
+        interpret_expr(mk_bin_op((), Op::And, bool((), true), divide_by_zero())),
+        Err(RuntimeError::DivisionByZero { ann: () })
+    );
+}
+
+#[test]
+fn test_interpret_lambda_and_apply() {
+    // (\a -> a + 1) 41
+    assert_eq!(
+        interpret_expr(mk_apply(
+            (),
+            mk_lambda((), "a", mk_bin_op((), Op::Add, var((), "a"), int((), 1))),
+            int((), 41)
+        )),
+        Ok(Value::VInt { int: 42 })
+    );
+
+    // closures capture the environment where they are created, not where
+    // they are called
+    // let a = 1 in let f = \b -> a + b in let a = 100 in f 10
+    assert_eq!(
+        interpret_expr(mk_let(
+            (),
+            "a",
+            int((), 1),
+            mk_let(
+                (),
+                "f",
+                mk_lambda((), "b", mk_bin_op((), Op::Add, var((), "a"), var((), "b"))),
+                mk_let(
+                    (),
+                    "a",
+                    int((), 100),
+                    mk_apply((), var((), "f"), int((), 10))
+                )
+            )
+        )),
+        Ok(Value::VInt { int: 11 })
+    );
+
+    // curried functions
+    // let sub = \a -> \b -> a - b in sub 10 3
+    assert_eq!(
+        interpret_expr(mk_let(
+            (),
+            "sub",
+            mk_lambda(
+                (),
+                "a",
+                mk_lambda(
+                    (),
+                    "b",
+                    mk_bin_op((), Op::Subtract, var((), "a"), var((), "b"))
+                )
+            ),
+            mk_apply((), mk_apply((), var((), "sub"), int((), 10)), int((), 3))
+        )),
+        Ok(Value::VInt { int: 7 })
+    );
+
+    // the argument is evaluated once, before the body
+    // (\a -> 1) (1 / 0)
+    assert_eq!(
+        interpret_expr(mk_apply(
+            (),
+            mk_lambda((), "a", int((), 1)),
+            mk_bin_op((), Op::Divide, int((), 1), int((), 0))
+        )),
+        Err(RuntimeError::DivisionByZero { ann: () })
+    );
+
+    assert_eq!(
+        interpret_expr(mk_apply((), int((), 1), int((), 2))),
+        Err(RuntimeError::ApplyingNonFunction {
+            ann: (),
+            found: Value::VInt { int: 1 }
+        })
+    );
+}
+
+#[test]
+fn test_interpret_module() {
+    use crate::parser::parse_module::parse_my_module;
+    use crate::parser::span::ParseInput;
+
+    let (_, module) = parse_my_module(ParseInput::new(
+        "def main = isEven 10\n\
+         def isEven = \\n -> if n == 0 then True else isOdd (n - 1)\n\
+         def isOdd = \\n -> if n == 0 then False else isEven (n - 1)\n\
+         def shadowed = let isOdd = 1 in isOdd",
+    ))
+    .unwrap();
+
+    assert_eq!(
+        interpret_module(module.clone(), "main"),
+        Ok(Value::VBool { bool: true })
+    );
+    assert_eq!(
+        interpret_module(module.clone(), "shadowed"),
+        Ok(Value::VInt { int: 1 })
+    );
+    assert_eq!(
+        interpret_module(module, "missing"),
+        Err(RuntimeError::UnknownDefinition {
+            identifier: "missing".to_string()
+        })
+    );
+}
+
+#[test]
+fn test_interpret_strings() {
+    use crate::parser::parse_constructors::string;
+
+    // "horse" ++ "\n" ++ "shoe"
+    let concat = mk_bin_op(
+        (),
+        Op::Concat,
+        mk_bin_op((), Op::Concat, string((), "horse"), string((), "\n")),
+        string((), "shoe"),
+    );
+    assert_eq!(
+        interpret_expr(concat.clone()),
+        Ok(Value::VString {
+            string: "horse\nshoe".to_string()
+        })
+    );
+
+    assert_eq!(
+        interpret_expr(mk_apply((), var((), "length"), concat)),
+        Ok(Value::VInt { int: 10 })
+    );
+
+    // length counts bytes
+    assert_eq!(
+        interpret_expr(mk_apply((), var((), "length"), string((), "é"))),
+        Ok(Value::VInt { int: 2 })
+    );
+
+    assert_eq!(
+        interpret_expr(mk_bin_op(
+            (),
+            Op::Equals,
+            string((), "a"),
+            mk_bin_op((), Op::Concat, string((), ""), string((), "a"))
+        )),
+        Ok(Value::VBool { bool: true })
+    );
+}
+
+#[test]
+fn test_interpret_data() {
+    use crate::parser::parse_constructors::{mk_case, mk_constructor, p_constructor, p_var};
+
+    // case Just 1 of Nothing -> 0 | Just a -> a + 1
+    let just_one = mk_constructor((), "Just", vec![int((), 1)]);
+    let maybe_plus_one = |scrutinee| {
+        mk_case(
+            (),
+            scrutinee,
+            vec![
+                (p_constructor((), "Nothing", vec![]), int((), 0)),
+                (
+                    p_constructor((), "Just", vec![p_var((), "a")]),
+                    mk_bin_op((), Op::Add, var((), "a"), int((), 1)),
+                ),
+            ],
+        )
+    };
+
+    assert_eq!(
+        interpret_expr(just_one.clone()),
+        Ok(Value::VData {
+            constructor: "Just".to_string(),
+            fields: vec![Value::VInt { int: 1 }]
+        })
+    );
+    assert_eq!(
+        interpret_expr(maybe_plus_one(just_one)),
+        Ok(Value::VInt { int: 2 })
+    );
+    assert_eq!(
+        interpret_expr(maybe_plus_one(mk_constructor((), "Nothing", vec![]))),
+        Ok(Value::VInt { int: 0 })
+    );
+
+    // nested patterns only match if every part matches
+    let nested = mk_case(
+        (),
+        mk_constructor((), "Just", vec![mk_constructor((), "Nothing", vec![])]),
+        vec![(
+            p_constructor(
+                (),
+                "Just",
+                vec![p_constructor((), "Just", vec![p_var((), "a")])],
+            ),
+            var((), "a"),
+        )],
+    );
+    assert_eq!(
+        interpret_expr(nested),
+        Err(RuntimeError::NoMatchingPattern {
+            ann: (),
+            found: Value::VData {
+                constructor: "Just".to_string(),
+                fields: vec![Value::VData {
+                    constructor: "Nothing".to_string(),
+                    fields: vec![]
+                }]
+            }
+        })
+    );
+}
+
+#[test]
+fn test_interpret_records() {
+    use crate::parser::parse_constructors::{mk_field_access, mk_record, mk_record_update};
+
+    let record = mk_record((), vec![("ok", bool((), true)), ("name", int((), 1))]);
+
+    // fields are sorted by name
+    assert_eq!(
+        interpret_expr(record.clone()),
+        Ok(Value::VRecord {
+            fields: vec![
+                ("name".to_string(), Value::VInt { int: 1 }),
+                ("ok".to_string(), Value::VBool { bool: true })
+            ]
+        })
+    );
+    assert_eq!(
+        interpret_expr(mk_field_access((), record.clone(), "name")),
+        Ok(Value::VInt { int: 1 })
+    );
+
+    // updating a record leaves the original alone
+    let updated = mk_let(
+        (),
+        "r",
+        record,
+        mk_let(
+            (),
+            "s",
+            mk_record_update((), var((), "r"), vec![("name", int((), 2))]),
+            mk_bin_op(
+                (),
+                Op::Add,
+                mk_field_access((), var((), "r"), "name"),
+                mk_field_access((), var((), "s"), "name"),
+            ),
+        ),
+    );
+    assert_eq!(interpret_expr(updated), Ok(Value::VInt { int: 3 }));
+
+    assert_eq!(
+        interpret_expr(mk_field_access((), int((), 1), "name")),
+        Err(RuntimeError::MissingField {
+            ann: (),
+            field: "name".to_string(),
+            found: Value::VInt { int: 1 }
+        })
+    );
+}
+
+#[test]
+fn test_interpret_tuples() {
+    use crate::parser::parse_constructors::{mk_case, mk_tuple, p_tuple, p_var};
+
+    let tuple = mk_tuple((), vec![int((), 1), bool((), true)]);
+    assert_eq!(
+        interpret_expr(tuple.clone()),
+        Ok(Value::VTuple {
+            items: vec![Value::VInt { int: 1 }, Value::VBool { bool: true }]
+        })
+    );
+    assert_eq!(
+        interpret_expr(mk_case(
+            (),
+            tuple,
+            vec![(
+                p_tuple((), vec![p_var((), "a"), p_var((), "b")]),
+                mk_tuple((), vec![var((), "b"), var((), "a")])
+            )]
+        )),
+        Ok(Value::VTuple {
+            items: vec![Value::VBool { bool: true }, Value::VInt { int: 1 }]
+        })
+    );
 }
@@ -0,0 +1,6 @@
+pub mod compile;
+pub mod interpret;
+pub mod parser;
+pub mod pretty;
+pub mod typecheck;
+pub mod types;
@@ -1,24 +1,43 @@
-use interpret::interpreter::interpret_expr;
-use typecheck::elaborate::elaborate_expr;
-use types::expr::{Expr, Prim};
+use rusty::interpret::interpreter::interpret_module;
+use rusty::parser::parse_module::parse_my_module;
+use rusty::parser::span::ParseInput;
+use rusty::typecheck::elaborate::elaborate_module;
+use rusty::typecheck::unify::normalise_type_vars;
 
-pub mod compile;
-pub mod interpret;
-pub mod parser;
-pub mod typecheck;
-pub mod types;
-
-// typecheck an arbitrary thing
+// typecheck a file, then run one of its definitions
+// usage: rusty <file> [definition]
 fn main() {
-    let raw_expr = Expr::EPrim {
-        ann: (),
-        prim: Prim::PInt { int: 1 },
+    let args: Vec<String> = std::env::args().collect();
+    let path = args.get(1).map(String::as_str).unwrap_or("thing.smol");
+    let entry = args.get(2).map(String::as_str).unwrap_or("main");
+
+    let source = match std::fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(err) => return println!("could not read {}: {}", path, err),
+    };
+
+    let module = match parse_my_module(ParseInput::new(&source)) {
+        Ok((_, module)) => module,
+        Err(nom::Err::Error(err) | nom::Err::Failure(err)) => {
+            return println!("parse error at line {}: {}", err.span.start.line, err.kind)
+        }
+        Err(err) => return println!("{:?}", err),
     };
 
-    match elaborate_expr(raw_expr) {
-        Ok(expr) => {
-            interpret_expr(expr);
+    match elaborate_module(module) {
+        Ok(typed) => {
+            for def in &typed.defs {
+                println!(
+                    "{} : {}",
+                    def.identifier,
+                    normalise_type_vars(def.ann.clone())
+                );
+            }
+            match interpret_module(typed, entry) {
+                Ok(value) => println!("{:?}", value),
+                Err(err) => println!("{:?}", err),
+            }
         }
-        Err(err) => println!("{:?}", err),
+        Err(err) => println!("type error: {}", err),
     }
 }
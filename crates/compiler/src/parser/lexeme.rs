@@ -1,13 +1,28 @@
+use super::span::{span_between, ParseInput, Span};
 use nom::{character::complete::multispace0, error::ParseError, sequence::preceded, IResult};
 
 /// A combinator that takes a parser `inner` and produces a parser that also consumes both leading and
 /// trailing whitespace, returning the output of `inner`.
-pub fn ws<'a, F: 'a, O, E: ParseError<&'a str>>(
+pub fn ws<'a, F: 'a, O, E: ParseError<ParseInput<'a>>>(
   inner: F,
-) -> impl FnMut(&'a str) -> IResult<&'a str, O, E>
+) -> impl FnMut(ParseInput<'a>) -> IResult<ParseInput<'a>, O, E>
 where
-  F: Fn(&'a str) -> IResult<&'a str, O, E>,
+  F: FnMut(ParseInput<'a>) -> IResult<ParseInput<'a>, O, E>,
 {
   preceded(multispace0, inner)
 }
 
+/// A combinator that skips leading whitespace then runs `inner`, returning its output alongside the
+/// `Span` of source that `inner` consumed.
+pub fn spanned<'a, F: 'a, O, E: ParseError<ParseInput<'a>>>(
+  mut inner: F,
+) -> impl FnMut(ParseInput<'a>) -> IResult<ParseInput<'a>, (Span, O), E>
+where
+  F: FnMut(ParseInput<'a>) -> IResult<ParseInput<'a>, O, E>,
+{
+  move |input| {
+    let (start, _) = multispace0(input)?;
+    let (rest, output) = inner(start)?;
+    Ok((rest, (span_between(&start, &rest), output)))
+  }
+}
@@ -1,3 +1,8 @@
+#[rustfmt::skip]
 pub mod lexeme;
 pub mod parse_constructors;
+pub mod parse_error;
 pub mod parse_expr;
+pub mod parse_module;
+pub mod parse_type;
+pub mod span;
@@ -1,35 +1,216 @@
-use crate::types::expr::{Expr, Prim};
+use crate::types::expr::{CaseArm, Expr, Op, Pattern, Prim};
+use crate::types::ty::Type;
 
 // construct int
-pub fn int(int_val: i32) -> Expr<()> {
+pub fn int<Ann>(ann: Ann, int_val: i32) -> Expr<Ann> {
     Expr::EPrim {
-        ann: (),
+        ann,
         prim: Prim::PInt { int: int_val },
     }
 }
 
 // construct bool
-pub fn bool(bool_val: bool) -> Expr<()> {
+pub fn bool<Ann>(ann: Ann, bool_val: bool) -> Expr<Ann> {
     Expr::EPrim {
-        ann: (),
+        ann,
         prim: Prim::PBool { bool: bool_val },
     }
 }
 
+// construct string
+pub fn string<Ann>(ann: Ann, string_val: &str) -> Expr<Ann> {
+    Expr::EPrim {
+        ann,
+        prim: Prim::PString {
+            string: string_val.to_string(),
+        },
+    }
+}
+
 // construct var
-pub fn var(identifier: &str) -> Expr<()> {
+pub fn var<Ann>(ann: Ann, identifier: &str) -> Expr<Ann> {
     Expr::EVar {
-        ann: (),
+        ann,
         identifier: identifier.to_string(),
     }
 }
 
 // construct if
-pub fn mk_if(pred_expr: Expr<()>, then_expr: Expr<()>, else_expr: Expr<()>) -> Expr<()> {
+pub fn mk_if<Ann>(
+    ann: Ann,
+    pred_expr: Expr<Ann>,
+    then_expr: Expr<Ann>,
+    else_expr: Expr<Ann>,
+) -> Expr<Ann> {
     Expr::EIf {
-        ann: (),
+        ann,
         pred_expr: Box::new(pred_expr),
         then_expr: Box::new(then_expr),
         else_expr: Box::new(else_expr),
     }
 }
+
+// construct let
+pub fn mk_let<Ann>(
+    ann: Ann,
+    identifier: &str,
+    bound_expr: Expr<Ann>,
+    rest_expr: Expr<Ann>,
+) -> Expr<Ann> {
+    Expr::ELet {
+        ann,
+        identifier: identifier.to_string(),
+        bound_expr: Box::new(bound_expr),
+        rest_expr: Box::new(rest_expr),
+    }
+}
+
+// construct recursive let
+pub fn mk_let_rec<Ann>(
+    ann: Ann,
+    identifier: &str,
+    bound_expr: Expr<Ann>,
+    rest_expr: Expr<Ann>,
+) -> Expr<Ann> {
+    Expr::ELetRec {
+        ann,
+        identifier: identifier.to_string(),
+        bound_expr: Box::new(bound_expr),
+        rest_expr: Box::new(rest_expr),
+    }
+}
+
+// construct binary operator
+pub fn mk_bin_op<Ann>(ann: Ann, op: Op, left_expr: Expr<Ann>, right_expr: Expr<Ann>) -> Expr<Ann> {
+    Expr::EBinOp {
+        ann,
+        op,
+        left_expr: Box::new(left_expr),
+        right_expr: Box::new(right_expr),
+    }
+}
+
+// construct lambda
+pub fn mk_lambda<Ann>(ann: Ann, identifier: &str, body_expr: Expr<Ann>) -> Expr<Ann> {
+    Expr::ELambda {
+        ann,
+        identifier: identifier.to_string(),
+        body_expr: Box::new(body_expr),
+    }
+}
+
+// construct function application
+pub fn mk_apply<Ann>(ann: Ann, function_expr: Expr<Ann>, argument_expr: Expr<Ann>) -> Expr<Ann> {
+    Expr::EApply {
+        ann,
+        function_expr: Box::new(function_expr),
+        argument_expr: Box::new(argument_expr),
+    }
+}
+
+// construct constructor
+pub fn mk_constructor<Ann>(ann: Ann, constructor: &str, args: Vec<Expr<Ann>>) -> Expr<Ann> {
+    Expr::EConstructor {
+        ann,
+        constructor: constructor.to_string(),
+        args,
+    }
+}
+
+// construct case
+pub fn mk_case<Ann>(
+    ann: Ann,
+    scrutinee_expr: Expr<Ann>,
+    arms: Vec<(Pattern<Ann>, Expr<Ann>)>,
+) -> Expr<Ann> {
+    Expr::ECase {
+        ann,
+        scrutinee_expr: Box::new(scrutinee_expr),
+        arms: arms
+            .into_iter()
+            .map(|(pattern, body_expr)| CaseArm { pattern, body_expr })
+            .collect(),
+    }
+}
+
+// construct tuple
+pub fn mk_tuple<Ann>(ann: Ann, items: Vec<Expr<Ann>>) -> Expr<Ann> {
+    Expr::ETuple { ann, items }
+}
+
+// construct record
+pub fn mk_record<Ann>(ann: Ann, fields: Vec<(&str, Expr<Ann>)>) -> Expr<Ann> {
+    Expr::ERecord {
+        ann,
+        fields: fields
+            .into_iter()
+            .map(|(name, field_expr)| (name.to_string(), field_expr))
+            .collect(),
+    }
+}
+
+// construct field access
+pub fn mk_field_access<Ann>(ann: Ann, record_expr: Expr<Ann>, field: &str) -> Expr<Ann> {
+    Expr::EFieldAccess {
+        ann,
+        record_expr: Box::new(record_expr),
+        field: field.to_string(),
+    }
+}
+
+// construct record update
+pub fn mk_record_update<Ann>(
+    ann: Ann,
+    record_expr: Expr<Ann>,
+    fields: Vec<(&str, Expr<Ann>)>,
+) -> Expr<Ann> {
+    Expr::ERecordUpdate {
+        ann,
+        record_expr: Box::new(record_expr),
+        fields: fields
+            .into_iter()
+            .map(|(name, field_expr)| (name.to_string(), field_expr))
+            .collect(),
+    }
+}
+
+// construct type annotation
+pub fn mk_annotation<Ann>(ann: Ann, expr: Expr<Ann>, annotation: Type<Ann>) -> Expr<Ann> {
+    Expr::EAnnotation {
+        ann,
+        expr: Box::new(expr),
+        annotation,
+    }
+}
+
+// construct variable pattern
+pub fn p_var<Ann>(ann: Ann, identifier: &str) -> Pattern<Ann> {
+    Pattern::PVar {
+        ann,
+        identifier: identifier.to_string(),
+    }
+}
+
+// construct wildcard pattern
+pub fn p_wildcard<Ann>(ann: Ann) -> Pattern<Ann> {
+    Pattern::PWildcard { ann }
+}
+
+// construct literal pattern
+pub fn p_literal<Ann>(ann: Ann, prim: Prim) -> Pattern<Ann> {
+    Pattern::PLiteral { ann, prim }
+}
+
+// construct tuple pattern
+pub fn p_tuple<Ann>(ann: Ann, items: Vec<Pattern<Ann>>) -> Pattern<Ann> {
+    Pattern::PTuple { ann, items }
+}
+
+// construct constructor pattern
+pub fn p_constructor<Ann>(ann: Ann, constructor: &str, args: Vec<Pattern<Ann>>) -> Pattern<Ann> {
+    Pattern::PConstructor {
+        ann,
+        constructor: constructor.to_string(),
+        args,
+    }
+}
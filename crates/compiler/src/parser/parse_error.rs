@@ -0,0 +1,87 @@
+use super::span::{location, ParseInput, Span};
+use nom::error::ErrorKind;
+
+use std::fmt;
+
+// errors our parsers can produce
+#[derive(Debug, PartialEq, Clone)]
+pub enum ParseErrorKind {
+    // an int literal that doesn't fit in an `i32`
+    IntegerOutOfRange { literal: String },
+    // a `\` in a string literal followed by something we don't understand
+    UnknownEscape { escape: char },
+    // a string literal with no closing `"`
+    UnterminatedString,
+    // the file stopped before something we'd started parsing was finished
+    UnexpectedEnd,
+    // nom couldn't match anything here
+    Nom(ErrorKind),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ParseError {
+    pub span: Span,
+    pub kind: ParseErrorKind,
+}
+
+// result of all our parsers
+pub type ParseResult<'a, O> = nom::IResult<ParseInput<'a>, O, ParseError>;
+
+impl<'a> nom::error::ParseError<ParseInput<'a>> for ParseError {
+    fn from_error_kind(input: ParseInput<'a>, kind: ErrorKind) -> Self {
+        let here = location(&input);
+        ParseError {
+            span: Span {
+                start: here,
+                end: here,
+            },
+            kind: match input.fragment().is_empty() {
+                true => ParseErrorKind::UnexpectedEnd,
+                false => ParseErrorKind::Nom(kind),
+            },
+        }
+    }
+
+    // keep the innermost error, it's the most specific
+    fn append(_input: ParseInput<'a>, _kind: ErrorKind, other: Self) -> Self {
+        other
+    }
+
+    // when every alternative fails, keep the error from the one that got
+    // furthest, it's most likely the one that was meant
+    fn or(self, other: Self) -> Self {
+        furthest(self, other)
+    }
+}
+
+// whichever of two errors got further through the input, or `later` if they
+// got as far as each other
+pub fn furthest(earlier: ParseError, later: ParseError) -> ParseError {
+    match earlier.span.start.offset > later.span.start.offset {
+        true => earlier,
+        false => later,
+    }
+}
+
+impl fmt::Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseErrorKind::IntegerOutOfRange { literal } => {
+                write!(
+                    f,
+                    "{} doesn't fit in an Int ({} to {})",
+                    literal,
+                    i32::MIN,
+                    i32::MAX
+                )
+            }
+            ParseErrorKind::UnknownEscape { escape } => {
+                write!(f, "unknown escape sequence `\\{}`", escape)
+            }
+            ParseErrorKind::UnterminatedString => write!(f, "this string has no closing `\"`"),
+            ParseErrorKind::UnexpectedEnd => write!(f, "unexpected end of file"),
+            ParseErrorKind::Nom(ErrorKind::Eof) => write!(f, "unexpected input"),
+            ParseErrorKind::Nom(_) => write!(f, "couldn't parse this"),
+        }
+    }
+}
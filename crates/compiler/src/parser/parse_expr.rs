@@ -1,127 +1,1473 @@
-use super::lexeme;
-use crate::parser::parse_constructors::{bool, int, mk_if, var};
-use crate::types::expr;
+use super::lexeme::{self, spanned};
+use super::parse_error::{ParseError, ParseErrorKind, ParseResult};
+use super::parse_type::parse_my_type;
+use super::span::{span_between, ParseInput, Span};
+use crate::parser::parse_constructors::{
+    bool, int, mk_annotation, mk_apply, mk_bin_op, mk_case, mk_constructor, mk_field_access, mk_if,
+    mk_lambda, mk_let, mk_let_rec, mk_tuple, p_constructor, p_literal, p_tuple, p_var, p_wildcard,
+    string, var,
+};
+use crate::types::expr::{self, Op, Pattern};
 use nom::branch::alt;
 use nom::{
-    bytes::complete::{tag, take_while_m_n},
-    character::complete::alpha1,
-    combinator::{map, map_res},
-    IResult,
+    bytes::complete::tag,
+    character::complete::{alpha1, char, digit1, multispace0},
+    combinator::{map, not, opt, recognize, verify},
+    multi::{many0, separated_list0, separated_list1},
+    sequence::{delimited, pair, preceded, terminated},
+    InputTake,
 };
 
-// Expr with () for annotations (we'll leave this for later)
-type ParseExpr = expr::Expr<()>;
+// Expr annotated with the part of the source it was parsed from
+type ParseExpr = expr::Expr<Span>;
 
-fn is_int_digit(c: char) -> bool {
-    c.is_digit(10)
-}
+type ParsePattern = Pattern<Span>;
 
-fn int_primary(input: &str) -> IResult<&str, u8> {
-    map_res(take_while_m_n(1, 12, is_int_digit), from_int)(input)
+// run a parser on a plain string and throw away the spans, so tests can
+// compare against trees built with `()` annotations
+#[cfg(test)]
+fn parse_without_spans<'a, F>(
+    mut parser: F,
+    input: &'a str,
+) -> Result<(&'a str, expr::Expr<()>), ()>
+where
+    F: FnMut(ParseInput<'a>) -> ParseResult<'a, ParseExpr>,
+{
+    parser(ParseInput::new(input))
+        .map(|(rest, expr)| (*rest.fragment(), expr::map_expr(expr, |_| ())))
+        .map_err(|_| ())
 }
 
-fn from_int(input: &str) -> Result<u8, std::num::ParseIntError> {
-    u8::from_str_radix(input, 10)
+// digits with an optional leading minus, ie `-123`
+fn int_primary(input: ParseInput) -> ParseResult<ParseInput> {
+    recognize(pair(opt(char('-')), digit1))(input)
 }
 
-fn parse_my_int(input: &str) -> IResult<&str, ParseExpr> {
-    let (input, int_val) = lexeme::ws(int_primary)(input)?;
-    let as_i32 = i32::from(int_val);
-    Ok((input, int(as_i32)))
+fn parse_my_int(input: ParseInput) -> ParseResult<ParseExpr> {
+    let (input, (span, literal)) = spanned(int_primary)(input)?;
+    match literal.fragment().parse::<i32>() {
+        Ok(int_val) => Ok((input, int(span, int_val))),
+        // this is definitely an int, just not one we can store, so fail
+        // rather than letting `alt` try other parsers
+        Err(_) => Err(nom::Err::Failure(ParseError {
+            span,
+            kind: ParseErrorKind::IntegerOutOfRange {
+                literal: literal.fragment().to_string(),
+            },
+        })),
+    }
 }
 
 #[test]
 fn test_parse_my_int() {
-    assert_eq!(parse_my_int(" 1"), Ok(("", int(1))));
-    assert_eq!(parse_my_int("1"), Ok(("", int(1))));
-    assert_eq!(parse_my_int("11"), Ok(("", int(11))));
-    assert_eq!(parse_my_int("11dog"), Ok(("dog", int(11))));
+    assert_eq!(
+        parse_without_spans(parse_my_int, " 1"),
+        Ok(("", int((), 1)))
+    );
+    assert_eq!(parse_without_spans(parse_my_int, "1"), Ok(("", int((), 1))));
+    assert_eq!(
+        parse_without_spans(parse_my_int, "11"),
+        Ok(("", int((), 11)))
+    );
+    assert_eq!(
+        parse_without_spans(parse_my_int, "11dog"),
+        Ok(("dog", int((), 11)))
+    );
+    assert_eq!(
+        parse_without_spans(parse_my_int, "256"),
+        Ok(("", int((), 256)))
+    );
+    assert_eq!(
+        parse_without_spans(parse_my_int, "-1"),
+        Ok(("", int((), -1)))
+    );
+    assert_eq!(
+        parse_without_spans(parse_my_int, " -0"),
+        Ok(("", int((), 0)))
+    );
+    assert_eq!(
+        parse_without_spans(parse_my_int, "2147483647"),
+        Ok(("", int((), i32::MAX)))
+    );
+    assert_eq!(
+        parse_without_spans(parse_my_int, "-2147483648"),
+        Ok(("", int((), i32::MIN)))
+    );
+    assert!(parse_without_spans(parse_my_int, "-").is_err());
+    assert!(parse_without_spans(parse_my_int, "- 1").is_err());
 }
 
-// check we aren't using protected words for variables
-fn var_is_protected(ident: &str) -> bool {
-    vec!["True", "False", "if", "then", "else"].contains(&ident)
-}
-
-// jesus
-fn parse_my_var(input: &str) -> IResult<&str, ParseExpr> {
-    map_res(lexeme::ws(alpha1), |var_val| {
-        match var_is_protected(var_val) {
-            true => Err(nom::Err::Error {
-                0: nom::error::Error {
-                    code: nom::error::ErrorKind::Tag,
-                    input: input,
+#[test]
+fn test_parse_int_out_of_range() {
+    use super::span::Location;
+
+    assert_eq!(
+        parse_my_expr(ParseInput::new(" 2147483648")).map_err(|e| match e {
+            nom::Err::Failure(e) => Some(e),
+            _ => None,
+        }),
+        Err(Some(ParseError {
+            span: Span {
+                start: Location {
+                    offset: 1,
+                    line: 1,
+                    column: 2
                 },
-            }),
-            false => Ok(var(var_val)),
+                end: Location {
+                    offset: 11,
+                    line: 1,
+                    column: 12
+                }
+            },
+            kind: ParseErrorKind::IntegerOutOfRange {
+                literal: "2147483648".to_string()
+            }
+        }))
+    );
+
+    // too small is out of range too, and the message doesn't say too big
+    match parse_my_expr(ParseInput::new("-2147483649")) {
+        Err(nom::Err::Failure(ParseError { kind, .. })) => {
+            assert_eq!(
+                kind,
+                ParseErrorKind::IntegerOutOfRange {
+                    literal: "-2147483649".to_string()
+                }
+            );
+            assert_eq!(
+                kind.to_string(),
+                "-2147483649 doesn't fit in an Int (-2147483648 to 2147483647)"
+            );
         }
+        other => panic!("expected IntegerOutOfRange, got {:?}", other),
+    }
+
+    // an out of range literal deep inside an expression fails the whole parse
+    assert!(matches!(
+        parse_my_expr(ParseInput::new("if True then 99999999999999 else 1")),
+        Err(nom::Err::Failure(ParseError {
+            kind: ParseErrorKind::IntegerOutOfRange { .. },
+            ..
+        }))
+    ));
+}
+
+// words that mean something to the parser, so can't be variables
+pub const PROTECTED_WORDS: [&str; 12] = [
+    "True", "False", "if", "then", "else", "let", "rec", "in", "def", "data", "case", "of",
+];
+
+// check we aren't using protected words for variables
+pub fn var_is_protected(ident: &str) -> bool {
+    PROTECTED_WORDS.contains(&ident)
+}
+
+// variables start with a lowercase letter, so we can tell them apart from
+// constructors
+pub fn parse_my_identifier(input: ParseInput) -> ParseResult<ParseInput> {
+    verify(alpha1, |var_val: &ParseInput| {
+        var_val.fragment().starts_with(|c: char| c.is_lowercase())
+            && !var_is_protected(var_val.fragment())
+    })(input)
+}
+
+// constructors and type names start with an uppercase letter
+pub fn parse_my_constructor_name(input: ParseInput) -> ParseResult<ParseInput> {
+    verify(alpha1, |name: &ParseInput| {
+        name.fragment().starts_with(|c: char| c.is_uppercase())
+            && !var_is_protected(name.fragment())
+    })(input)
+}
+
+fn parse_my_var(input: ParseInput) -> ParseResult<ParseExpr> {
+    map(spanned(parse_my_identifier), |(span, var_val)| {
+        var(span, var_val.fragment())
     })(input)
 }
 
 #[test]
 fn test_parse_my_var() {
-    assert_ne!(parse_my_var("False"), Ok(("", var("False"))));
-    assert_ne!(parse_my_var("True"), Ok(("", var("True"))));
-    assert_ne!(parse_my_var("if"), Ok(("", var("if"))));
-    assert_eq!(parse_my_var(" p"), Ok(("", var("p"))));
-    assert_eq!(parse_my_var("p"), Ok(("", var("p"))));
-    assert_eq!(parse_my_var("poo"), Ok(("", var("poo"))));
-    assert_eq!(parse_my_var("poo "), Ok((" ", var("poo"))))
+    assert_ne!(
+        parse_without_spans(parse_my_var, "False"),
+        Ok(("", var((), "False")))
+    );
+    assert_ne!(
+        parse_without_spans(parse_my_var, "True"),
+        Ok(("", var((), "True")))
+    );
+    assert_ne!(
+        parse_without_spans(parse_my_var, "if"),
+        Ok(("", var((), "if")))
+    );
+    assert_ne!(
+        parse_without_spans(parse_my_var, "let"),
+        Ok(("", var((), "let")))
+    );
+    assert_ne!(
+        parse_without_spans(parse_my_var, "in"),
+        Ok(("", var((), "in")))
+    );
+    assert_eq!(
+        parse_without_spans(parse_my_var, " p"),
+        Ok(("", var((), "p")))
+    );
+    assert_eq!(
+        parse_without_spans(parse_my_var, "p"),
+        Ok(("", var((), "p")))
+    );
+    assert_eq!(
+        parse_without_spans(parse_my_var, "poo"),
+        Ok(("", var((), "poo")))
+    );
+    assert_eq!(
+        parse_without_spans(parse_my_var, "poo "),
+        Ok((" ", var((), "poo")))
+    );
+    assert!(parse_without_spans(parse_my_var, "Poo").is_err());
+    assert!(parse_without_spans(parse_my_var, "case").is_err());
 }
 
-fn parse_true(input: &str) -> IResult<&str, ParseExpr> {
-    map(lexeme::ws(tag("True")), |_| bool(true))(input)
+fn parse_true(input: ParseInput) -> ParseResult<ParseExpr> {
+    map(spanned(tag("True")), |(span, _)| bool(span, true))(input)
 }
 
-fn parse_false(input: &str) -> IResult<&str, ParseExpr> {
-    map(lexeme::ws(tag("False")), |_| bool(false))(input)
+fn parse_false(input: ParseInput) -> ParseResult<ParseExpr> {
+    map(spanned(tag("False")), |(span, _)| bool(span, false))(input)
 }
 
-fn parse_my_bool(input: &str) -> IResult<&str, ParseExpr> {
+fn parse_my_bool(input: ParseInput) -> ParseResult<ParseExpr> {
     alt((parse_true, parse_false))(input)
 }
 
 #[test]
 fn test_parse_my_bool() {
-    assert_eq!(parse_my_bool(" True"), Ok(("", bool(true))));
-    assert_eq!(parse_my_bool("False"), Ok(("", bool(false))));
-    assert_eq!(parse_my_bool("   True100"), Ok(("100", bool(true))));
+    assert_eq!(
+        parse_without_spans(parse_my_bool, " True"),
+        Ok(("", bool((), true)))
+    );
+    assert_eq!(
+        parse_without_spans(parse_my_bool, "False"),
+        Ok(("", bool((), false)))
+    );
+    assert_eq!(
+        parse_without_spans(parse_my_bool, "   True100"),
+        Ok(("100", bool((), true)))
+    );
+}
+
+// a `"` delimited string, with escape sequences replaced by what they mean
+fn string_literal(input: ParseInput) -> ParseResult<String> {
+    let (body, _) = char('"')(input)?;
+    let mut string_val = String::new();
+    let mut chars = body.fragment().char_indices();
+
+    loop {
+        match chars.next() {
+            Some((index, '"')) => {
+                let (rest, _) = body.take_split(index + 1);
+                return Ok((rest, string_val));
+            }
+            Some((index, '\\')) => {
+                let escaped = match chars.next() {
+                    Some((_, 'n')) => '\n',
+                    Some((_, 't')) => '\t',
+                    Some((_, 'r')) => '\r',
+                    Some((_, '0')) => '\0',
+                    Some((_, '\\')) => '\\',
+                    Some((_, '"')) => '"',
+                    Some((escape_index, escape)) => {
+                        // point at the whole escape sequence
+                        let (escape_start, _) = body.take_split(index);
+                        let (escape_end, _) = body.take_split(escape_index + escape.len_utf8());
+                        return Err(nom::Err::Failure(ParseError {
+                            span: span_between(&escape_start, &escape_end),
+                            kind: ParseErrorKind::UnknownEscape { escape },
+                        }));
+                    }
+                    None => break,
+                };
+                string_val.push(escaped);
+            }
+            Some((_, other)) => string_val.push(other),
+            None => break,
+        }
+    }
+
+    // we ran out of input before the closing quote
+    let (end, _) = input.take_split(input.fragment().len());
+    Err(nom::Err::Failure(ParseError {
+        span: span_between(&input, &end),
+        kind: ParseErrorKind::UnterminatedString,
+    }))
+}
+
+fn parse_my_string(input: ParseInput) -> ParseResult<ParseExpr> {
+    map(spanned(string_literal), |(span, string_val)| {
+        string(span, &string_val)
+    })(input)
 }
 
-pub fn parse_my_if(input: &str) -> IResult<&str, ParseExpr> {
-    let (input, _) = lexeme::ws(tag("if"))(input)?;
-    let (input, pred_expr) = parse_my_expr(input)?;
+#[test]
+fn test_parse_my_string() {
+    assert_eq!(
+        parse_without_spans(parse_my_string, " \"horse\""),
+        Ok(("", string((), "horse")))
+    );
+    assert_eq!(
+        parse_without_spans(parse_my_string, "\"\" ++ a"),
+        Ok((" ++ a", string((), "")))
+    );
+    assert_eq!(
+        parse_without_spans(parse_my_string, "\"a\\nb\\t\\\"c\\\"\\\\\""),
+        Ok(("", string((), "a\nb\t\"c\"\\")))
+    );
+    assert_eq!(
+        parse_without_spans(parse_my_string, "\"sdfsdf`\""),
+        Ok(("", string((), "sdfsdf`")))
+    );
+    assert_eq!(
+        parse_without_spans(parse_my_string, "\"héllo\""),
+        Ok(("", string((), "héllo")))
+    );
 
-    let (input, _) = lexeme::ws(tag("then"))(input)?;
-    let (input, then_expr) = parse_my_expr(input)?;
+    match parse_my_string(ParseInput::new("\"ab\\qc\"")) {
+        Err(nom::Err::Failure(ParseError { span, kind })) => {
+            assert_eq!(kind, ParseErrorKind::UnknownEscape { escape: 'q' });
+            assert_eq!((span.start.offset, span.end.offset), (3, 5));
+        }
+        other => panic!("expected an unknown escape, got {:?}", other),
+    }
+
+    match parse_my_string(ParseInput::new("\"abc")) {
+        Err(nom::Err::Failure(ParseError { span, kind })) => {
+            assert_eq!(kind, ParseErrorKind::UnterminatedString);
+            assert_eq!((span.start.offset, span.end.offset), (0, 4));
+        }
+        other => panic!("expected an unterminated string, got {:?}", other),
+    }
+}
+
+pub fn parse_my_if(input: ParseInput) -> ParseResult<ParseExpr> {
+    let (input, (span, (pred_expr, then_expr, else_expr))) = spanned(|input| {
+        let (input, _) = tag("if")(input)?;
+        let (input, pred_expr) = parse_my_expr(input)?;
 
-    let (input, _) = lexeme::ws(tag("else"))(input)?;
-    let (input, else_expr) = parse_my_expr(input)?;
+        let (input, _) = lexeme::ws(tag("then"))(input)?;
+        let (input, then_expr) = parse_my_expr(input)?;
 
-    Ok((input, mk_if(pred_expr, then_expr, else_expr)))
+        let (input, _) = lexeme::ws(tag("else"))(input)?;
+        let (input, else_expr) = parse_my_expr(input)?;
+
+        Ok((input, (pred_expr, then_expr, else_expr)))
+    })(input)?;
+
+    Ok((input, mk_if(span, pred_expr, then_expr, else_expr)))
 }
 
 #[test]
 fn test_parse_my_if() {
     assert_eq!(
-        parse_my_if("if 1 then False else True"),
-        Ok(("", mk_if(int(1), bool(false), bool(true))))
+        parse_without_spans(parse_my_if, "if 1 then False else True"),
+        Ok(("", mk_if((), int((), 1), bool((), false), bool((), true))))
+    );
+
+    assert_eq!(
+        parse_without_spans(parse_my_if, "if False then 1 else 2"),
+        Ok(("", mk_if((), bool((), false), int((), 1), int((), 2))))
     );
+}
+
+// `let rec` can only bind a lambda, as nothing else can use itself
+pub fn parse_my_let(input: ParseInput) -> ParseResult<ParseExpr> {
+    let (input, (span, (recursive, identifier, bound_expr, rest_expr))) = spanned(|input| {
+        let (input, _) = tag("let")(input)?;
+        let (input, recursive) = opt(lexeme::ws(verify(alpha1, |word: &ParseInput| {
+            *word.fragment() == "rec"
+        })))(input)?;
+        let (input, identifier) = lexeme::ws(parse_my_identifier)(input)?;
+
+        let (input, _) = lexeme::ws(tag("="))(input)?;
+        let (input, bound_expr) = match recursive {
+            Some(_) => parse_my_lambda(input)?,
+            None => parse_my_expr(input)?,
+        };
+
+        let (input, _) = lexeme::ws(tag("in"))(input)?;
+        let (input, rest_expr) = parse_my_expr(input)?;
+
+        Ok((
+            input,
+            (recursive.is_some(), identifier, bound_expr, rest_expr),
+        ))
+    })(input)?;
+
+    if recursive {
+        Ok((
+            input,
+            mk_let_rec(span, identifier.fragment(), bound_expr, rest_expr),
+        ))
+    } else {
+        Ok((
+            input,
+            mk_let(span, identifier.fragment(), bound_expr, rest_expr),
+        ))
+    }
+}
+
+#[test]
+fn test_parse_my_let_rec() {
+    assert_eq!(
+        parse_without_spans(parse_my_expr, "let rec f = \\a -> f a in f 1"),
+        Ok((
+            "",
+            mk_let_rec(
+                (),
+                "f",
+                mk_lambda((), "a", mk_apply((), var((), "f"), var((), "a"))),
+                mk_apply((), var((), "f"), int((), 1))
+            )
+        ))
+    );
+    // a plain `let` can still bind names starting with `rec`
+    assert_eq!(
+        parse_without_spans(parse_my_expr, "let record = 1 in record"),
+        Ok(("", mk_let((), "record", int((), 1), var((), "record"))))
+    );
+    // only functions can be recursive
+    assert!(parse_without_spans(parse_my_expr, "let rec a = a in a").is_err());
+}
 
+#[test]
+fn test_parse_my_let() {
     assert_eq!(
-        parse_my_if("if False then 1 else 2"),
-        Ok(("", mk_if(bool(false), int(1), int(2))))
+        parse_without_spans(parse_my_let, "let a = 1 in a"),
+        Ok(("", mk_let((), "a", int((), 1), var((), "a"))))
     );
+
+    assert_eq!(
+        parse_without_spans(
+            parse_my_let,
+            "let a = True in let b = 2 in if a then b else 3"
+        ),
+        Ok((
+            "",
+            mk_let(
+                (),
+                "a",
+                bool((), true),
+                mk_let(
+                    (),
+                    "b",
+                    int((), 2),
+                    mk_if((), var((), "a"), var((), "b"), int((), 3))
+                )
+            )
+        ))
+    );
+
+    assert_eq!(
+        parse_without_spans(parse_my_let, "let\n  a =\n let b = 1 in b\nin a"),
+        Ok((
+            "",
+            mk_let(
+                (),
+                "a",
+                mk_let((), "b", int((), 1), var((), "b")),
+                var((), "a")
+            )
+        ))
+    );
+
+    // keywords can't be bound
+    assert!(parse_without_spans(parse_my_let, "let in = 1 in 2").is_err());
+    assert!(parse_without_spans(parse_my_let, "let a = 1").is_err());
+}
+
+#[test]
+fn test_parse_and_elaborate_let() {
+    use crate::typecheck::elaborate::elaborate_expr;
+    use crate::types::expr::{get_expr_annotation, map_expr};
+    use crate::types::ty::{remove_type_annotation, Type};
+
+    let elaborate = |input| {
+        let (_, parsed) = parse_my_expr(ParseInput::new(input)).unwrap();
+        elaborate_expr(parsed).map(|typed| remove_type_annotation(get_expr_annotation(typed)))
+    };
+
+    assert_eq!(elaborate("let a = 1 in a"), Ok(Type::TInt { ann: () }));
+    assert_eq!(
+        elaborate("let a = 1 in let a = False in a"),
+        Ok(Type::TBool { ann: () })
+    );
+    assert_eq!(
+        elaborate("let a = True in if a then 1 else 2"),
+        Ok(Type::TInt { ann: () })
+    );
+    assert!(elaborate("let a = 1 in if a then 1 else 2").is_err());
+    assert!(elaborate("let a = let b = 1 in b in b").is_err());
+
+    // the elaborated tree has the same shape as the parsed one
+    let (_, parsed) = parse_my_expr(ParseInput::new("let a = 1 in let b = a in b")).unwrap();
+    let typed = elaborate_expr(parsed.clone()).unwrap();
+    assert_eq!(map_expr(typed, |_| ()), map_expr(parsed, |_| ()));
 }
 
 #[test]
 fn test_parse_my_expr() {
-    assert_eq!(parse_my_expr("True"), Ok(("", bool(true))));
-    assert_eq!(parse_my_expr("False"), Ok(("", bool(false))));
-    assert_eq!(parse_my_expr("p"), Ok(("", var("p"))));
-    assert_eq!(parse_my_expr("poo"), Ok(("", var("poo"))));
+    assert_eq!(
+        parse_without_spans(parse_my_expr, "True"),
+        Ok(("", bool((), true)))
+    );
+    assert_eq!(
+        parse_without_spans(parse_my_expr, "False"),
+        Ok(("", bool((), false)))
+    );
+    assert_eq!(
+        parse_without_spans(parse_my_expr, "p"),
+        Ok(("", var((), "p")))
+    );
+    assert_eq!(
+        parse_without_spans(parse_my_expr, "poo"),
+        Ok(("", var((), "poo")))
+    );
+}
+
+#[test]
+fn test_parse_spans() {
+    use super::span::Location;
+    use crate::types::expr::get_expr_annotation;
+
+    let (_, parsed) = parse_my_expr(ParseInput::new("  if True\nthen 1 else 22")).unwrap();
+
+    assert_eq!(
+        get_expr_annotation(parsed.clone()),
+        Span {
+            start: Location {
+                offset: 2,
+                line: 1,
+                column: 3
+            },
+            end: Location {
+                offset: 24,
+                line: 2,
+                column: 15
+            }
+        }
+    );
+
+    match parsed {
+        expr::Expr::EIf { else_expr, .. } => assert_eq!(
+            get_expr_annotation(*else_expr),
+            Span {
+                start: Location {
+                    offset: 22,
+                    line: 2,
+                    column: 13
+                },
+                end: Location {
+                    offset: 24,
+                    line: 2,
+                    column: 15
+                }
+            }
+        ),
+        _ => panic!("expected an if expression"),
+    }
+}
+
+// `(a)` is just `a`, but `(a, b)` is a tuple, and either can be followed by
+// a type, as in `(a : Int)`
+fn parse_my_parens(input: ParseInput) -> ParseResult<ParseExpr> {
+    let (input, (span, (mut items, annotation))) = spanned(delimited(
+        char('('),
+        pair(
+            separated_list1(lexeme::ws(char(',')), parse_my_expr),
+            opt(preceded(lexeme::ws(char(':')), parse_my_type)),
+        ),
+        lexeme::ws(char(')')),
+    ))(input)?;
+
+    let expr = if items.len() == 1 {
+        items.remove(0)
+    } else {
+        mk_tuple(span, items)
+    };
+
+    match annotation {
+        Some(annotation) => Ok((input, mk_annotation(span, expr, annotation))),
+        None => Ok((input, expr)),
+    }
+}
+
+// operators with two characters need to come before their one character
+// prefixes, so we don't parse `<=` as `<`
+fn parse_my_op(input: ParseInput) -> ParseResult<Op> {
+    lexeme::ws(alt((
+        map(tag("&&"), |_| Op::And),
+        map(tag("||"), |_| Op::Or),
+        map(tag("++"), |_| Op::Concat),
+        map(tag("=="), |_| Op::Equals),
+        map(tag("!="), |_| Op::NotEquals),
+        map(tag("<="), |_| Op::LessThanOrEqual),
+        map(tag(">="), |_| Op::GreaterThanOrEqual),
+        map(tag("<"), |_| Op::LessThan),
+        map(tag(">"), |_| Op::GreaterThan),
+        map(tag("+"), |_| Op::Add),
+        map(tag("-"), |_| Op::Subtract),
+        map(tag("*"), |_| Op::Multiply),
+        map(tag("/"), |_| Op::Divide),
+        map(tag("%"), |_| Op::Modulo),
+    )))(input)
+}
+
+// how tightly each operator binds, higher binds tighter
+pub fn op_precedence(op: Op) -> u8 {
+    match op {
+        Op::Or => 1,
+        Op::And => 2,
+        Op::Equals
+        | Op::NotEquals
+        | Op::LessThan
+        | Op::LessThanOrEqual
+        | Op::GreaterThan
+        | Op::GreaterThanOrEqual => 3,
+        Op::Add | Op::Subtract | Op::Concat => 4,
+        Op::Multiply | Op::Divide | Op::Modulo => 5,
+    }
+}
+
+pub fn parse_my_lambda(input: ParseInput) -> ParseResult<ParseExpr> {
+    let (input, (span, (identifier, body_expr))) = spanned(|input| {
+        let (input, _) = char('\\')(input)?;
+        let (input, identifier) = lexeme::ws(parse_my_identifier)(input)?;
+
+        let (input, _) = lexeme::ws(tag("->"))(input)?;
+        let (input, body_expr) = parse_my_expr(input)?;
+
+        Ok((input, (identifier, body_expr)))
+    })(input)?;
+
+    Ok((input, mk_lambda(span, identifier.fragment(), body_expr)))
+}
+
+#[test]
+fn test_parse_my_lambda() {
+    assert_eq!(
+        parse_without_spans(parse_my_lambda, "\\a -> a"),
+        Ok(("", mk_lambda((), "a", var((), "a"))))
+    );
+
+    // the body extends as far as it can
+    assert_eq!(
+        parse_without_spans(parse_my_expr, "\\a -> \\b -> a + b"),
+        Ok((
+            "",
+            mk_lambda(
+                (),
+                "a",
+                mk_lambda((), "b", mk_bin_op((), Op::Add, var((), "a"), var((), "b")))
+            )
+        ))
+    );
+
+    assert!(parse_without_spans(parse_my_lambda, "\\if -> 1").is_err());
+}
+
+// a constructor along with any arguments we can find for it
+fn parse_my_constructor(input: ParseInput) -> ParseResult<ParseExpr> {
+    let (start, _) = multispace0(input)?;
+    let (input, name) = parse_my_constructor_name(start)?;
+    let (input, args) = many0(parse_my_argument)(input)?;
+
+    Ok((
+        input,
+        mk_constructor(span_between(&start, &input), name.fragment(), args),
+    ))
+}
+
+// a constructor on its own, which is all we can pass as an argument without
+// brackets
+fn parse_my_bare_constructor(input: ParseInput) -> ParseResult<ParseExpr> {
+    map(spanned(parse_my_constructor_name), |(span, name)| {
+        mk_constructor(span, name.fragment(), vec![])
+    })(input)
+}
+
+// `_`, but not the start of a longer word
+fn parse_my_wildcard(input: ParseInput) -> ParseResult<ParsePattern> {
+    map(spanned(terminated(char('_'), not(alpha1))), |(span, _)| {
+        p_wildcard(span)
+    })(input)
+}
+
+// `1`, `True` or `"horse"`
+fn parse_my_literal_pattern(input: ParseInput) -> ParseResult<ParsePattern> {
+    map(
+        alt((parse_my_bool, parse_my_int, parse_my_string)),
+        |expr| match expr {
+            expr::Expr::EPrim { ann, prim } => p_literal(ann, prim),
+            _ => unreachable!("literals are always prims"),
+        },
+    )(input)
+}
+
+// patterns that don't need brackets to be a constructor argument
+fn parse_my_pattern_argument(input: ParseInput) -> ParseResult<ParsePattern> {
+    alt((
+        parse_my_wildcard,
+        parse_my_literal_pattern,
+        map(spanned(parse_my_identifier), |(span, identifier)| {
+            p_var(span, identifier.fragment())
+        }),
+        map(spanned(parse_my_constructor_name), |(span, name)| {
+            p_constructor(span, name.fragment(), vec![])
+        }),
+        map(
+            spanned(delimited(
+                char('('),
+                separated_list1(lexeme::ws(char(',')), parse_my_pattern),
+                lexeme::ws(char(')')),
+            )),
+            |(span, mut items)| {
+                if items.len() == 1 {
+                    items.remove(0)
+                } else {
+                    p_tuple(span, items)
+                }
+            },
+        ),
+    ))(input)
 }
 
-pub fn parse_my_expr(input: &str) -> IResult<&str, ParseExpr> {
-    alt((parse_my_bool, parse_my_int, parse_my_var, parse_my_if))(input)
+pub fn parse_my_pattern(input: ParseInput) -> ParseResult<ParsePattern> {
+    let (start, _) = multispace0(input)?;
+    match parse_my_constructor_name(start) {
+        Ok((input, name)) => {
+            let (input, args) = many0(parse_my_pattern_argument)(input)?;
+            Ok((
+                input,
+                p_constructor(span_between(&start, &input), name.fragment(), args),
+            ))
+        }
+        Err(nom::Err::Error(_)) => parse_my_pattern_argument(start),
+        Err(other) => Err(other),
+    }
+}
+
+// a single `|`, so we don't eat the start of `||`
+fn parse_arm_separator(input: ParseInput) -> ParseResult<char> {
+    lexeme::ws(terminated(char('|'), not(char('|'))))(input)
+}
+
+fn parse_my_case_arm(input: ParseInput) -> ParseResult<(ParsePattern, ParseExpr)> {
+    let (input, pattern) = parse_my_pattern(input)?;
+    let (input, _) = lexeme::ws(tag("->"))(input)?;
+    let (input, body_expr) = parse_my_expr(input)?;
+    Ok((input, (pattern, body_expr)))
+}
+
+// `case x of Just a -> a | Nothing -> 0`
+pub fn parse_my_case(input: ParseInput) -> ParseResult<ParseExpr> {
+    let (input, (span, (scrutinee_expr, arms))) = spanned(|input| {
+        let (input, _) = tag("case")(input)?;
+        let (input, scrutinee_expr) = parse_my_expr(input)?;
+        let (input, _) = lexeme::ws(tag("of"))(input)?;
+        let (input, arms) = separated_list1(parse_arm_separator, parse_my_case_arm)(input)?;
+        Ok((input, (scrutinee_expr, arms)))
+    })(input)?;
+
+    Ok((input, mk_case(span, scrutinee_expr, arms)))
+}
+
+#[test]
+fn test_parse_my_case() {
+    assert_eq!(
+        parse_without_spans(parse_my_expr, "case x of Just a -> a | Nothing -> 0"),
+        Ok((
+            "",
+            mk_case(
+                (),
+                var((), "x"),
+                vec![
+                    (
+                        p_constructor((), "Just", vec![p_var((), "a")]),
+                        var((), "a")
+                    ),
+                    (p_constructor((), "Nothing", vec![]), int((), 0)),
+                ]
+            )
+        ))
+    );
+
+    // nested patterns, and `||` in an arm isn't the start of another arm
+    assert_eq!(
+        parse_without_spans(
+            parse_my_expr,
+            "case x of Pair (Just a) b -> a || b | Pair Nothing b -> b"
+        ),
+        Ok((
+            "",
+            mk_case(
+                (),
+                var((), "x"),
+                vec![
+                    (
+                        p_constructor(
+                            (),
+                            "Pair",
+                            vec![
+                                p_constructor((), "Just", vec![p_var((), "a")]),
+                                p_var((), "b")
+                            ]
+                        ),
+                        mk_bin_op((), Op::Or, var((), "a"), var((), "b"))
+                    ),
+                    (
+                        p_constructor(
+                            (),
+                            "Pair",
+                            vec![p_constructor((), "Nothing", vec![]), p_var((), "b")]
+                        ),
+                        var((), "b")
+                    ),
+                ]
+            )
+        ))
+    );
+
+    // literals and wildcards
+    assert_eq!(
+        parse_without_spans(
+            parse_my_expr,
+            "case x of 1 -> True | -2 -> False | Just \"a\" -> True | _ -> False"
+        ),
+        Ok((
+            "",
+            mk_case(
+                (),
+                var((), "x"),
+                vec![
+                    (p_literal((), expr::Prim::PInt { int: 1 }), bool((), true)),
+                    (p_literal((), expr::Prim::PInt { int: -2 }), bool((), false)),
+                    (
+                        p_constructor(
+                            (),
+                            "Just",
+                            vec![p_literal(
+                                (),
+                                expr::Prim::PString {
+                                    string: "a".to_string()
+                                }
+                            )]
+                        ),
+                        bool((), true)
+                    ),
+                    (p_wildcard(()), bool((), false)),
+                ]
+            )
+        ))
+    );
+
+    // `_a` isn't a wildcard
+    assert!(parse_without_spans(parse_my_expr, "case x of _a -> 1").is_err());
+}
+
+#[test]
+fn test_parse_my_constructor() {
+    assert_eq!(
+        parse_without_spans(parse_my_expr, "Nothing"),
+        Ok(("", mk_constructor((), "Nothing", vec![])))
+    );
+
+    // constructors take every argument after them
+    assert_eq!(
+        parse_without_spans(parse_my_expr, "Pair 1 (Just a) + 1"),
+        Ok((
+            "",
+            mk_bin_op(
+                (),
+                Op::Add,
+                mk_constructor(
+                    (),
+                    "Pair",
+                    vec![int((), 1), mk_constructor((), "Just", vec![var((), "a")])]
+                ),
+                int((), 1)
+            )
+        ))
+    );
+
+    // but a constructor that is itself an argument takes nothing
+    assert_eq!(
+        parse_without_spans(parse_my_expr, "f Nothing 1"),
+        Ok((
+            "",
+            mk_apply(
+                (),
+                mk_apply((), var((), "f"), mk_constructor((), "Nothing", vec![])),
+                int((), 1)
+            )
+        ))
+    );
+}
+
+// `name: expr`
+fn parse_my_record_field(input: ParseInput) -> ParseResult<(String, ParseExpr)> {
+    let (input, name) = lexeme::ws(parse_my_identifier)(input)?;
+    let (input, _) = lexeme::ws(char(':'))(input)?;
+    let (input, field_expr) = parse_my_expr(input)?;
+    Ok((input, (name.fragment().to_string(), field_expr)))
+}
+
+// `{ name: 1, ok: True }`
+fn parse_my_record(input: ParseInput) -> ParseResult<ParseExpr> {
+    map(
+        spanned(delimited(
+            lexeme::ws(char('{')),
+            separated_list0(lexeme::ws(char(',')), parse_my_record_field),
+            lexeme::ws(char('}')),
+        )),
+        |(span, fields)| expr::Expr::ERecord { ann: span, fields },
+    )(input)
+}
+
+// `{ record | name: 2 }`
+fn parse_my_record_update(input: ParseInput) -> ParseResult<ParseExpr> {
+    map(
+        spanned(delimited(
+            lexeme::ws(char('{')),
+            pair(
+                terminated(parse_my_var, parse_arm_separator),
+                separated_list1(lexeme::ws(char(',')), parse_my_record_field),
+            ),
+            lexeme::ws(char('}')),
+        )),
+        |(span, (record_expr, fields))| expr::Expr::ERecordUpdate {
+            ann: span,
+            record_expr: Box::new(record_expr),
+            fields,
+        },
+    )(input)
+}
+
+// `record.name.first`, with no spaces around the dots
+fn parse_my_field_access(input: ParseInput) -> ParseResult<ParseExpr> {
+    let (start, _) = multispace0(input)?;
+    let (mut input, mut record_expr) = alt((
+        parse_my_var,
+        parse_my_parens,
+        parse_my_record_update,
+        parse_my_record,
+    ))(start)?;
+
+    loop {
+        match preceded(char('.'), parse_my_identifier)(input) {
+            Ok((after_field, field)) => {
+                let span = span_between(&start, &after_field);
+                record_expr = mk_field_access(span, record_expr, field.fragment());
+                input = after_field;
+            }
+            Err(nom::Err::Error(_)) => return Ok((input, record_expr)),
+            Err(other) => return Err(other),
+        }
+    }
+}
+
+// things that can be passed as arguments without wrapping them in brackets
+// we don't allow negative literals here, so `a -1` is a subtraction
+fn parse_my_argument(input: ParseInput) -> ParseResult<ParseExpr> {
+    let (input, _) = not(lexeme::ws(char('-')))(input)?;
+    alt((
+        parse_my_bool,
+        parse_my_int,
+        parse_my_string,
+        parse_my_field_access,
+        parse_my_bare_constructor,
+    ))(input)
+}
+
+// anything that can be the operand of an operator
+fn parse_my_atom(input: ParseInput) -> ParseResult<ParseExpr> {
+    alt((
+        parse_my_bool,
+        parse_my_int,
+        parse_my_string,
+        parse_my_field_access,
+        parse_my_constructor,
+        parse_my_if,
+        parse_my_let,
+        parse_my_lambda,
+        parse_my_case,
+        parse_my_negate,
+    ))(input)
+}
+
+// `-x` is `0 - x`, and takes a whole application, so `-f a` is `-(f a)`
+// negative literals are parsed as literals before we get here
+fn parse_my_negate(input: ParseInput) -> ParseResult<ParseExpr> {
+    map(
+        spanned(preceded(char('-'), parse_my_application)),
+        |(span, expr)| mk_bin_op(span, Op::Subtract, int(span, 0), expr),
+    )(input)
+}
+
+#[test]
+fn test_parse_my_negate() {
+    let negate = |expr| mk_bin_op((), Op::Subtract, int((), 0), expr);
+
+    assert_eq!(
+        parse_without_spans(parse_my_expr, "-x"),
+        Ok(("", negate(var((), "x"))))
+    );
+    assert_eq!(
+        parse_without_spans(parse_my_expr, "-(a + b)"),
+        Ok((
+            "",
+            negate(mk_bin_op((), Op::Add, var((), "a"), var((), "b")))
+        ))
+    );
+    // it binds tighter than operators, but looser than application
+    assert_eq!(
+        parse_without_spans(parse_my_expr, "-f a * 2"),
+        Ok((
+            "",
+            mk_bin_op(
+                (),
+                Op::Multiply,
+                negate(mk_apply((), var((), "f"), var((), "a"))),
+                int((), 2)
+            )
+        ))
+    );
+    assert_eq!(
+        parse_without_spans(parse_my_expr, "let x = 3 in -x"),
+        Ok(("", mk_let((), "x", int((), 3), negate(var((), "x")))))
+    );
+    // still a subtraction when there's something on the left
+    assert_eq!(
+        parse_without_spans(parse_my_expr, "a -x"),
+        Ok(("", mk_bin_op((), Op::Subtract, var((), "a"), var((), "x"))))
+    );
+    assert_eq!(
+        parse_without_spans(parse_my_expr, "a - -1"),
+        Ok(("", mk_bin_op((), Op::Subtract, var((), "a"), int((), -1))))
+    );
+}
+
+// `f a b` applies `f` to `a`, then applies the result to `b`
+fn parse_my_application(input: ParseInput) -> ParseResult<ParseExpr> {
+    let (start, _) = multispace0(input)?;
+    let (mut input, mut function_expr) = parse_my_atom(start)?;
+
+    loop {
+        match parse_my_argument(input) {
+            Ok((after_argument, argument_expr)) => {
+                let span = span_between(&start, &after_argument);
+                function_expr = mk_apply(span, function_expr, argument_expr);
+                input = after_argument;
+            }
+            Err(nom::Err::Error(_)) => return Ok((input, function_expr)),
+            Err(other) => return Err(other),
+        }
+    }
+}
+
+#[test]
+fn test_parse_my_application() {
+    assert_eq!(
+        parse_without_spans(parse_my_expr, "f 1"),
+        Ok(("", mk_apply((), var((), "f"), int((), 1))))
+    );
+
+    assert_eq!(
+        parse_without_spans(parse_my_expr, "f a True"),
+        Ok((
+            "",
+            mk_apply((), mk_apply((), var((), "f"), var((), "a")), bool((), true))
+        ))
+    );
+
+    // application binds tighter than operators
+    assert_eq!(
+        parse_without_spans(parse_my_expr, "f 1 + g (2 * 3)"),
+        Ok((
+            "",
+            mk_bin_op(
+                (),
+                Op::Add,
+                mk_apply((), var((), "f"), int((), 1)),
+                mk_apply(
+                    (),
+                    var((), "g"),
+                    mk_bin_op((), Op::Multiply, int((), 2), int((), 3))
+                )
+            )
+        ))
+    );
+
+    assert_eq!(
+        parse_without_spans(parse_my_expr, "f -1"),
+        Ok(("", mk_bin_op((), Op::Subtract, var((), "f"), int((), 1))))
+    );
+
+    assert_eq!(
+        parse_without_spans(parse_my_expr, "f (-1)"),
+        Ok(("", mk_apply((), var((), "f"), int((), -1))))
+    );
+
+    assert_eq!(
+        parse_without_spans(parse_my_expr, "(\\a -> a) 1"),
+        Ok((
+            "",
+            mk_apply((), mk_lambda((), "a", var((), "a")), int((), 1))
+        ))
+    );
+
+    // keywords end an application
+    assert_eq!(
+        parse_without_spans(parse_my_expr, "if f a then g 1 else 2"),
+        Ok((
+            "",
+            mk_if(
+                (),
+                mk_apply((), var((), "f"), var((), "a")),
+                mk_apply((), var((), "g"), int((), 1)),
+                int((), 2)
+            )
+        ))
+    );
+}
+
+// precedence climbing: parse an atom, then keep folding in operators that
+// bind at least as tightly as `min_precedence`
+fn parse_my_bin_op(min_precedence: u8, input: ParseInput) -> ParseResult<ParseExpr> {
+    let (start, _) = multispace0(input)?;
+    let (mut input, mut left_expr) = parse_my_application(start)?;
+
+    loop {
+        match parse_my_op(input) {
+            Ok((after_op, op)) if op_precedence(op) >= min_precedence => {
+                // the right hand side only takes operators that bind tighter,
+                // so operators of the same precedence associate to the left
+                let (after_right, right_expr) = parse_my_bin_op(op_precedence(op) + 1, after_op)?;
+                let span = span_between(&start, &after_right);
+                left_expr = mk_bin_op(span, op, left_expr, right_expr);
+                input = after_right;
+            }
+            _ => return Ok((input, left_expr)),
+        }
+    }
+}
+
+#[test]
+fn test_parse_my_bin_op() {
+    // 1 + 2 * 3 == 1 + (2 * 3)
+    assert_eq!(
+        parse_without_spans(parse_my_expr, "1 + 2 * 3"),
+        Ok((
+            "",
+            mk_bin_op(
+                (),
+                Op::Add,
+                int((), 1),
+                mk_bin_op((), Op::Multiply, int((), 2), int((), 3))
+            )
+        ))
+    );
+
+    // 1 - 2 - 3 == (1 - 2) - 3
+    assert_eq!(
+        parse_without_spans(parse_my_expr, "1 - 2 - 3"),
+        Ok((
+            "",
+            mk_bin_op(
+                (),
+                Op::Subtract,
+                mk_bin_op((), Op::Subtract, int((), 1), int((), 2)),
+                int((), 3)
+            )
+        ))
+    );
+
+    // `++` isn't two `+`s
+    assert_eq!(
+        parse_without_spans(parse_my_expr, "a ++ \"b\" ++ c"),
+        Ok((
+            "",
+            mk_bin_op(
+                (),
+                Op::Concat,
+                mk_bin_op((), Op::Concat, var((), "a"), string((), "b")),
+                var((), "c")
+            )
+        ))
+    );
+
+    // 1-1 is a subtraction, not `1` followed by `-1`
+    assert_eq!(
+        parse_without_spans(parse_my_expr, "1-1"),
+        Ok(("", mk_bin_op((), Op::Subtract, int((), 1), int((), 1))))
+    );
+
+    assert_eq!(
+        parse_without_spans(parse_my_expr, "1 - -1"),
+        Ok(("", mk_bin_op((), Op::Subtract, int((), 1), int((), -1))))
+    );
+
+    // (1 + 2) * 3
+    assert_eq!(
+        parse_without_spans(parse_my_expr, "(1 + 2) * 3"),
+        Ok((
+            "",
+            mk_bin_op(
+                (),
+                Op::Multiply,
+                mk_bin_op((), Op::Add, int((), 1), int((), 2)),
+                int((), 3)
+            )
+        ))
+    );
+
+    // a || b && c <= d == (a || (b && (c <= d)))
+    assert_eq!(
+        parse_without_spans(parse_my_expr, "a || b && c <= d"),
+        Ok((
+            "",
+            mk_bin_op(
+                (),
+                Op::Or,
+                var((), "a"),
+                mk_bin_op(
+                    (),
+                    Op::And,
+                    var((), "b"),
+                    mk_bin_op((), Op::LessThanOrEqual, var((), "c"), var((), "d"))
+                )
+            )
+        ))
+    );
+
+    // a % 2 != 0
+    assert_eq!(
+        parse_without_spans(parse_my_expr, "a % 2 != 0"),
+        Ok((
+            "",
+            mk_bin_op(
+                (),
+                Op::NotEquals,
+                mk_bin_op((), Op::Modulo, var((), "a"), int((), 2)),
+                int((), 0)
+            )
+        ))
+    );
+
+    // `else` extends as far as it can
+    assert_eq!(
+        parse_without_spans(parse_my_expr, "1 + if a then 2 else 3 + 4"),
+        Ok((
+            "",
+            mk_bin_op(
+                (),
+                Op::Add,
+                int((), 1),
+                mk_if(
+                    (),
+                    var((), "a"),
+                    int((), 2),
+                    mk_bin_op((), Op::Add, int((), 3), int((), 4))
+                )
+            )
+        ))
+    );
+
+    // operators inside `let`
+    assert_eq!(
+        parse_without_spans(parse_my_expr, "let a = 1 > 2 in a == False"),
+        Ok((
+            "",
+            mk_let(
+                (),
+                "a",
+                mk_bin_op((), Op::GreaterThan, int((), 1), int((), 2)),
+                mk_bin_op((), Op::Equals, var((), "a"), bool((), false))
+            )
+        ))
+    );
+}
+
+#[test]
+fn test_parse_bin_op_spans() {
+    use crate::types::expr::get_expr_annotation;
+
+    let (_, parsed) = parse_my_expr(ParseInput::new(" 10 +  2 * 3")).unwrap();
+    let span = get_expr_annotation(parsed.clone());
+    assert_eq!((span.start.offset, span.end.offset), (1, 12));
+
+    match parsed {
+        expr::Expr::EBinOp { right_expr, .. } => {
+            let span = get_expr_annotation(*right_expr);
+            assert_eq!((span.start.offset, span.end.offset), (7, 12));
+        }
+        _ => panic!("expected a binary operator"),
+    }
+}
+
+pub fn parse_my_expr(input: ParseInput) -> ParseResult<ParseExpr> {
+    parse_my_bin_op(0, input)
+}
+
+#[test]
+fn test_parse_my_record() {
+    use crate::parser::parse_constructors::{mk_record, mk_record_update};
+
+    assert_eq!(
+        parse_without_spans(parse_my_expr, "{ name: 1, ok: True }"),
+        Ok((
+            "",
+            mk_record((), vec![("name", int((), 1)), ("ok", bool((), true))])
+        ))
+    );
+    assert_eq!(
+        parse_without_spans(parse_my_expr, "{}"),
+        Ok(("", mk_record((), vec![])))
+    );
+
+    // field access binds tighter than application
+    assert_eq!(
+        parse_without_spans(parse_my_expr, "f r.name.first"),
+        Ok((
+            "",
+            mk_apply(
+                (),
+                var((), "f"),
+                mk_field_access((), mk_field_access((), var((), "r"), "name"), "first")
+            )
+        ))
+    );
+    assert_eq!(
+        parse_without_spans(parse_my_expr, "{ a: 1 }.a + 1"),
+        Ok((
+            "",
+            mk_bin_op(
+                (),
+                Op::Add,
+                mk_field_access((), mk_record((), vec![("a", int((), 1))]), "a"),
+                int((), 1)
+            )
+        ))
+    );
+
+    assert_eq!(
+        parse_without_spans(parse_my_expr, "{ r | name: 2, ok: a || b }"),
+        Ok((
+            "",
+            mk_record_update(
+                (),
+                var((), "r"),
+                vec![
+                    ("name", int((), 2)),
+                    ("ok", mk_bin_op((), Op::Or, var((), "a"), var((), "b")))
+                ]
+            )
+        ))
+    );
+}
+
+#[test]
+fn test_parse_my_tuple() {
+    assert_eq!(
+        parse_without_spans(parse_my_expr, "(1, True, a + 1)"),
+        Ok((
+            "",
+            mk_tuple(
+                (),
+                vec![
+                    int((), 1),
+                    bool((), true),
+                    mk_bin_op((), Op::Add, var((), "a"), int((), 1))
+                ]
+            )
+        ))
+    );
+    assert_eq!(
+        parse_without_spans(parse_my_expr, "(1)"),
+        Ok(("", int((), 1)))
+    );
+    assert_eq!(
+        parse_without_spans(parse_my_expr, "case t of (a, (Just b, _)) -> a"),
+        Ok((
+            "",
+            mk_case(
+                (),
+                var((), "t"),
+                vec![(
+                    p_tuple(
+                        (),
+                        vec![
+                            p_var((), "a"),
+                            p_tuple(
+                                (),
+                                vec![
+                                    p_constructor((), "Just", vec![p_var((), "b")]),
+                                    p_wildcard(())
+                                ]
+                            )
+                        ]
+                    ),
+                    var((), "a")
+                )]
+            )
+        ))
+    );
+}
+
+#[test]
+fn test_parse_my_annotation() {
+    use crate::parser::parse_constructors::mk_annotation;
+    use crate::types::ty::Type;
+
+    assert_eq!(
+        parse_without_spans(parse_my_expr, "(a + 1 : Int)"),
+        Ok((
+            "",
+            mk_annotation(
+                (),
+                mk_bin_op((), Op::Add, var((), "a"), int((), 1)),
+                Type::TInt { ann: () }
+            )
+        ))
+    );
+    assert_eq!(
+        parse_without_spans(parse_my_expr, "f (\\a -> a : Int -> Int)"),
+        Ok((
+            "",
+            mk_apply(
+                (),
+                var((), "f"),
+                mk_annotation(
+                    (),
+                    mk_lambda((), "a", var((), "a")),
+                    Type::TFunction {
+                        ann: (),
+                        argument: Box::new(Type::TInt { ann: () }),
+                        result: Box::new(Type::TInt { ann: () })
+                    }
+                )
+            )
+        ))
+    );
+    assert_eq!(
+        parse_without_spans(parse_my_expr, "(1, True : (Int, Bool))"),
+        Ok((
+            "",
+            mk_annotation(
+                (),
+                mk_tuple((), vec![int((), 1), bool((), true)]),
+                Type::TTuple {
+                    ann: (),
+                    items: vec![Type::TInt { ann: () }, Type::TBool { ann: () }]
+                }
+            )
+        ))
+    );
 }
@@ -0,0 +1,294 @@
+use super::lexeme::{self, spanned};
+use super::parse_error::{furthest, ParseResult};
+use super::parse_expr::{parse_my_constructor_name, parse_my_expr, parse_my_identifier};
+use super::parse_type::{parse_my_type, parse_type_atom};
+use super::span::{span_between, ParseInput, Span};
+#[cfg(test)]
+use crate::parser::parse_constructors::{int, mk_apply, mk_bin_op, mk_lambda, var};
+#[cfg(test)]
+use crate::types::expr::Op;
+#[cfg(test)]
+use crate::types::module::map_module;
+use crate::types::module::{Data, DataConstructor, Def, Module};
+#[cfg(test)]
+use crate::types::ty::Type;
+use nom::{
+    bytes::complete::tag,
+    character::complete::{char, multispace0},
+    combinator::{cut, eof, opt},
+    multi::{many0, separated_list1},
+    sequence::preceded,
+};
+
+// `def name = expr` or `def name : type = expr`
+pub fn parse_my_def(input: ParseInput) -> ParseResult<Def<Span>> {
+    let (input, (span, (identifier, signature, expr))) = spanned(|input| {
+        let (input, _) = tag("def")(input)?;
+        let (input, identifier) = lexeme::ws(parse_my_identifier)(input)?;
+
+        // once we've seen the `:` it must be a type, so if it isn't we say
+        // where it went wrong rather than going back to before the `:`
+        let (input, signature) = opt(preceded(lexeme::ws(tag(":")), cut(parse_my_type)))(input)?;
+
+        let (input, _) = lexeme::ws(tag("="))(input)?;
+        let (input, expr) = parse_my_expr(input)?;
+
+        Ok((input, (identifier, signature, expr)))
+    })(input)?;
+
+    Ok((
+        input,
+        Def {
+            ann: span,
+            identifier: identifier.fragment().to_string(),
+            signature,
+            expr,
+        },
+    ))
+}
+
+fn parse_my_data_constructor<'a>(
+    params: &[String],
+    input: ParseInput<'a>,
+) -> ParseResult<'a, DataConstructor<Span>> {
+    let (start, _) = multispace0(input)?;
+    let (input, name) = parse_my_constructor_name(start)?;
+    let (input, fields) = many0(|input| parse_type_atom(params, input))(input)?;
+
+    Ok((
+        input,
+        DataConstructor {
+            ann: span_between(&start, &input),
+            name: name.fragment().to_string(),
+            fields,
+        },
+    ))
+}
+
+// `data Maybe a = Just a | Nothing`
+pub fn parse_my_data(input: ParseInput) -> ParseResult<Data<Span>> {
+    let (input, (span, (name, params, constructors))) = spanned(|input| {
+        let (input, _) = tag("data")(input)?;
+        let (input, name) = lexeme::ws(parse_my_constructor_name)(input)?;
+        let (input, params) = many0(lexeme::ws(parse_my_identifier))(input)?;
+        let params: Vec<String> = params
+            .into_iter()
+            .map(|param| param.fragment().to_string())
+            .collect();
+
+        let (input, _) = lexeme::ws(tag("="))(input)?;
+        let (input, constructors) = separated_list1(lexeme::ws(char('|')), |input| {
+            parse_my_data_constructor(&params, input)
+        })(input)?;
+
+        Ok((input, (name, params, constructors)))
+    })(input)?;
+
+    Ok((
+        input,
+        Data {
+            ann: span,
+            name: name.fragment().to_string(),
+            params,
+            constructors,
+        },
+    ))
+}
+
+// a whole file, which must be nothing but `def`s and `data`s
+//
+// if something other than the end of the file follows the last one we could
+// parse, we report whichever of the `def`, `data` or end of file got
+// furthest, so a broken `def` is blamed rather than its first character
+pub fn parse_my_module(input: ParseInput) -> ParseResult<Module<Span>> {
+    let mut module = Module {
+        datas: vec![],
+        defs: vec![],
+    };
+    let mut input = input;
+
+    let (def_error, data_error) = loop {
+        let def_error = match parse_my_def(input) {
+            Ok((rest, def)) => {
+                module.defs.push(def);
+                input = rest;
+                continue;
+            }
+            Err(nom::Err::Error(error)) => error,
+            Err(other) => return Err(other),
+        };
+        match parse_my_data(input) {
+            Ok((rest, data)) => {
+                module.datas.push(data);
+                input = rest;
+            }
+            Err(nom::Err::Error(data_error)) => break (def_error, data_error),
+            Err(other) => return Err(other),
+        }
+    };
+
+    let (input, _) = multispace0(input)?;
+    match eof(input) {
+        Ok((input, _)) => Ok((input, module)),
+        Err(nom::Err::Error(eof_error)) => Err(nom::Err::Error(furthest(
+            furthest(def_error, data_error),
+            eof_error,
+        ))),
+        Err(other) => Err(other),
+    }
+}
+
+#[cfg(test)]
+fn parse_module_without_spans(input: &str) -> Result<Module<()>, ()> {
+    parse_my_module(ParseInput::new(input))
+        .map(|(_, module)| map_module(module, |_| ()))
+        .map_err(|_| ())
+}
+
+#[test]
+fn test_parse_my_module() {
+    assert_eq!(
+        parse_module_without_spans(""),
+        Ok(Module {
+            datas: vec![],
+            defs: vec![]
+        })
+    );
+
+    assert_eq!(
+        parse_module_without_spans(
+            "def one = 1\n\ndef inc : Int -> Int = \\a -> a + one\ndef two = inc one\n"
+        ),
+        Ok(Module {
+            datas: vec![],
+            defs: vec![
+                Def {
+                    ann: (),
+                    identifier: "one".to_string(),
+                    signature: None,
+                    expr: int((), 1)
+                },
+                Def {
+                    ann: (),
+                    identifier: "inc".to_string(),
+                    signature: Some(Type::TFunction {
+                        ann: (),
+                        argument: Box::new(Type::TInt { ann: () }),
+                        result: Box::new(Type::TInt { ann: () })
+                    }),
+                    expr: mk_lambda(
+                        (),
+                        "a",
+                        mk_bin_op((), Op::Add, var((), "a"), var((), "one"))
+                    )
+                },
+                Def {
+                    ann: (),
+                    identifier: "two".to_string(),
+                    signature: None,
+                    expr: mk_apply((), var((), "inc"), var((), "one"))
+                },
+            ]
+        })
+    );
+
+    // `def` can't be a variable, so it ends the previous definition
+    assert!(parse_module_without_spans("def a = def").is_err());
+    assert!(parse_module_without_spans("def a = 1 2 +").is_err());
+    assert!(parse_module_without_spans("1 + 2").is_err());
+}
+
+#[test]
+fn test_parse_def_spans() {
+    let (_, module) = parse_my_module(ParseInput::new("def a = 1\ndef b = 2")).unwrap();
+    let spans: Vec<_> = module
+        .defs
+        .iter()
+        .map(|def| (def.ann.start.offset, def.ann.end.offset))
+        .collect();
+    assert_eq!(spans, vec![(0, 9), (10, 19)]);
+}
+
+#[test]
+fn test_parse_module_errors() {
+    use super::parse_error::ParseErrorKind;
+
+    let error_at = |input| match parse_my_module(ParseInput::new(input)) {
+        Err(nom::Err::Error(error) | nom::Err::Failure(error)) => {
+            (error.span.start.offset, error.kind)
+        }
+        other => panic!("expected an error, got {:?}", other),
+    };
+
+    // a broken definition is blamed where it went wrong, not where it started
+    assert_eq!(
+        error_at("def main = let x = 1 in"),
+        (23, ParseErrorKind::UnexpectedEnd)
+    );
+    assert_eq!(
+        error_at("def a = 1\ndef main = let x = 1 in   "),
+        (36, ParseErrorKind::UnexpectedEnd)
+    );
+    assert_eq!(error_at("def a = 1\ndata T = A |").0, 21);
+    assert_eq!(error_at("def main = (1, 2").0, 16);
+    assert_eq!(error_at("def a = 1\ndef b : Int -> = 2").0, 25);
+
+    // but something that's neither is unexpected where it starts
+    assert_eq!(
+        error_at("def a = 1\n  )"),
+        (12, ParseErrorKind::Nom(nom::error::ErrorKind::Eof))
+    );
+}
+
+#[test]
+fn test_parse_my_data() {
+    let module = parse_module_without_spans(
+        "data Maybe a = Just a | Nothing\ndef nothing = Nothing\ndata Pair a b = Pair a (Maybe b)",
+    )
+    .unwrap();
+
+    assert_eq!(
+        module.datas,
+        vec![
+            Data {
+                ann: (),
+                name: "Maybe".to_string(),
+                params: vec!["a".to_string()],
+                constructors: vec![
+                    DataConstructor {
+                        ann: (),
+                        name: "Just".to_string(),
+                        fields: vec![Type::TVar { ann: (), var: 0 }]
+                    },
+                    DataConstructor {
+                        ann: (),
+                        name: "Nothing".to_string(),
+                        fields: vec![]
+                    }
+                ]
+            },
+            Data {
+                ann: (),
+                name: "Pair".to_string(),
+                params: vec!["a".to_string(), "b".to_string()],
+                constructors: vec![DataConstructor {
+                    ann: (),
+                    name: "Pair".to_string(),
+                    fields: vec![
+                        Type::TVar { ann: (), var: 0 },
+                        Type::TConstructor {
+                            ann: (),
+                            name: "Maybe".to_string(),
+                            args: vec![Type::TVar { ann: (), var: 1 }]
+                        }
+                    ]
+                }]
+            }
+        ]
+    );
+    assert_eq!(module.defs.len(), 1);
+
+    // fields can only mention the declared parameters
+    assert!(parse_module_without_spans("data Maybe = Just a").is_err());
+    assert!(parse_module_without_spans("data maybe = Just").is_err());
+}
@@ -0,0 +1,358 @@
+use super::lexeme::{self, spanned};
+use super::parse_error::{ParseError, ParseResult};
+use super::parse_expr::{parse_my_constructor_name, parse_my_identifier};
+use super::span::{span_between, ParseInput, Span};
+use crate::types::ty::Type;
+use nom::branch::alt;
+use nom::error::{ErrorKind, ParseError as _};
+use nom::{
+    bytes::complete::tag,
+    character::complete::{alpha1, char, multispace0},
+    combinator::{map, verify},
+    multi::{many0, separated_list0, separated_list1},
+    sequence::delimited,
+};
+
+// Type annotated with the part of the source it was parsed from
+type ParseType = Type<Span>;
+
+// a type name, making sure we don't match the start of a longer word
+fn type_name<'a>(name: &'static str) -> impl FnMut(ParseInput<'a>) -> ParseResult<'a, Span> {
+    map(
+        spanned(verify(alpha1, move |found: &ParseInput| {
+            *found.fragment() == name
+        })),
+        |(span, _)| span,
+    )
+}
+
+fn parse_type_int(input: ParseInput) -> ParseResult<ParseType> {
+    map(type_name("Int"), |ann| Type::TInt { ann })(input)
+}
+
+fn parse_type_bool(input: ParseInput) -> ParseResult<ParseType> {
+    map(type_name("Bool"), |ann| Type::TBool { ann })(input)
+}
+
+fn parse_type_string(input: ParseInput) -> ParseResult<ParseType> {
+    map(type_name("String"), |ann| Type::TString { ann })(input)
+}
+
+// one of the type parameters of a `data` declaration, which become type
+// variables numbered by their position
+fn parse_type_param<'a>(params: &[String], input: ParseInput<'a>) -> ParseResult<'a, ParseType> {
+    let (rest, (span, name)) = spanned(parse_my_identifier)(input)?;
+    match params.iter().position(|param| param == name.fragment()) {
+        Some(var) => Ok((
+            rest,
+            Type::TVar {
+                ann: span,
+                var: var as u32,
+            },
+        )),
+        None => Err(nom::Err::Error(ParseError::from_error_kind(
+            input,
+            ErrorKind::Verify,
+        ))),
+    }
+}
+
+// a type declared with `data`, without any arguments
+fn parse_type_bare_constructor(input: ParseInput) -> ParseResult<ParseType> {
+    map(spanned(parse_my_constructor_name), |(span, name)| {
+        Type::TConstructor {
+            ann: span,
+            name: name.fragment().to_string(),
+            args: vec![],
+        }
+    })(input)
+}
+
+fn parse_type_record_field<'a>(
+    params: &[String],
+    input: ParseInput<'a>,
+) -> ParseResult<'a, (String, ParseType)> {
+    let (input, name) = lexeme::ws(parse_my_identifier)(input)?;
+    let (input, _) = lexeme::ws(char(':'))(input)?;
+    let (input, ty) = parse_type_with_params(params, input)?;
+    Ok((input, (name.fragment().to_string(), ty)))
+}
+
+// `{ name: Int, ok: Bool }`
+fn parse_type_record<'a>(params: &[String], input: ParseInput<'a>) -> ParseResult<'a, ParseType> {
+    let (start, _) = multispace0(input)?;
+    let (input, mut fields) = delimited(
+        lexeme::ws(char('{')),
+        separated_list0(lexeme::ws(char(',')), |input| {
+            parse_type_record_field(params, input)
+        }),
+        lexeme::ws(char('}')),
+    )(start)?;
+
+    fields.sort_by(|(a, _), (b, _)| a.cmp(b));
+    Ok((
+        input,
+        Type::TRecord {
+            ann: span_between(&start, &input),
+            fields,
+        },
+    ))
+}
+
+pub fn parse_type_atom<'a>(params: &[String], input: ParseInput<'a>) -> ParseResult<'a, ParseType> {
+    alt((
+        parse_type_int,
+        parse_type_bool,
+        parse_type_string,
+        parse_type_bare_constructor,
+        |input| parse_type_param(params, input),
+        |input| parse_type_record(params, input),
+        |input| parse_type_parens(params, input),
+    ))(input)
+}
+
+// `(Int)` is just `Int`, but `(Int, Bool)` is a tuple
+fn parse_type_parens<'a>(params: &[String], input: ParseInput<'a>) -> ParseResult<'a, ParseType> {
+    let (start, _) = multispace0(input)?;
+    let (input, mut items) = delimited(
+        char('('),
+        separated_list1(lexeme::ws(char(',')), |input| {
+            parse_type_with_params(params, input)
+        }),
+        lexeme::ws(char(')')),
+    )(start)?;
+
+    if items.len() == 1 {
+        Ok((input, items.remove(0)))
+    } else {
+        Ok((
+            input,
+            Type::TTuple {
+                ann: span_between(&start, &input),
+                items,
+            },
+        ))
+    }
+}
+
+// `Maybe Int`, or any atom on its own
+fn parse_type_application<'a>(
+    params: &[String],
+    input: ParseInput<'a>,
+) -> ParseResult<'a, ParseType> {
+    let (start, _) = multispace0(input)?;
+    match parse_type_atom(params, start)? {
+        (
+            input,
+            Type::TConstructor {
+                name,
+                args: no_args,
+                ..
+            },
+        ) if no_args.is_empty() => {
+            let (input, args) = many0(|input| parse_type_atom(params, input))(input)?;
+            Ok((
+                input,
+                Type::TConstructor {
+                    ann: span_between(&start, &input),
+                    name,
+                    args,
+                },
+            ))
+        }
+        other => Ok(other),
+    }
+}
+
+pub fn parse_my_type(input: ParseInput) -> ParseResult<ParseType> {
+    parse_type_with_params(&[], input)
+}
+
+// `a -> b -> c` means `a -> (b -> c)`
+pub fn parse_type_with_params<'a>(
+    params: &[String],
+    input: ParseInput<'a>,
+) -> ParseResult<'a, ParseType> {
+    let (start, _) = multispace0(input)?;
+    let (input, argument) = parse_type_application(params, start)?;
+
+    match lexeme::ws(tag("->"))(input) {
+        Ok((input, _)) => {
+            let (input, result) = parse_type_with_params(params, input)?;
+            Ok((
+                input,
+                Type::TFunction {
+                    ann: span_between(&start, &input),
+                    argument: Box::new(argument),
+                    result: Box::new(result),
+                },
+            ))
+        }
+        Err(nom::Err::Error(_)) => Ok((input, argument)),
+        Err(other) => Err(other),
+    }
+}
+
+#[cfg(test)]
+fn parse_type_without_spans(input: &str) -> Result<(&str, Type<()>), ()> {
+    parse_my_type(ParseInput::new(input))
+        .map(|(rest, ty)| {
+            (
+                *rest.fragment(),
+                crate::types::ty::remove_type_annotation(ty),
+            )
+        })
+        .map_err(|_| ())
+}
+
+#[test]
+fn test_parse_my_type() {
+    assert_eq!(
+        parse_type_without_spans("Int"),
+        Ok(("", Type::TInt { ann: () }))
+    );
+    assert_eq!(
+        parse_type_without_spans(" Bool"),
+        Ok(("", Type::TBool { ann: () }))
+    );
+    assert_eq!(
+        parse_type_without_spans("String"),
+        Ok(("", Type::TString { ann: () }))
+    );
+    assert!(parse_type_without_spans("a").is_err());
+
+    // arrows associate to the right
+    assert_eq!(
+        parse_type_without_spans("Int -> Bool -> Int"),
+        Ok((
+            "",
+            Type::TFunction {
+                ann: (),
+                argument: Box::new(Type::TInt { ann: () }),
+                result: Box::new(Type::TFunction {
+                    ann: (),
+                    argument: Box::new(Type::TBool { ann: () }),
+                    result: Box::new(Type::TInt { ann: () })
+                })
+            }
+        ))
+    );
+
+    assert_eq!(
+        parse_type_without_spans("(Int -> Bool) -> Int"),
+        Ok((
+            "",
+            Type::TFunction {
+                ann: (),
+                argument: Box::new(Type::TFunction {
+                    ann: (),
+                    argument: Box::new(Type::TInt { ann: () }),
+                    result: Box::new(Type::TBool { ann: () })
+                }),
+                result: Box::new(Type::TInt { ann: () })
+            }
+        ))
+    );
+}
+
+#[test]
+fn test_parse_type_constructors() {
+    let maybe = |arg| Type::TConstructor {
+        ann: (),
+        name: "Maybe".to_string(),
+        args: vec![arg],
+    };
+
+    assert_eq!(
+        parse_type_without_spans("Maybe Int"),
+        Ok(("", maybe(Type::TInt { ann: () })))
+    );
+    assert_eq!(
+        parse_type_without_spans("Maybe (Maybe Bool) -> Int"),
+        Ok((
+            "",
+            Type::TFunction {
+                ann: (),
+                argument: Box::new(maybe(maybe(Type::TBool { ann: () }))),
+                result: Box::new(Type::TInt { ann: () })
+            }
+        ))
+    );
+
+    // type parameters are numbered by position
+    let params = vec!["a".to_string(), "b".to_string()];
+    assert_eq!(
+        parse_type_with_params(&params, ParseInput::new("b -> Maybe a")).map(|(rest, ty)| (
+            *rest.fragment(),
+            crate::types::ty::remove_type_annotation(ty)
+        )),
+        Ok((
+            "",
+            Type::TFunction {
+                ann: (),
+                argument: Box::new(Type::TVar { ann: (), var: 1 }),
+                result: Box::new(maybe(Type::TVar { ann: (), var: 0 }))
+            }
+        ))
+    );
+}
+
+#[test]
+fn test_parse_type_record() {
+    // fields are sorted by name
+    assert_eq!(
+        parse_type_without_spans("{ ok: Bool, name: Int -> Int }"),
+        Ok((
+            "",
+            Type::TRecord {
+                ann: (),
+                fields: vec![
+                    (
+                        "name".to_string(),
+                        Type::TFunction {
+                            ann: (),
+                            argument: Box::new(Type::TInt { ann: () }),
+                            result: Box::new(Type::TInt { ann: () })
+                        }
+                    ),
+                    ("ok".to_string(), Type::TBool { ann: () })
+                ]
+            }
+        ))
+    );
+    assert_eq!(
+        parse_type_without_spans("{}"),
+        Ok((
+            "",
+            Type::TRecord {
+                ann: (),
+                fields: vec![]
+            }
+        ))
+    );
+}
+
+#[test]
+fn test_parse_type_tuple() {
+    assert_eq!(
+        parse_type_without_spans("(Int, Bool -> Bool)"),
+        Ok((
+            "",
+            Type::TTuple {
+                ann: (),
+                items: vec![
+                    Type::TInt { ann: () },
+                    Type::TFunction {
+                        ann: (),
+                        argument: Box::new(Type::TBool { ann: () }),
+                        result: Box::new(Type::TBool { ann: () })
+                    }
+                ]
+            }
+        ))
+    );
+    assert_eq!(
+        parse_type_without_spans("(Int)"),
+        Ok(("", Type::TInt { ann: () }))
+    );
+}
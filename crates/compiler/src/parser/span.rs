@@ -0,0 +1,60 @@
+use nom_locate::LocatedSpan;
+
+// input type for all our parsers, tracks where we are in the source
+pub type ParseInput<'a> = LocatedSpan<&'a str>;
+
+// a single point in the source
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Location {
+    // byte offset from the start of the input
+    pub offset: usize,
+    // 1-indexed line number
+    pub line: u32,
+    // 1-indexed column, counted in chars
+    pub column: usize,
+}
+
+// a range of source, from `start` up to (but not including) `end`
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Span {
+    pub start: Location,
+    pub end: Location,
+}
+
+pub fn location(input: &ParseInput) -> Location {
+    Location {
+        offset: input.location_offset(),
+        line: input.location_line(),
+        column: input.get_utf8_column(),
+    }
+}
+
+// the span covering everything consumed between `before` and `after`
+pub fn span_between(before: &ParseInput, after: &ParseInput) -> Span {
+    Span {
+        start: location(before),
+        end: location(after),
+    }
+}
+
+#[test]
+fn test_span_between() {
+    let input = ParseInput::new("one\ntwo");
+    let (after, _): (ParseInput, ParseInput) =
+        nom::bytes::complete::take::<_, _, nom::error::Error<_>>(5_usize)(input).unwrap();
+    assert_eq!(
+        span_between(&input, &after),
+        Span {
+            start: Location {
+                offset: 0,
+                line: 1,
+                column: 1
+            },
+            end: Location {
+                offset: 5,
+                line: 2,
+                column: 2
+            }
+        }
+    );
+}
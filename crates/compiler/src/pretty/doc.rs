@@ -0,0 +1,147 @@
+// documents that can be laid out at different widths, after Wadler's
+// "a prettier printer"
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Doc {
+    Text(String),
+    // a newline, or `flat` if the group it is in fits on one line
+    Line { flat: &'static str },
+    // lines inside `doc` are indented by `indent` more
+    Nest { indent: usize, doc: Box<Doc> },
+    Concat(Vec<Doc>),
+    // lay out `doc` on one line if it fits, otherwise break all of its own
+    // lines, leaving nested groups to decide for themselves
+    Group(Box<Doc>),
+}
+
+pub fn text<S: Into<String>>(text: S) -> Doc {
+    Doc::Text(text.into())
+}
+
+// a space if it fits
+pub fn line() -> Doc {
+    Doc::Line { flat: " " }
+}
+
+// nothing if it fits
+pub fn softline() -> Doc {
+    Doc::Line { flat: "" }
+}
+
+pub fn nest(indent: usize, doc: Doc) -> Doc {
+    Doc::Nest {
+        indent,
+        doc: Box::new(doc),
+    }
+}
+
+pub fn concat(docs: Vec<Doc>) -> Doc {
+    Doc::Concat(docs)
+}
+
+pub fn group(doc: Doc) -> Doc {
+    Doc::Group(Box::new(doc))
+}
+
+// `docs` with `separator` between each of them
+pub fn join(docs: Vec<Doc>, separator: Doc) -> Doc {
+    let mut joined = vec![];
+    for (index, doc) in docs.into_iter().enumerate() {
+        if index > 0 {
+            joined.push(separator.clone());
+        }
+        joined.push(doc);
+    }
+    concat(joined)
+}
+
+// each item is the indent, whether we're laying out flat, and the doc
+type Pending<'a> = Vec<(usize, bool, &'a Doc)>;
+
+// lay out `doc`, trying to keep lines within `width` characters
+pub fn render(doc: &Doc, width: usize) -> String {
+    let mut output = String::new();
+    let mut column = 0;
+    let mut pending: Pending = vec![(0, false, doc)];
+
+    while let Some((indent, flat, doc)) = pending.pop() {
+        match doc {
+            Doc::Text(text) => {
+                output.push_str(text);
+                column += text.chars().count();
+            }
+            Doc::Line { flat: text } if flat => {
+                output.push_str(text);
+                column += text.chars().count();
+            }
+            Doc::Line { .. } => {
+                output.push('\n');
+                output.push_str(&" ".repeat(indent));
+                column = indent;
+            }
+            Doc::Nest { indent: more, doc } => pending.push((indent + more, flat, doc)),
+            Doc::Concat(docs) => {
+                for doc in docs.iter().rev() {
+                    pending.push((indent, flat, doc));
+                }
+            }
+            Doc::Group(doc) => {
+                let fits = flat || fits(width as isize - column as isize, doc, &pending);
+                pending.push((indent, fits, doc));
+            }
+        }
+    }
+
+    output
+}
+
+// whether `doc` laid out flat fits in `remaining` characters, along with
+// whatever comes after it up to the next line break
+fn fits(mut remaining: isize, doc: &Doc, rest: &Pending) -> bool {
+    let mut docs: Vec<(bool, &Doc)> = vec![(true, doc)];
+    let mut rest = rest.iter().rev();
+
+    while remaining >= 0 {
+        let (flat, doc) = match docs.pop() {
+            Some(next) => next,
+            None => match rest.next() {
+                Some((_, flat, doc)) => (*flat, *doc),
+                None => return true,
+            },
+        };
+        match doc {
+            Doc::Text(text) => remaining -= text.chars().count() as isize,
+            Doc::Line { flat: text } if flat => remaining -= text.chars().count() as isize,
+            Doc::Line { .. } => return true,
+            Doc::Nest { doc, .. } | Doc::Group(doc) => docs.push((flat, doc)),
+            Doc::Concat(children) => {
+                for doc in children.iter().rev() {
+                    docs.push((flat, doc));
+                }
+            }
+        }
+    }
+
+    false
+}
+
+#[test]
+fn test_render() {
+    let doc = group(concat(vec![
+        text("if a then"),
+        nest(2, concat(vec![line(), text("b")])),
+        line(),
+        text("else"),
+        nest(2, concat(vec![line(), text("c")])),
+    ]));
+    assert_eq!(render(&doc, 80), "if a then b else c");
+    assert_eq!(render(&doc, 10), "if a then\n  b\nelse\n  c");
+
+    // text after a group has to fit too
+    let doc = concat(vec![
+        group(join(vec![text("a"), text("b")], line())),
+        text(" and more"),
+    ]);
+    assert_eq!(render(&doc, 12), "a b and more");
+    assert_eq!(render(&doc, 11), "a\nb and more");
+}
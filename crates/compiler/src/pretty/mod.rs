@@ -0,0 +1,4 @@
+pub mod doc;
+pub mod print_expr;
+pub mod print_module;
+pub mod print_type;
@@ -0,0 +1,375 @@
+use super::doc::{concat, group, join, line, nest, render, softline, text, Doc};
+use super::print_type::print_type;
+use crate::parser::parse_expr::op_precedence;
+use crate::types::expr::{Expr, Prim};
+
+// how wide we try to keep lines
+pub const DEFAULT_WIDTH: usize = 80;
+
+// `expr` as source that parses back to the same thing
+pub fn print_expr<Ann>(expr: &Expr<Ann>, width: usize) -> String {
+    render(&expr_to_doc(expr), width)
+}
+
+pub fn expr_to_doc<Ann>(expr: &Expr<Ann>) -> Doc {
+    match expr {
+        Expr::EPrim { prim, .. } => text(prim.to_string()),
+        Expr::EVar { identifier, .. } => text(identifier),
+        Expr::EIf {
+            pred_expr,
+            then_expr,
+            else_expr,
+            ..
+        } => {
+            // `else if` chains stay at the same indentation
+            let else_doc = match **else_expr {
+                Expr::EIf { .. } => concat(vec![text("else "), expr_to_doc(else_expr)]),
+                _ => concat(vec![
+                    text("else"),
+                    nest(2, concat(vec![line(), expr_to_doc(else_expr)])),
+                ]),
+            };
+            group(concat(vec![
+                text("if "),
+                nest(3, expr_to_doc(pred_expr)),
+                text(" then"),
+                nest(2, concat(vec![line(), expr_to_doc(then_expr)])),
+                line(),
+                else_doc,
+            ]))
+        }
+        Expr::ELet {
+            identifier,
+            bound_expr,
+            rest_expr,
+            ..
+        } => let_to_doc("let", identifier, bound_expr, rest_expr),
+        Expr::ELetRec {
+            identifier,
+            bound_expr,
+            rest_expr,
+            ..
+        } => let_to_doc("let rec", identifier, bound_expr, rest_expr),
+        Expr::EBinOp {
+            op,
+            left_expr,
+            right_expr,
+            ..
+        } => {
+            // operators of the same precedence associate to the left
+            let precedence = op_precedence(*op);
+            group(concat(vec![
+                operand_to_doc(left_expr, precedence),
+                nest(
+                    2,
+                    concat(vec![
+                        line(),
+                        text(format!("{} ", op)),
+                        operand_to_doc(right_expr, precedence + 1),
+                    ]),
+                ),
+            ]))
+        }
+        Expr::ELambda {
+            identifier,
+            body_expr,
+            ..
+        } => group(concat(vec![
+            text(format!("\\{} ->", identifier)),
+            nest(2, concat(vec![line(), expr_to_doc(body_expr)])),
+        ])),
+        Expr::EApply { .. } => {
+            let mut args = vec![];
+            let mut function_expr = expr;
+            while let Expr::EApply {
+                function_expr: inner,
+                argument_expr,
+                ..
+            } = function_expr
+            {
+                args.push(argument_expr.as_ref());
+                function_expr = inner;
+            }
+            args.reverse();
+            // a constructor would take the arguments for itself
+            let function_doc = match function_expr {
+                Expr::EConstructor { .. } => parens(expr_to_doc(function_expr)),
+                _ if is_argument(function_expr) || is_negative_int(function_expr) => {
+                    expr_to_doc(function_expr)
+                }
+                _ => parens(expr_to_doc(function_expr)),
+            };
+            apply_to_doc(function_doc, &args)
+        }
+        Expr::EConstructor {
+            constructor, args, ..
+        } => apply_to_doc(text(constructor), &args.iter().collect::<Vec<_>>()),
+        Expr::ECase {
+            scrutinee_expr,
+            arms,
+            ..
+        } => {
+            let mut docs = vec![
+                text("case "),
+                nest(5, expr_to_doc(scrutinee_expr)),
+                text(" of"),
+            ];
+            for (index, arm) in arms.iter().enumerate() {
+                // an arm ending in another `case` would take the rest of our
+                // arms as its own
+                let body_doc = if index + 1 < arms.len() && ends_with_case(&arm.body_expr) {
+                    parens(expr_to_doc(&arm.body_expr))
+                } else {
+                    expr_to_doc(&arm.body_expr)
+                };
+                let arm_doc = group(concat(vec![
+                    text(format!("{} ->", arm.pattern)),
+                    nest(2, concat(vec![line(), body_doc])),
+                ]));
+                // arms line up with each other after the `|`
+                docs.push(match index {
+                    0 => nest(4, concat(vec![line(), arm_doc])),
+                    _ => nest(2, concat(vec![line(), text("| "), nest(2, arm_doc)])),
+                });
+            }
+            group(concat(docs))
+        }
+        Expr::ETuple { items, .. } => group(concat(vec![
+            text("("),
+            nest(2, concat(vec![softline(), items_to_doc(items)])),
+            softline(),
+            text(")"),
+        ])),
+        Expr::ERecord { fields, .. } if fields.is_empty() => text("{}"),
+        Expr::ERecord { fields, .. } => group(concat(vec![
+            text("{"),
+            nest(2, concat(vec![line(), fields_to_doc(fields)])),
+            line(),
+            text("}"),
+        ])),
+        Expr::EFieldAccess {
+            record_expr, field, ..
+        } => {
+            let record_doc = match **record_expr {
+                Expr::EVar { .. }
+                | Expr::EFieldAccess { .. }
+                | Expr::ERecord { .. }
+                | Expr::ERecordUpdate { .. }
+                | Expr::ETuple { .. }
+                | Expr::EAnnotation { .. } => expr_to_doc(record_expr),
+                _ => parens(expr_to_doc(record_expr)),
+            };
+            concat(vec![record_doc, text(format!(".{}", field))])
+        }
+        Expr::ERecordUpdate {
+            record_expr,
+            fields,
+            ..
+        } => group(concat(vec![
+            text("{ "),
+            expr_to_doc(record_expr),
+            text(" |"),
+            nest(2, concat(vec![line(), fields_to_doc(fields)])),
+            line(),
+            text("}"),
+        ])),
+        // `(a, b : T)` annotates the whole tuple
+        Expr::EAnnotation {
+            expr, annotation, ..
+        } => {
+            let expr_doc = match &**expr {
+                Expr::ETuple { items, .. } => items_to_doc(items),
+                _ => expr_to_doc(expr),
+            };
+            group(concat(vec![
+                text("("),
+                nest(1, expr_doc),
+                text(format!(" : {})", print_type(annotation, &[]))),
+            ]))
+        }
+    }
+}
+
+fn let_to_doc<Ann>(
+    keyword: &str,
+    identifier: &str,
+    bound_expr: &Expr<Ann>,
+    rest_expr: &Expr<Ann>,
+) -> Doc {
+    // a chain of `let`s stays at the same indentation
+    group(concat(vec![
+        group(concat(vec![
+            text(format!("{} {} =", keyword, identifier)),
+            nest(2, concat(vec![line(), expr_to_doc(bound_expr)])),
+            line(),
+            text("in"),
+        ])),
+        line(),
+        expr_to_doc(rest_expr),
+    ]))
+}
+
+fn apply_to_doc<Ann>(function_doc: Doc, args: &[&Expr<Ann>]) -> Doc {
+    let mut docs = vec![];
+    for arg in args {
+        docs.push(line());
+        docs.push(if is_argument(arg) {
+            expr_to_doc(arg)
+        } else {
+            parens(expr_to_doc(arg))
+        });
+    }
+    group(concat(vec![function_doc, nest(2, concat(docs))]))
+}
+
+// an operand that binds at least as tightly as `min_precedence`
+fn operand_to_doc<Ann>(expr: &Expr<Ann>, min_precedence: u8) -> Doc {
+    match expr {
+        Expr::EBinOp { op, .. } if op_precedence(*op) >= min_precedence => expr_to_doc(expr),
+        // these go as far as they can, so would take the rest of our
+        // operators with them
+        Expr::EBinOp { .. }
+        | Expr::EIf { .. }
+        | Expr::ELet { .. }
+        | Expr::ELetRec { .. }
+        | Expr::ELambda { .. }
+        | Expr::ECase { .. } => parens(expr_to_doc(expr)),
+        _ => expr_to_doc(expr),
+    }
+}
+
+fn items_to_doc<Ann>(items: &[Expr<Ann>]) -> Doc {
+    join(
+        items.iter().map(expr_to_doc).collect(),
+        concat(vec![text(","), line()]),
+    )
+}
+
+fn fields_to_doc<Ann>(fields: &[(String, Expr<Ann>)]) -> Doc {
+    join(
+        fields
+            .iter()
+            .map(|(name, field_expr)| {
+                concat(vec![text(format!("{}: ", name)), expr_to_doc(field_expr)])
+            })
+            .collect(),
+        concat(vec![text(","), line()]),
+    )
+}
+
+fn parens(doc: Doc) -> Doc {
+    concat(vec![text("("), nest(1, doc), text(")")])
+}
+
+// things that can be passed as arguments without brackets
+// a negative literal would be a subtraction instead
+fn is_argument<Ann>(expr: &Expr<Ann>) -> bool {
+    match expr {
+        Expr::EPrim { .. } => !is_negative_int(expr),
+        Expr::EConstructor { args, .. } => args.is_empty(),
+        Expr::EVar { .. }
+        | Expr::EFieldAccess { .. }
+        | Expr::ERecord { .. }
+        | Expr::ERecordUpdate { .. }
+        | Expr::ETuple { .. }
+        | Expr::EAnnotation { .. } => true,
+        _ => false,
+    }
+}
+
+fn is_negative_int<Ann>(expr: &Expr<Ann>) -> bool {
+    matches!(
+        expr,
+        Expr::EPrim {
+            prim: Prim::PInt { int },
+            ..
+        } if *int < 0
+    )
+}
+
+fn ends_with_case<Ann>(expr: &Expr<Ann>) -> bool {
+    match expr {
+        Expr::ECase { .. } => true,
+        Expr::EIf { else_expr, .. } => ends_with_case(else_expr),
+        Expr::ELet { rest_expr, .. } | Expr::ELetRec { rest_expr, .. } => ends_with_case(rest_expr),
+        Expr::ELambda { body_expr, .. } => ends_with_case(body_expr),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+fn format_expr(input: &str, width: usize) -> String {
+    use crate::parser::parse_expr::parse_my_expr;
+    use crate::parser::span::ParseInput;
+    use crate::types::expr::map_expr;
+
+    let (_, expr) = parse_my_expr(ParseInput::new(input)).unwrap();
+    let printed = print_expr(&expr, width);
+
+    // printing keeps the tree, and printing that again changes nothing
+    let (rest, reparsed) = parse_my_expr(ParseInput::new(&printed)).unwrap();
+    assert_eq!(*rest.fragment(), "", "didn't parse all of {}", printed);
+    assert_eq!(map_expr(reparsed.clone(), |_| ()), map_expr(expr, |_| ()));
+    assert_eq!(print_expr(&reparsed, width), printed);
+
+    printed
+}
+
+#[test]
+fn test_print_expr() {
+    let flat = |input| format_expr(input, DEFAULT_WIDTH);
+
+    assert_eq!(flat("1+2*3"), "1 + 2 * 3");
+    assert_eq!(flat("(1+2)*3"), "(1 + 2) * 3");
+    assert_eq!(flat("1-(2-3)"), "1 - (2 - 3)");
+    assert_eq!(flat("f (-1) (g  a) -1"), "f (-1) (g a) - 1");
+    assert_eq!(flat("Pair (Just 1) Nothing"), "Pair (Just 1) Nothing");
+    assert_eq!(flat("(f Nothing) 1"), "f Nothing 1");
+    assert_eq!(
+        flat("(\\a -> a) 1 + (if a then 1 else 2)"),
+        "(\\a -> a) 1 + (if a then 1 else 2)"
+    );
+    assert_eq!(
+        flat("let rec f = \\a -> f a in f"),
+        "let rec f = \\a -> f a in f"
+    );
+    assert_eq!(flat("{ a: 1, b: \"x\\n\" }.a"), "{ a: 1, b: \"x\\n\" }.a");
+    assert_eq!(flat("{ r | a: (1,2) }"), "{ r | a: (1, 2) }");
+    assert_eq!(flat("(1, True : (Int, Bool))"), "(1, True : (Int, Bool))");
+    assert_eq!(flat("((1, True) : (Int, Bool))"), "(1, True : (Int, Bool))");
+    assert_eq!(
+        flat("case x of A -> (case y of B -> 1 | C -> 2) | D -> 3"),
+        "case x of A -> (case y of B -> 1 | C -> 2) | D -> 3"
+    );
+}
+
+#[test]
+fn test_print_expr_width() {
+    // every line broken still parses back to the same thing
+    for input in [
+        "f (-1) (g a) - 1 * 2 + 3",
+        "let rec f = \\a -> if a then f a else (a, { b: a }) in f",
+        "case x of A -> (case y of B -> 1 | C -> 2) | D -> { r | a: \"s\" }.a",
+        "(1, True : (Int, Bool))",
+        "Pair (Just (1 + 2)) (\\a -> a)",
+    ] {
+        format_expr(input, 1);
+    }
+
+    assert_eq!(
+        format_expr("let a = 1 in let b = if a then 100 else 200 in a + b", 20),
+        "let a = 1 in\nlet b =\n  if a then\n    100\n  else\n    200\nin\na + b"
+    );
+    assert_eq!(
+        format_expr("if a then b else if c then d else e", 16),
+        "if a then\n  b\nelse if c then\n  d\nelse\n  e"
+    );
+    assert_eq!(
+        format_expr("case x of Just a -> a | Nothing -> 0", 20),
+        "case x of\n    Just a -> a\n  | Nothing -> 0"
+    );
+    assert_eq!(
+        format_expr("\\a -> (a, { name: a, ok: True })", 16),
+        "\\a ->\n  (\n    a,\n    {\n      name: a,\n      ok: True\n    }\n  )"
+    );
+}
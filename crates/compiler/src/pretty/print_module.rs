@@ -0,0 +1,101 @@
+use super::doc::{concat, group, line, nest, render, text, Doc};
+use super::print_expr::expr_to_doc;
+use super::print_type::{print_type, print_type_atom};
+use crate::types::module::{Data, Def, Module};
+
+// a whole file, with every `data` before the definitions that use them
+pub fn print_module<Ann>(module: &Module<Ann>, width: usize) -> String {
+    let items: Vec<String> = module
+        .datas
+        .iter()
+        .map(|data| print_data(data, width))
+        .chain(module.defs.iter().map(|def| print_def(def, width)))
+        .collect();
+    match items.is_empty() {
+        true => String::new(),
+        false => format!("{}\n", items.join("\n\n")),
+    }
+}
+
+pub fn print_def<Ann>(def: &Def<Ann>, width: usize) -> String {
+    render(&def_to_doc(def), width)
+}
+
+pub fn print_data<Ann>(data: &Data<Ann>, width: usize) -> String {
+    render(&data_to_doc(data), width)
+}
+
+fn def_to_doc<Ann>(def: &Def<Ann>) -> Doc {
+    let header = match &def.signature {
+        Some(signature) => format!("def {} : {} =", def.identifier, print_type(signature, &[])),
+        None => format!("def {} =", def.identifier),
+    };
+    group(concat(vec![
+        text(header),
+        nest(2, concat(vec![line(), expr_to_doc(&def.expr)])),
+    ]))
+}
+
+// constructors go one per line if they don't all fit
+fn data_to_doc<Ann>(data: &Data<Ann>) -> Doc {
+    let mut header = format!("data {}", data.name);
+    for param in &data.params {
+        header.push(' ');
+        header.push_str(param);
+    }
+
+    let mut constructors = vec![];
+    for (index, constructor) in data.constructors.iter().enumerate() {
+        let mut printed = constructor.name.clone();
+        for field in &constructor.fields {
+            printed.push(' ');
+            printed.push_str(&print_type_atom(field, &data.params));
+        }
+        constructors.push(line());
+        constructors.push(text(match index {
+            0 => format!("= {}", printed),
+            _ => format!("| {}", printed),
+        }));
+    }
+
+    group(concat(vec![text(header), nest(2, concat(constructors))]))
+}
+
+#[cfg(test)]
+fn format_module(input: &str, width: usize) -> String {
+    use crate::parser::parse_module::parse_my_module;
+    use crate::parser::span::ParseInput;
+    use crate::types::module::map_module;
+
+    let (_, module) = parse_my_module(ParseInput::new(input)).unwrap();
+    let printed = print_module(&module, width);
+
+    // printing keeps the tree, and printing that again changes nothing
+    let (_, reparsed) = parse_my_module(ParseInput::new(&printed)).unwrap();
+    assert_eq!(
+        map_module(reparsed.clone(), |_| ()),
+        map_module(module, |_| ())
+    );
+    assert_eq!(print_module(&reparsed, width), printed);
+
+    printed
+}
+
+#[test]
+fn test_print_module() {
+    assert_eq!(format_module("", 80), "");
+    assert_eq!(
+        format_module(
+            "def main : Int = fromMaybe   (Just 1)\ndata Maybe a = Just a | Nothing\n def fromMaybe = \\m -> case m of Just a -> a | Nothing -> 0",
+            80
+        ),
+        "data Maybe a = Just a | Nothing\n\ndef main : Int = fromMaybe (Just 1)\n\ndef fromMaybe = \\m -> case m of Just a -> a | Nothing -> 0\n"
+    );
+    assert_eq!(
+        format_module(
+            "data Tree a = Leaf | Node (Tree a) a (Tree a) | Fn (a -> a)\ndef size : Int = let a = 1 in a",
+            24
+        ),
+        "data Tree a\n  = Leaf\n  | Node (Tree a) a (Tree a)\n  | Fn (a -> a)\n\ndef size : Int =\n  let a = 1 in a\n"
+    );
+}
@@ -0,0 +1,83 @@
+use crate::types::ty::{type_var_name, Type};
+
+// a type as it would be written in the source
+// `params` are the names of the type variables, as in a `data` declaration
+pub fn print_type<Ann>(ty: &Type<Ann>, params: &[String]) -> String {
+    match ty {
+        Type::TInt { .. } => "Int".to_string(),
+        Type::TBool { .. } => "Bool".to_string(),
+        Type::TString { .. } => "String".to_string(),
+        // `->` is right associative, so only arguments need parens
+        Type::TFunction {
+            argument, result, ..
+        } => match **argument {
+            Type::TFunction { .. } => format!(
+                "({}) -> {}",
+                print_type(argument, params),
+                print_type(result, params)
+            ),
+            _ => format!(
+                "{} -> {}",
+                print_type(argument, params),
+                print_type(result, params)
+            ),
+        },
+        Type::TConstructor { name, args, .. } => {
+            let mut printed = name.clone();
+            for arg in args {
+                printed.push(' ');
+                printed.push_str(&print_type_atom(arg, params));
+            }
+            printed
+        }
+        Type::TTuple { items, .. } => {
+            let items: Vec<String> = items.iter().map(|item| print_type(item, params)).collect();
+            format!("({})", items.join(", "))
+        }
+        Type::TRecord { fields, .. } if fields.is_empty() => "{}".to_string(),
+        Type::TRecord { fields, .. } => {
+            let fields: Vec<String> = fields
+                .iter()
+                .map(|(name, ty)| format!("{}: {}", name, print_type(ty, params)))
+                .collect();
+            format!("{{ {} }}", fields.join(", "))
+        }
+        Type::TVar { var, .. } => match params.get(*var as usize) {
+            Some(param) => param.clone(),
+            None => type_var_name(*var),
+        },
+    }
+}
+
+// a type that can be a constructor argument, or a field of a data constructor
+pub fn print_type_atom<Ann>(ty: &Type<Ann>, params: &[String]) -> String {
+    match ty {
+        Type::TFunction { .. } => format!("({})", print_type(ty, params)),
+        Type::TConstructor { args, .. } if !args.is_empty() => {
+            format!("({})", print_type(ty, params))
+        }
+        _ => print_type(ty, params),
+    }
+}
+
+#[test]
+fn test_print_type() {
+    use crate::parser::parse_type::parse_type_with_params;
+    use crate::parser::span::ParseInput;
+
+    let params = vec!["a".to_string(), "b".to_string()];
+    let round_trip = |input: &str| {
+        let (_, ty) = parse_type_with_params(&params, ParseInput::new(input)).unwrap();
+        print_type(&ty, &params)
+    };
+
+    assert_eq!(
+        round_trip("(a -> b) -> Maybe (List a)"),
+        "(a -> b) -> Maybe (List a)"
+    );
+    assert_eq!(
+        round_trip("{ b: (Int,Bool), a: {} }"),
+        "{ a: {}, b: (Int, Bool) }"
+    );
+    assert_eq!(round_trip("Either (Int -> Int) b"), "Either (Int -> Int) b");
+}
@@ -0,0 +1,102 @@
+// split definitions into groups that must be typechecked together, with
+// every group coming after the groups it depends on
+//
+// `dependencies[i]` lists the indexes of the definitions that definition `i`
+// refers to. Definitions that refer to each other, directly or indirectly,
+// end up in the same group. This is Tarjan's algorithm, which finds each
+// group only after everything it depends on.
+pub fn dependency_groups(dependencies: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    let mut state = Tarjan {
+        dependencies,
+        next_index: 0,
+        indexes: vec![None; dependencies.len()],
+        lowlinks: vec![0; dependencies.len()],
+        stack: vec![],
+        on_stack: vec![false; dependencies.len()],
+        groups: vec![],
+    };
+
+    for node in 0..dependencies.len() {
+        if state.indexes[node].is_none() {
+            state.visit(node);
+        }
+    }
+
+    state.groups
+}
+
+struct Tarjan<'a> {
+    dependencies: &'a [Vec<usize>],
+    next_index: usize,
+    // order in which we first visited each node
+    indexes: Vec<Option<usize>>,
+    // smallest index reachable from each node while it's on the stack
+    lowlinks: Vec<usize>,
+    stack: Vec<usize>,
+    on_stack: Vec<bool>,
+    groups: Vec<Vec<usize>>,
+}
+
+impl<'a> Tarjan<'a> {
+    fn visit(&mut self, node: usize) {
+        self.indexes[node] = Some(self.next_index);
+        self.lowlinks[node] = self.next_index;
+        self.next_index += 1;
+        self.stack.push(node);
+        self.on_stack[node] = true;
+
+        for &dependency in &self.dependencies[node] {
+            match self.indexes[dependency] {
+                None => {
+                    self.visit(dependency);
+                    self.lowlinks[node] = self.lowlinks[node].min(self.lowlinks[dependency]);
+                }
+                Some(index) if self.on_stack[dependency] => {
+                    self.lowlinks[node] = self.lowlinks[node].min(index);
+                }
+                Some(_) => {}
+            }
+        }
+
+        // `node` is the first of its group we visited, so everything above it
+        // on the stack is in the same group
+        if Some(self.lowlinks[node]) == self.indexes[node] {
+            let mut group = vec![];
+            loop {
+                let member = self.stack.pop().expect("node should be on the stack");
+                self.on_stack[member] = false;
+                group.push(member);
+                if member == node {
+                    break;
+                }
+            }
+            // keep source order within a group
+            group.sort();
+            self.groups.push(group);
+        }
+    }
+}
+
+#[test]
+fn test_dependency_groups() {
+    // no dependencies, everything is on its own
+    assert_eq!(dependency_groups(&[vec![], vec![]]), vec![vec![0], vec![1]]);
+
+    // 0 uses 1 which uses 2
+    assert_eq!(
+        dependency_groups(&[vec![1], vec![2], vec![]]),
+        vec![vec![2], vec![1], vec![0]]
+    );
+
+    // 0 and 1 use each other, 2 uses itself and 0
+    assert_eq!(
+        dependency_groups(&[vec![1], vec![0], vec![2, 0]]),
+        vec![vec![0, 1], vec![2]]
+    );
+
+    // 3 uses 0, which is in a cycle with 1 and 2
+    assert_eq!(
+        dependency_groups(&[vec![1], vec![2], vec![0], vec![0]]),
+        vec![vec![0, 1, 2], vec![3]]
+    );
+}
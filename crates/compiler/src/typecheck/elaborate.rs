@@ -1,8 +1,20 @@
-use crate::parser::parse_constructors::{bool, int};
-use crate::types::expr::{get_expr_annotation, map_expr, Expr, Prim};
-use crate::types::ty::{map_type, remove_type_annotation, Type};
+use crate::compile::free_variables::free_variables;
+#[cfg(test)]
+use crate::parser::parse_constructors::{
+    bool, int, mk_apply, mk_bin_op, mk_if, mk_lambda, mk_let, string, var,
+};
+use crate::types::builtin::{builtin_type, lookup_builtin};
+use crate::types::expr::{get_expr_annotation, map_expr, CaseArm, Expr, Op, Pattern, Prim};
+use crate::types::module::{Data, DataConstructor, Def, Module};
+use crate::types::ty::{map_type, Type};
 use crate::types::typeerror::TypeError;
 
+use super::dependencies::dependency_groups;
+use super::env::{ConstructorInfo, TypeEnv, TypeScheme};
+use super::exhaustiveness::check_case_patterns;
+use super::suggest::suggest_names;
+use super::unify::{free_type_vars, Substitution};
+
 use std::collections::HashMap;
 
 // entry point, here we create an empty type checking environment
@@ -11,13 +23,296 @@ pub fn elaborate_expr<Ann>(expr: Expr<Ann>) -> Result<Expr<Type<Ann>>, TypeError
 where
     Ann: Clone + Copy,
 {
-    let mut env = HashMap::new();
+    let mut env = TypeEnv::new();
+    let mut subst = Substitution::new();
+
+    let expr_a = infer(&mut env, &mut subst, expr)?;
+
+    // annotations were made before we'd solved everything, so fill in
+    // everything we've learned since
+    Result::Ok(map_expr(expr_a, |ty| subst.apply(ty)))
+}
+
+// typecheck every definition in a module
+// definitions can refer to any other definition, wherever it is in the file,
+// so we check them in dependency order, and check definitions that refer to
+// each other together
+pub fn elaborate_module<Ann>(module: Module<Ann>) -> Result<Module<Type<Ann>>, TypeError<Ann>>
+where
+    Ann: Clone + Copy,
+{
+    let Module { datas, defs } = module;
+
+    let mut env = TypeEnv::new();
+
+    // types can be used before they are declared, so every one of them is in
+    // scope before we look at any fields or signatures
+    for (index, data) in datas.iter().enumerate() {
+        if datas[..index]
+            .iter()
+            .any(|earlier| earlier.name == data.name)
+        {
+            return Result::Err(TypeError::DuplicateType {
+                ann: data.ann,
+                name: data.name.clone(),
+            });
+        }
+        for (index, constructor) in data.constructors.iter().enumerate() {
+            let repeated = data.constructors[..index]
+                .iter()
+                .any(|earlier| earlier.name == constructor.name);
+            if repeated || env.lookup_constructor(&constructor.name).is_some() {
+                return Result::Err(TypeError::DuplicateConstructor {
+                    ann: constructor.ann,
+                    constructor: constructor.name.clone(),
+                });
+            }
+        }
+        env.define_data(data);
+    }
+
+    for data in &datas {
+        for constructor in &data.constructors {
+            for field in &constructor.fields {
+                check_type_is_known(&env, field)?;
+            }
+        }
+    }
+
+    for def in &defs {
+        if let Some(signature) = &def.signature {
+            check_type_is_known(&env, signature)?;
+        }
+    }
+
+    for (index, def) in defs.iter().enumerate() {
+        if defs[..index]
+            .iter()
+            .any(|earlier| earlier.identifier == def.identifier)
+        {
+            return Result::Err(TypeError::DuplicateDefinition {
+                ann: def.ann,
+                identifier: def.identifier.clone(),
+            });
+        }
+    }
+
+    // the other definitions each definition refers to
+    let dependencies: Vec<Vec<usize>> = defs
+        .iter()
+        .map(|def| {
+            free_variables(&def.expr)
+                .iter()
+                .filter_map(|name| defs.iter().position(|other| &other.identifier == name))
+                .collect()
+        })
+        .collect();
+
+    let mut subst = Substitution::new();
+    let mut typed_defs: Vec<Option<Def<Type<Ann>>>> = vec![None; defs.len()];
+
+    for group in dependency_groups(&dependencies) {
+        // while we're checking a group its definitions can only be used at
+        // one type, and are only generalised once we know what that is
+        let def_types: Vec<Type<Ann>> = group
+            .iter()
+            .map(|&index| match &defs[index].signature {
+                Some(signature) => signature.clone(),
+                None => subst.fresh(defs[index].ann),
+            })
+            .collect();
+
+        let bindings = group
+            .iter()
+            .zip(&def_types)
+            .map(|(&index, ty)| {
+                (
+                    defs[index].identifier.clone(),
+                    TypeScheme::monomorphic(ty.clone()),
+                )
+            })
+            .collect();
+
+        let exprs = env.with_bindings(bindings, |env| {
+            group
+                .iter()
+                .zip(&def_types)
+                .map(|(&index, ty)| {
+                    let expr = defs[index].expr.clone();
+                    match defs[index].signature {
+                        Some(_) => check_annotation(env, &mut subst, expr, ty.clone()),
+                        None => check(env, &mut subst, expr, ty.clone()),
+                    }
+                })
+                .collect::<Result<Vec<_>, _>>()
+        })?;
+
+        for ((&index, ty), expr_a) in group.iter().zip(def_types).zip(exprs) {
+            let def = &defs[index];
+            env.define(def.identifier.clone(), generalise(&env, &subst, ty.clone()));
+            typed_defs[index] = Some(Def {
+                ann: map_type(ty, |_| def.ann),
+                identifier: def.identifier.clone(),
+                signature: def.signature.clone().map(annotate_signature),
+                expr: expr_a,
+            });
+        }
+    }
+
+    Result::Ok(Module {
+        datas: datas.into_iter().map(elaborate_data).collect(),
+        defs: typed_defs
+            .into_iter()
+            .map(|def| {
+                let def = def.expect("every definition is in a group");
+                Def {
+                    ann: subst.apply(def.ann),
+                    identifier: def.identifier,
+                    signature: def.signature,
+                    expr: map_expr(def.expr, |ty| subst.apply(ty)),
+                }
+            })
+            .collect(),
+    })
+}
+
+// every type named in `ty` must be declared, and given the right number of
+// arguments
+fn check_type_is_known<Ann>(env: &TypeEnv<Ann>, ty: &Type<Ann>) -> Result<(), TypeError<Ann>>
+where
+    Ann: Clone + Copy,
+{
+    match ty {
+        Type::TConstructor { ann, name, args } => {
+            match env.lookup_data_type(name) {
+                Some(expected) if expected == args.len() => {}
+                Some(expected) => {
+                    return Result::Err(TypeError::TypeArityMismatch {
+                        ann: *ann,
+                        name: name.clone(),
+                        expected,
+                        found: args.len(),
+                    })
+                }
+                None => {
+                    return Result::Err(TypeError::UnknownType {
+                        ann: *ann,
+                        name: name.clone(),
+                    })
+                }
+            }
+            args.iter()
+                .try_for_each(|arg| check_type_is_known(env, arg))
+        }
+        Type::TFunction {
+            argument, result, ..
+        } => {
+            check_type_is_known(env, argument)?;
+            check_type_is_known(env, result)
+        }
+        Type::TTuple { items, .. } => items
+            .iter()
+            .try_for_each(|item| check_type_is_known(env, item)),
+        Type::TRecord { ann, fields } => {
+            check_unique_fields(*ann, fields)?;
+            fields
+                .iter()
+                .try_for_each(|(_, field)| check_type_is_known(env, field))
+        }
+        Type::TInt { .. } | Type::TBool { .. } | Type::TString { .. } | Type::TVar { .. } => {
+            Result::Ok(())
+        }
+    }
+}
+
+// a `data` declaration is annotated with the type it declares, and each
+// constructor with its type as a function from its fields
+fn elaborate_data<Ann>(data: Data<Ann>) -> Data<Type<Ann>>
+where
+    Ann: Clone + Copy,
+{
+    let data_type = |ann| Type::TConstructor {
+        ann,
+        name: data.name.clone(),
+        args: (0..data.params.len())
+            .map(|var| Type::TVar {
+                ann,
+                var: var as u32,
+            })
+            .collect(),
+    };
+
+    Data {
+        ann: data_type(data.ann),
+        name: data.name.clone(),
+        params: data.params.clone(),
+        constructors: data
+            .constructors
+            .iter()
+            .map(|constructor| DataConstructor {
+                ann: constructor.fields.iter().rev().fold(
+                    data_type(constructor.ann),
+                    |result, field| Type::TFunction {
+                        ann: constructor.ann,
+                        argument: Box::new(field.clone()),
+                        result: Box::new(result),
+                    },
+                ),
+                name: constructor.name.clone(),
+                fields: constructor
+                    .fields
+                    .iter()
+                    .cloned()
+                    .map(annotate_signature)
+                    .collect(),
+            })
+            .collect(),
+    }
+}
 
-    infer(&mut env, expr)
+// every node of an elaborated tree is annotated with its type, and the type a
+// signature stands for is the signature itself
+fn annotate_signature<Ann>(signature: Type<Ann>) -> Type<Type<Ann>>
+where
+    Ann: Clone + Copy,
+{
+    match signature.clone() {
+        Type::TInt { .. } => Type::TInt { ann: signature },
+        Type::TBool { .. } => Type::TBool { ann: signature },
+        Type::TString { .. } => Type::TString { ann: signature },
+        Type::TFunction {
+            argument, result, ..
+        } => Type::TFunction {
+            ann: signature,
+            argument: Box::new(annotate_signature(*argument)),
+            result: Box::new(annotate_signature(*result)),
+        },
+        Type::TConstructor { name, args, .. } => Type::TConstructor {
+            ann: signature,
+            name,
+            args: args.into_iter().map(annotate_signature).collect(),
+        },
+        Type::TTuple { items, .. } => Type::TTuple {
+            ann: signature,
+            items: items.into_iter().map(annotate_signature).collect(),
+        },
+        Type::TRecord { fields, .. } => Type::TRecord {
+            ann: signature,
+            fields: fields
+                .into_iter()
+                .map(|(name, ty)| (name, annotate_signature(ty)))
+                .collect(),
+        },
+        Type::TVar { var, .. } => Type::TVar {
+            ann: signature,
+            var,
+        },
+    }
 }
 
 fn infer<Ann>(
-    env: &mut HashMap<String, Type<Ann>>,
+    env: &mut TypeEnv<Ann>,
+    subst: &mut Substitution<Ann>,
     expr: Expr<Ann>,
 ) -> Result<Expr<Type<Ann>>, TypeError<Ann>>
 where
@@ -25,66 +320,694 @@ where
 {
     match expr {
         Expr::EPrim { ann, prim } => Result::Ok(Expr::EPrim {
-            ann: match prim {
-                Prim::PInt { .. } => Type::TInt { ann },
-                Prim::PBool { .. } => Type::TBool { ann },
-            },
-            prim: prim,
+            ann: prim_type(&prim, ann),
+            prim,
         }),
         Expr::EIf {
             ann,
             pred_expr,
             then_expr,
             else_expr,
-        } => infer_if(env, ann, *pred_expr, *then_expr, *else_expr),
+        } => infer_if(env, subst, ann, *pred_expr, *then_expr, *else_expr),
         Expr::ELet {
+            ann,
+            identifier,
+            bound_expr,
+            rest_expr,
+        } => {
+            let bound_a = infer(env, subst, *bound_expr)?;
+            let scheme = generalise(env, subst, get_expr_annotation(bound_a.clone()));
+            // `identifier` is only in scope in `rest_expr`
+            let rest_a = env.with_binding(identifier.clone(), scheme, |env| {
+                infer(env, subst, *rest_expr)
+            })?;
+            Result::Ok(Expr::ELet {
+                ann: map_type(get_expr_annotation(rest_a.clone()), |_| ann),
+                identifier,
+                bound_expr: Box::new(bound_a),
+                rest_expr: Box::new(rest_a),
+            })
+        }
+        // the function can only be used at one type inside its own body, and
+        // is generalised once we know what that is, like a group of
+        // definitions
+        Expr::ELetRec {
+            ann,
             identifier,
             bound_expr,
             rest_expr,
-            ..
         } => {
-            let bound_a = infer(env, *bound_expr)?;
-            env.insert(identifier, get_expr_annotation(bound_a));
-            infer(env, *rest_expr)
-        }
-        Expr::EVar { identifier, ann } => match env.get(&identifier).copied() {
-            Option::Some(ty) => {
-                let type_with_ann = map_type(ty, |_| ann);
-                Result::Ok(Expr::EVar {
-                    ann: type_with_ann,
+            if !matches!(*bound_expr, Expr::ELambda { .. }) {
+                return Result::Err(TypeError::RecursiveNonFunction { ann, identifier });
+            }
+            let bound_type = subst.fresh(ann);
+            let bound_a = env.with_binding(
+                identifier.clone(),
+                TypeScheme::monomorphic(bound_type.clone()),
+                |env| check(env, subst, *bound_expr, bound_type.clone()),
+            )?;
+            let scheme = generalise(env, subst, bound_type);
+            let rest_a = env.with_binding(identifier.clone(), scheme, |env| {
+                infer(env, subst, *rest_expr)
+            })?;
+            Result::Ok(Expr::ELetRec {
+                ann: map_type(get_expr_annotation(rest_a.clone()), |_| ann),
+                identifier,
+                bound_expr: Box::new(bound_a),
+                rest_expr: Box::new(rest_a),
+            })
+        }
+        // anything in scope shadows the builtins
+        Expr::EVar { identifier, ann } => {
+            match (
+                env.lookup(&identifier).cloned(),
+                lookup_builtin(&identifier),
+            ) {
+                (Option::Some(scheme), _) => {
+                    let type_with_ann = map_type(instantiate(subst, ann, scheme), |_| ann);
+                    Result::Ok(Expr::EVar {
+                        ann: type_with_ann,
+                        identifier,
+                    })
+                }
+                (Option::None, Option::Some(builtin)) => Result::Ok(Expr::EVar {
+                    ann: builtin_type(builtin, ann),
                     identifier,
-                })
+                }),
+                (Option::None, Option::None) => Result::Err(TypeError::UnboundVariable {
+                    ann,
+                    suggestions: suggest_names(&identifier, env.names()),
+                    identifier,
+                }),
             }
-            Option::None => panic!("no"),
+        }
+        Expr::EBinOp {
+            ann,
+            op,
+            left_expr,
+            right_expr,
+        } => infer_bin_op(env, subst, ann, op, *left_expr, *right_expr),
+        // we don't know what the argument is yet, so we make up a type
+        // variable and let the body tell us
+        Expr::ELambda {
+            ann,
+            identifier,
+            body_expr,
+        } => {
+            let argument_type = subst.fresh(ann);
+            infer_lambda_body(env, subst, ann, identifier, argument_type, *body_expr, None)
+        }
+        Expr::EApply {
+            ann,
+            function_expr,
+            argument_expr,
+        } => infer_apply(env, subst, ann, *function_expr, *argument_expr),
+        Expr::EConstructor {
+            ann,
+            constructor,
+            args,
+        } => {
+            let (fields, data_type) =
+                instantiate_constructor(env, subst, ann, &constructor, args.len())?;
+            let args_a = args
+                .into_iter()
+                .zip(fields)
+                .map(|(arg, field)| check(env, subst, arg, field))
+                .collect::<Result<Vec<_>, _>>()?;
+            Result::Ok(Expr::EConstructor {
+                ann: map_type(subst.apply(data_type), |_| ann),
+                constructor,
+                args: args_a,
+            })
+        }
+        Expr::ECase {
+            ann,
+            scrutinee_expr,
+            arms,
+        } => infer_case(env, subst, ann, *scrutinee_expr, arms),
+        // the annotation is the type of the whole expression, even if the
+        // expression inside has a subtype of it
+        Expr::EAnnotation {
+            ann,
+            expr,
+            annotation,
+        } => {
+            check_type_is_known(env, &annotation)?;
+            let expr_a = check_annotation(env, subst, *expr, annotation.clone())?;
+            Result::Ok(Expr::EAnnotation {
+                ann: map_type(annotation.clone(), |_| ann),
+                expr: Box::new(expr_a),
+                annotation: annotate_signature(annotation),
+            })
+        }
+        Expr::ETuple { ann, items } => {
+            let items_a = items
+                .into_iter()
+                .map(|item| infer(env, subst, item))
+                .collect::<Result<Vec<_>, _>>()?;
+            Result::Ok(Expr::ETuple {
+                ann: Type::TTuple {
+                    ann,
+                    items: items_a
+                        .iter()
+                        .map(|item_a| get_expr_annotation(item_a.clone()))
+                        .collect(),
+                },
+                items: items_a,
+            })
+        }
+        Expr::ERecord { ann, fields } => {
+            check_unique_fields(ann, &fields)?;
+            let fields_a = fields
+                .into_iter()
+                .map(|(name, field_expr)| Result::Ok((name, infer(env, subst, field_expr)?)))
+                .collect::<Result<Vec<_>, _>>()?;
+            let mut field_types: Vec<(String, Type<Ann>)> = fields_a
+                .iter()
+                .map(|(name, field_a)| (name.clone(), get_expr_annotation(field_a.clone())))
+                .collect();
+            field_types.sort_by(|(a, _), (b, _)| a.cmp(b));
+            Result::Ok(Expr::ERecord {
+                ann: Type::TRecord {
+                    ann,
+                    fields: field_types,
+                },
+                fields: fields_a,
+            })
+        }
+        Expr::EFieldAccess {
+            ann,
+            record_expr,
+            field,
+        } => {
+            let record_a = infer(env, subst, *record_expr)?;
+            let field_type = lookup_field(subst, ann, &field, &record_a)?;
+            Result::Ok(Expr::EFieldAccess {
+                ann: map_type(field_type, |_| ann),
+                record_expr: Box::new(record_a),
+                field,
+            })
+        }
+        // the fields we replace keep their types, so the record does too
+        Expr::ERecordUpdate {
+            ann,
+            record_expr,
+            fields,
+        } => {
+            check_unique_fields(ann, &fields)?;
+            let record_a = infer(env, subst, *record_expr)?;
+            let fields_a = fields
+                .into_iter()
+                .map(|(name, field_expr)| {
+                    let field_type = lookup_field(subst, ann, &name, &record_a)?;
+                    Result::Ok((name, check(env, subst, field_expr, field_type)?))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            Result::Ok(Expr::ERecordUpdate {
+                ann: map_type(subst.apply(get_expr_annotation(record_a.clone())), |_| ann),
+                record_expr: Box::new(record_a),
+                fields: fields_a,
+            })
+        }
+    }
+}
+
+// a record can't mention the same field twice
+fn check_unique_fields<Ann, A>(ann: Ann, fields: &[(String, A)]) -> Result<(), TypeError<Ann>>
+where
+    Ann: Clone + Copy,
+{
+    for (index, (name, _)) in fields.iter().enumerate() {
+        if fields[..index].iter().any(|(earlier, _)| earlier == name) {
+            return Result::Err(TypeError::DuplicateField {
+                ann,
+                field: name.clone(),
+            });
+        }
+    }
+    Result::Ok(())
+}
+
+// the type of `field` in the record `record_a`
+// record types are never inferred from how they're used: there's no way to
+// say "any record with a `name` field", so the record must already be known
+// here, from a literal, a `def` signature or an annotation. `\r -> r.name`
+// is rejected, `\r -> (r : { name: Int }).name` is fine
+fn lookup_field<Ann>(
+    subst: &Substitution<Ann>,
+    ann: Ann,
+    field: &str,
+    record_a: &Expr<Type<Ann>>,
+) -> Result<Type<Ann>, TypeError<Ann>>
+where
+    Ann: Clone + Copy,
+{
+    match subst.apply(get_expr_annotation(record_a.clone())) {
+        Type::TRecord { fields, .. } => match fields.iter().find(|(name, _)| name == field) {
+            Some((_, field_type)) => Result::Ok(field_type.clone()),
+            None => Result::Err(TypeError::MissingField {
+                ann,
+                field: field.to_string(),
+                found: Type::TRecord { ann, fields },
+            }),
+        },
+        found => Result::Err(TypeError::AccessingNonRecord { ann, found }),
+    }
+}
+
+fn prim_type<Ann>(prim: &Prim, ann: Ann) -> Type<Ann>
+where
+    Ann: Clone + Copy,
+{
+    match prim {
+        Prim::PInt { .. } => Type::TInt { ann },
+        Prim::PBool { .. } => Type::TBool { ann },
+        Prim::PString { .. } => Type::TString { ann },
+    }
+}
+
+// the types of a constructor's fields, and the type it constructs
+type ConstructorType<Ann> = (Vec<Type<Ann>>, Type<Ann>);
+
+// the field types and result type of a use of `constructor` with
+// `arg_count` arguments, with fresh type variables for the type's parameters
+fn instantiate_constructor<Ann>(
+    env: &TypeEnv<Ann>,
+    subst: &mut Substitution<Ann>,
+    ann: Ann,
+    constructor: &str,
+    arg_count: usize,
+) -> Result<ConstructorType<Ann>, TypeError<Ann>>
+where
+    Ann: Clone + Copy,
+{
+    let ConstructorInfo {
+        data_name,
+        param_count,
+        fields,
+    } = match env.lookup_constructor(constructor) {
+        Some(info) => info.clone(),
+        None => {
+            return Result::Err(TypeError::UnknownConstructor {
+                ann,
+                constructor: constructor.to_string(),
+            })
+        }
+    };
+
+    if fields.len() != arg_count {
+        return Result::Err(TypeError::ConstructorArityMismatch {
+            ann,
+            constructor: constructor.to_string(),
+            expected: fields.len(),
+            found: arg_count,
+        });
+    }
+
+    let args: Vec<Type<Ann>> = (0..param_count).map(|_| subst.fresh(ann)).collect();
+    let replacements: HashMap<u32, Type<Ann>> = args
+        .iter()
+        .enumerate()
+        .map(|(var, arg)| (var as u32, arg.clone()))
+        .collect();
+
+    let fields = fields
+        .into_iter()
+        .map(|field| replace_type_vars(&replacements, map_type(field, |_| ann)))
+        .collect();
+
+    Result::Ok((
+        fields,
+        Type::TConstructor {
+            ann,
+            name: data_name,
+            args,
+        },
+    ))
+}
+
+// every arm must match the scrutinee and produce the same type
+fn infer_case<Ann>(
+    env: &mut TypeEnv<Ann>,
+    subst: &mut Substitution<Ann>,
+    ann: Ann,
+    scrutinee_expr: Expr<Ann>,
+    arms: Vec<CaseArm<Ann>>,
+) -> Result<Expr<Type<Ann>>, TypeError<Ann>>
+where
+    Ann: Clone + Copy,
+{
+    let scrutinee_a = infer(env, subst, scrutinee_expr)?;
+    let scrutinee_type = get_expr_annotation(scrutinee_a.clone());
+    let result_type = subst.fresh(ann);
+
+    let patterns: Vec<Pattern<Ann>> = arms.iter().map(|arm| arm.pattern.clone()).collect();
+
+    let mut arms_a = vec![];
+    for arm in arms {
+        let mut bindings = vec![];
+        let pattern_a = check_pattern(
+            env,
+            subst,
+            arm.pattern,
+            scrutinee_type.clone(),
+            &mut bindings,
+        )?;
+        // variables bound by a pattern are only in scope in its arm
+        let body_a = env.with_bindings(bindings, |env| {
+            check(env, subst, arm.body_expr, result_type.clone())
+        })?;
+        arms_a.push(CaseArm {
+            pattern: pattern_a,
+            body_expr: body_a,
+        });
+    }
+
+    // only once we know every pattern has the right type
+    check_case_patterns(env, ann, &patterns)?;
+
+    Result::Ok(Expr::ECase {
+        ann: map_type(subst.apply(result_type), |_| ann),
+        scrutinee_expr: Box::new(scrutinee_a),
+        arms: arms_a,
+    })
+}
+
+// check `pattern` matches values of `expected_type`, collecting the variables
+// it binds
+fn check_pattern<Ann>(
+    env: &TypeEnv<Ann>,
+    subst: &mut Substitution<Ann>,
+    pattern: Pattern<Ann>,
+    expected_type: Type<Ann>,
+    bindings: &mut Vec<(String, TypeScheme<Ann>)>,
+) -> Result<Pattern<Type<Ann>>, TypeError<Ann>>
+where
+    Ann: Clone + Copy,
+{
+    match pattern {
+        Pattern::PVar { ann, identifier } => {
+            bindings.push((
+                identifier.clone(),
+                TypeScheme::monomorphic(expected_type.clone()),
+            ));
+            Result::Ok(Pattern::PVar {
+                ann: map_type(subst.apply(expected_type), |_| ann),
+                identifier,
+            })
+        }
+        Pattern::PWildcard { ann } => Result::Ok(Pattern::PWildcard {
+            ann: map_type(subst.apply(expected_type), |_| ann),
+        }),
+        Pattern::PLiteral { ann, prim } => {
+            let literal_type = prim_type(&prim, ann);
+            subst.unify(expected_type, literal_type.clone())?;
+            Result::Ok(Pattern::PLiteral {
+                ann: literal_type,
+                prim,
+            })
+        }
+        // we know how many items the tuple has, but nothing else about them
+        Pattern::PTuple { ann, items } => {
+            let item_types: Vec<Type<Ann>> = items.iter().map(|_| subst.fresh(ann)).collect();
+            let tuple_type = Type::TTuple {
+                ann,
+                items: item_types.clone(),
+            };
+            subst.unify(expected_type, tuple_type.clone())?;
+            let items_a = items
+                .into_iter()
+                .zip(item_types)
+                .map(|(item, item_type)| check_pattern(env, subst, item, item_type, bindings))
+                .collect::<Result<Vec<_>, _>>()?;
+            Result::Ok(Pattern::PTuple {
+                ann: map_type(subst.apply(tuple_type), |_| ann),
+                items: items_a,
+            })
+        }
+        Pattern::PConstructor {
+            ann,
+            constructor,
+            args,
+        } => {
+            let (fields, data_type) =
+                instantiate_constructor(env, subst, ann, &constructor, args.len())?;
+            subst.unify(expected_type, data_type.clone())?;
+            let args_a = args
+                .into_iter()
+                .zip(fields)
+                .map(|(arg, field)| check_pattern(env, subst, arg, field, bindings))
+                .collect::<Result<Vec<_>, _>>()?;
+            Result::Ok(Pattern::PConstructor {
+                ann: map_type(subst.apply(data_type), |_| ann),
+                constructor,
+                args: args_a,
+            })
+        }
+    }
+}
+
+// a scheme for `ty` that is polymorphic in every type variable that isn't
+// mentioned in the environment
+fn generalise<Ann>(env: &TypeEnv<Ann>, subst: &Substitution<Ann>, ty: Type<Ann>) -> TypeScheme<Ann>
+where
+    Ann: Clone + Copy,
+{
+    let env_vars: Vec<u32> = env
+        .schemes()
+        .flat_map(|scheme| {
+            free_type_vars(&subst.apply(scheme.ty.clone()))
+                .into_iter()
+                .filter(|var| !scheme.vars.contains(var))
+                .collect::<Vec<_>>()
+        })
+        .collect();
+
+    let ty = subst.apply(ty);
+    let vars = free_type_vars(&ty)
+        .into_iter()
+        .filter(|var| !env_vars.contains(var))
+        .collect();
+
+    TypeScheme { vars, ty }
+}
+
+// a copy of `scheme` with fresh type variables for everything it is
+// polymorphic in
+fn instantiate<Ann>(subst: &mut Substitution<Ann>, ann: Ann, scheme: TypeScheme<Ann>) -> Type<Ann>
+where
+    Ann: Clone + Copy,
+{
+    let fresh_vars: HashMap<u32, Type<Ann>> = scheme
+        .vars
+        .iter()
+        .map(|var| (*var, subst.fresh(ann)))
+        .collect();
+
+    replace_type_vars(&fresh_vars, scheme.ty)
+}
+
+fn replace_type_vars<Ann>(replacements: &HashMap<u32, Type<Ann>>, ty: Type<Ann>) -> Type<Ann>
+where
+    Ann: Clone + Copy,
+{
+    match ty {
+        Type::TVar { ann, var } => match replacements.get(&var) {
+            Some(replacement) => replacement.clone(),
+            None => Type::TVar { ann, var },
+        },
+        Type::TFunction {
+            ann,
+            argument,
+            result,
+        } => Type::TFunction {
+            ann,
+            argument: Box::new(replace_type_vars(replacements, *argument)),
+            result: Box::new(replace_type_vars(replacements, *result)),
+        },
+        Type::TConstructor { ann, name, args } => Type::TConstructor {
+            ann,
+            name,
+            args: args
+                .into_iter()
+                .map(|arg| replace_type_vars(replacements, arg))
+                .collect(),
+        },
+        Type::TTuple { ann, items } => Type::TTuple {
+            ann,
+            items: items
+                .into_iter()
+                .map(|item| replace_type_vars(replacements, item))
+                .collect(),
+        },
+        Type::TRecord { ann, fields } => Type::TRecord {
+            ann,
+            fields: fields
+                .into_iter()
+                .map(|(name, ty)| (name, replace_type_vars(replacements, ty)))
+                .collect(),
         },
+        other => other,
     }
 }
 
+fn infer_apply<Ann>(
+    env: &mut TypeEnv<Ann>,
+    subst: &mut Substitution<Ann>,
+    ann: Ann,
+    function_expr: Expr<Ann>,
+    argument_expr: Expr<Ann>,
+) -> Result<Expr<Type<Ann>>, TypeError<Ann>>
+where
+    Ann: Clone + Copy,
+{
+    let function_a = infer(env, subst, function_expr)?;
+
+    let (argument_type, result_type) = match subst.apply(get_expr_annotation(function_a.clone())) {
+        Type::TFunction {
+            argument, result, ..
+        } => (*argument, *result),
+        // we don't know what this is yet, so it had better be a function
+        Type::TVar { ann: var_ann, var } => {
+            let argument_type = subst.fresh(ann);
+            let result_type = subst.fresh(ann);
+            subst.unify(
+                Type::TVar { ann: var_ann, var },
+                Type::TFunction {
+                    ann,
+                    argument: Box::new(argument_type.clone()),
+                    result: Box::new(result_type.clone()),
+                },
+            )?;
+            (argument_type, result_type)
+        }
+        found => return Result::Err(TypeError::ApplyingNonFunction { ann, found }),
+    };
+
+    let argument_a = check(env, subst, argument_expr, argument_type)?;
+
+    Result::Ok(Expr::EApply {
+        ann: map_type(subst.apply(result_type), |_| ann),
+        function_expr: Box::new(function_a),
+        argument_expr: Box::new(argument_a),
+    })
+}
+
+// typecheck a lambda body once we know the type of its argument, checking it
+// against `expected_result` if we have one
+fn infer_lambda_body<Ann>(
+    env: &mut TypeEnv<Ann>,
+    subst: &mut Substitution<Ann>,
+    ann: Ann,
+    identifier: String,
+    argument_type: Type<Ann>,
+    body_expr: Expr<Ann>,
+    expected_result: Option<Type<Ann>>,
+) -> Result<Expr<Type<Ann>>, TypeError<Ann>>
+where
+    Ann: Clone + Copy,
+{
+    // arguments are never generalised, every use must agree on one type
+    let body_a = env.with_binding(
+        identifier.clone(),
+        TypeScheme::monomorphic(argument_type.clone()),
+        |env| match expected_result {
+            Some(result_type) => check(env, subst, body_expr, result_type),
+            None => infer(env, subst, body_expr),
+        },
+    )?;
+
+    Result::Ok(Expr::ELambda {
+        ann: Type::TFunction {
+            ann,
+            argument: Box::new(argument_type),
+            result: Box::new(get_expr_annotation(body_a.clone())),
+        },
+        identifier,
+        body_expr: Box::new(body_a),
+    })
+}
+
+fn infer_bin_op<Ann>(
+    env: &mut TypeEnv<Ann>,
+    subst: &mut Substitution<Ann>,
+    ann: Ann,
+    op: Op,
+    left_expr: Expr<Ann>,
+    right_expr: Expr<Ann>,
+) -> Result<Expr<Type<Ann>>, TypeError<Ann>>
+where
+    Ann: Clone + Copy,
+{
+    let (left_a, right_a, result_type) = match op {
+        Op::Add | Op::Subtract | Op::Multiply | Op::Divide | Op::Modulo => (
+            check(env, subst, left_expr, Type::TInt { ann })?,
+            check(env, subst, right_expr, Type::TInt { ann })?,
+            Type::TInt { ann },
+        ),
+        Op::LessThan | Op::LessThanOrEqual | Op::GreaterThan | Op::GreaterThanOrEqual => (
+            check(env, subst, left_expr, Type::TInt { ann })?,
+            check(env, subst, right_expr, Type::TInt { ann })?,
+            Type::TBool { ann },
+        ),
+        // both backends can only compare ints, bools and strings, and need to
+        // know which they have, as wasm compares strings differently
+        Op::Equals | Op::NotEquals => {
+            let left_a = infer(env, subst, left_expr)?;
+            let left_type = get_expr_annotation(left_a.clone());
+            let right_a = check(env, subst, right_expr, left_type.clone())?;
+            match subst.apply(left_type) {
+                Type::TInt { .. } | Type::TBool { .. } | Type::TString { .. } => {}
+                found => return Result::Err(TypeError::NotComparable { ann, found }),
+            }
+            (left_a, right_a, Type::TBool { ann })
+        }
+        Op::And | Op::Or => (
+            check(env, subst, left_expr, Type::TBool { ann })?,
+            check(env, subst, right_expr, Type::TBool { ann })?,
+            Type::TBool { ann },
+        ),
+        Op::Concat => (
+            check(env, subst, left_expr, Type::TString { ann })?,
+            check(env, subst, right_expr, Type::TString { ann })?,
+            Type::TString { ann },
+        ),
+    };
+
+    Result::Ok(Expr::EBinOp {
+        ann: result_type,
+        op,
+        left_expr: Box::new(left_a),
+        right_expr: Box::new(right_a),
+    })
+}
+
 fn infer_if<Ann>(
-    env: &mut HashMap<String, Type<Ann>>,
+    env: &mut TypeEnv<Ann>,
+    subst: &mut Substitution<Ann>,
     ann: Ann,
     pred_expr: Expr<Ann>,
     then_expr: Expr<Ann>,
     else_expr: Expr<Ann>,
 ) -> Result<Expr<Type<Ann>>, TypeError<Ann>>
 where
-    Ann: Copy,
+    Ann: Clone + Copy,
 {
-    Result::map_err(
-        check(env, pred_expr, Type::TBool { ann }),
+    let pred_a = Result::map_err(
+        check(env, subst, pred_expr, Type::TBool { ann }),
         |err| match err {
-            TypeError::TypeMismatch { type_b, .. } => TypeError::PredicateShouldBeBool {
-                ann: ann,
-                found: type_b,
-            },
+            TypeError::TypeMismatch { type_b, .. } => {
+                TypeError::PredicateShouldBeBool { ann, found: type_b }
+            }
             other => other,
         },
     )?;
 
-    let then_a = infer(env, then_expr)?;
+    let then_a = infer(env, subst, then_expr)?;
+    let then_type = get_expr_annotation(then_a.clone());
 
-    Result::map_err(
-        check(env, else_expr, get_expr_annotation(then_a)),
+    let else_a = Result::map_err(
+        check(env, subst, else_expr, then_type.clone()),
         |err| match err {
             TypeError::TypeMismatch { type_a, type_b } => TypeError::MismatchedIfBranches {
                 ann,
@@ -93,48 +1016,214 @@ where
             },
             other => other,
         },
-    )
+    )?;
+
+    Result::Ok(Expr::EIf {
+        ann: map_type(subst.apply(then_type), |_| ann),
+        pred_expr: Box::new(pred_a),
+        then_expr: Box::new(then_a),
+        else_expr: Box::new(else_a),
+    })
 }
 
 fn check<Ann>(
-    env: &mut HashMap<String, Type<Ann>>,
+    env: &mut TypeEnv<Ann>,
+    subst: &mut Substitution<Ann>,
     expr: Expr<Ann>,
     expected_type: Type<Ann>,
 ) -> Result<Expr<Type<Ann>>, TypeError<Ann>>
 where
     Ann: Clone + Copy,
 {
-    let expr_a = infer(env, expr)?;
+    if let Some(result) = check_lambda(env, subst, &expr, &expected_type) {
+        return result;
+    }
+
+    let expr_a = infer(env, subst, expr)?;
     let found_type = get_expr_annotation(expr_a.clone());
-    let combined_type = subtype(expected_type, found_type)?;
-    // when we're doing real subtyping we should probably munge `combined_type`
-    Result::Ok(map_expr(expr_a, |_| combined_type))
+    // `expr_a` keeps the type we found rather than the one we expected, a
+    // compiled record finds its fields by name, so one with extra fields
+    // works wherever one with fewer is expected without being converted
+    subtype(subst, expected_type, found_type)?;
+    Result::Ok(expr_a)
 }
 
-fn subtype<Ann>(type_a: Type<Ann>, type_b: Type<Ann>) -> Result<Type<Ann>, TypeError<Ann>>
+// like `check`, but for a type written in the source, so if the types don't
+// match we blame the whole annotation rather than whichever part of it
+// didn't match
+fn check_annotation<Ann>(
+    env: &mut TypeEnv<Ann>,
+    subst: &mut Substitution<Ann>,
+    expr: Expr<Ann>,
+    annotated: Type<Ann>,
+) -> Result<Expr<Type<Ann>>, TypeError<Ann>>
 where
     Ann: Clone + Copy,
 {
-    if remove_type_annotation(type_a.clone()) == remove_type_annotation(type_b.clone()) {
-        Result::Ok(type_a)
-    } else {
-        Result::Err(TypeError::TypeMismatch { type_a, type_b })
+    let expr_a = infer_annotated(env, subst, expr, &annotated)?;
+    let found = get_expr_annotation(expr_a.clone());
+    match subtype(subst, annotated.clone(), found.clone()) {
+        Result::Err(TypeError::TypeMismatch { .. }) => Result::Err(TypeError::AnnotationMismatch {
+            annotated: subst.apply(annotated),
+            found: subst.apply(found),
+        }),
+        Result::Err(error) => Result::Err(error),
+        Result::Ok(_) => Result::Ok(expr_a),
     }
 }
 
-#[test]
-fn test_basic_prim_values() {
-    let int_expr = int(1);
-
-    assert_eq!(
-        Result::map(elaborate_expr(int_expr.clone()), get_expr_annotation),
-        Result::Ok(Type::TInt { ann: () })
-    );
-
-    let bool_expr = bool(true);
-
-    assert_eq!(
-        Result::map(elaborate_expr(bool_expr.clone()), get_expr_annotation),
+// like `infer`, but lambdas take their argument types from `annotated`, so
+// their bodies can use them. The result is compared with the annotation
+// afterwards, so a body that doesn't match is blamed on the whole annotation
+fn infer_annotated<Ann>(
+    env: &mut TypeEnv<Ann>,
+    subst: &mut Substitution<Ann>,
+    expr: Expr<Ann>,
+    annotated: &Type<Ann>,
+) -> Result<Expr<Type<Ann>>, TypeError<Ann>>
+where
+    Ann: Clone + Copy,
+{
+    match (expr, subst.apply(annotated.clone())) {
+        (
+            Expr::ELambda {
+                ann,
+                identifier,
+                body_expr,
+            },
+            Type::TFunction {
+                argument, result, ..
+            },
+        ) => {
+            let body_a = env.with_binding(
+                identifier.clone(),
+                TypeScheme::monomorphic(*argument.clone()),
+                |env| infer_annotated(env, subst, *body_expr, &result),
+            )?;
+            Result::Ok(Expr::ELambda {
+                ann: Type::TFunction {
+                    ann,
+                    argument,
+                    result: Box::new(get_expr_annotation(body_a.clone())),
+                },
+                identifier,
+                body_expr: Box::new(body_a),
+            })
+        }
+        (expr, _) => infer(env, subst, expr),
+    }
+}
+
+// lambdas get their argument type from the type we expect, so `None` if
+// `expr` isn't one or we don't expect a function
+fn check_lambda<Ann>(
+    env: &mut TypeEnv<Ann>,
+    subst: &mut Substitution<Ann>,
+    expr: &Expr<Ann>,
+    expected_type: &Type<Ann>,
+) -> Option<Result<Expr<Type<Ann>>, TypeError<Ann>>>
+where
+    Ann: Clone + Copy,
+{
+    match (expr, subst.apply(expected_type.clone())) {
+        (
+            Expr::ELambda {
+                ann,
+                identifier,
+                body_expr,
+            },
+            Type::TFunction {
+                argument, result, ..
+            },
+        ) => Some(infer_lambda_body(
+            env,
+            subst,
+            *ann,
+            identifier.clone(),
+            *argument,
+            *body_expr.clone(),
+            Some(*result),
+        )),
+        _ => None,
+    }
+}
+
+// check a `type_b` can be used wherever a `type_a` is expected, returning the
+// type it's used at
+// a record can have more fields than the one we expect, and so functions
+// can take records with fewer fields than the ones we expect
+fn subtype<Ann>(
+    subst: &mut Substitution<Ann>,
+    type_a: Type<Ann>,
+    type_b: Type<Ann>,
+) -> Result<Type<Ann>, TypeError<Ann>>
+where
+    Ann: Clone + Copy,
+{
+    match (subst.apply(type_a.clone()), subst.apply(type_b.clone())) {
+        (
+            Type::TRecord {
+                fields: fields_a, ..
+            },
+            Type::TRecord {
+                fields: fields_b, ..
+            },
+        ) => {
+            for (name, field_a) in fields_a {
+                match fields_b.iter().find(|(other, _)| *other == name) {
+                    Some((_, field_b)) => {
+                        subtype(subst, field_a, field_b.clone())?;
+                    }
+                    None => {
+                        return Result::Err(TypeError::TypeMismatch {
+                            type_a: subst.apply(type_a),
+                            type_b: subst.apply(type_b),
+                        })
+                    }
+                }
+            }
+        }
+        (
+            Type::TFunction {
+                argument: argument_a,
+                result: result_a,
+                ..
+            },
+            Type::TFunction {
+                argument: argument_b,
+                result: result_b,
+                ..
+            },
+        ) => {
+            // the function we found will be given `argument_a`s
+            subtype(subst, *argument_b, *argument_a)?;
+            subtype(subst, *result_a, *result_b)?;
+        }
+        (Type::TTuple { items: items_a, .. }, Type::TTuple { items: items_b, .. })
+            if items_a.len() == items_b.len() =>
+        {
+            for (item_a, item_b) in items_a.into_iter().zip(items_b) {
+                subtype(subst, item_a, item_b)?;
+            }
+        }
+        _ => subst.unify(type_a.clone(), type_b)?,
+    }
+    Result::Ok(subst.apply(type_a))
+}
+
+#[test]
+fn test_basic_prim_values() {
+    let int_expr = int((), 1);
+
+    assert_eq!(
+        Result::map(elaborate_expr(int_expr.clone()), get_expr_annotation),
+        Result::Ok(Type::TInt { ann: () })
+    );
+
+    let bool_expr = bool((), true);
+
+    assert_eq!(
+        Result::map(elaborate_expr(bool_expr.clone()), get_expr_annotation),
         Result::Ok(Type::TBool { ann: () })
     );
 
@@ -184,7 +1273,7 @@ fn test_basic_prim_values() {
 
 #[test]
 fn test_let_and_var() {
-    let int_expr = int(1);
+    let int_expr = int((), 1);
 
     let let_and_fetch = Expr::ELet {
         ann: (),
@@ -201,3 +1290,1138 @@ fn test_let_and_var() {
         Result::Ok(Type::TInt { ann: () })
     );
 }
+
+#[test]
+fn test_errors_carry_source_spans() {
+    use crate::parser::parse_expr::parse_my_expr;
+    use crate::parser::span::ParseInput;
+
+    let (_, parsed) = parse_my_expr(ParseInput::new("if 100 then 1 else 2")).unwrap();
+
+    match elaborate_expr(parsed) {
+        Err(TypeError::PredicateShouldBeBool { ann, found }) => {
+            // the whole `if` expression
+            assert_eq!((ann.start.offset, ann.end.offset), (0, 20));
+            // the predicate we found instead of a bool
+            match found {
+                Type::TInt { ann } => assert_eq!((ann.start.offset, ann.end.offset), (3, 6)),
+                other => panic!("expected TInt, got {:?}", other),
+            }
+        }
+        other => panic!("expected PredicateShouldBeBool, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_elaborate_keeps_structure() {
+    let if_expr = Expr::EIf {
+        ann: (),
+        pred_expr: Box::new(bool((), true)),
+        then_expr: Box::new(int((), 1)),
+        else_expr: Box::new(int((), 2)),
+    };
+
+    assert_eq!(
+        elaborate_expr(if_expr),
+        Result::Ok(Expr::EIf {
+            ann: Type::TInt { ann: () },
+            pred_expr: Box::new(bool(Type::TBool { ann: () }, true)),
+            then_expr: Box::new(int(Type::TInt { ann: () }, 1)),
+            else_expr: Box::new(int(Type::TInt { ann: () }, 2)),
+        })
+    );
+
+    let let_expr = Expr::ELet {
+        ann: (),
+        identifier: "a".to_string(),
+        bound_expr: Box::new(bool((), true)),
+        rest_expr: Box::new(int((), 1)),
+    };
+
+    assert_eq!(
+        elaborate_expr(let_expr),
+        Result::Ok(Expr::ELet {
+            ann: Type::TInt { ann: () },
+            identifier: "a".to_string(),
+            bound_expr: Box::new(bool(Type::TBool { ann: () }, true)),
+            rest_expr: Box::new(int(Type::TInt { ann: () }, 1)),
+        })
+    );
+}
+
+#[test]
+fn test_unbound_variable() {
+    let missing_var = Expr::EVar {
+        ann: (),
+        identifier: "a".to_string(),
+    };
+
+    assert_eq!(
+        elaborate_expr(missing_var),
+        Result::Err(TypeError::UnboundVariable {
+            ann: (),
+            identifier: "a".to_string(),
+            suggestions: vec![]
+        })
+    );
+
+    let typo_var = Expr::ELet {
+        ann: (),
+        identifier: "horse".to_string(),
+        bound_expr: Box::new(int((), 1)),
+        rest_expr: Box::new(Expr::EVar {
+            ann: (),
+            identifier: "hose".to_string(),
+        }),
+    };
+
+    assert_eq!(
+        elaborate_expr(typo_var),
+        Result::Err(TypeError::UnboundVariable {
+            ann: (),
+            identifier: "hose".to_string(),
+            suggestions: vec!["horse".to_string()]
+        })
+    );
+}
+
+#[test]
+fn test_let_bindings_do_not_leak() {
+    // if True then (let a = 1 in a) else a
+    let leak_into_other_branch = Expr::EIf {
+        ann: (),
+        pred_expr: Box::new(bool((), true)),
+        then_expr: Box::new(mk_let((), "a", int((), 1), var((), "a"))),
+        else_expr: Box::new(var((), "a")),
+    };
+
+    assert_eq!(
+        elaborate_expr(leak_into_other_branch),
+        Result::Err(TypeError::UnboundVariable {
+            ann: (),
+            identifier: "a".to_string(),
+            suggestions: vec![]
+        })
+    );
+
+    // let b = (let a = 1 in a) in a
+    let leak_into_sibling = mk_let(
+        (),
+        "b",
+        mk_let((), "a", int((), 1), var((), "a")),
+        var((), "a"),
+    );
+
+    assert_eq!(
+        elaborate_expr(leak_into_sibling),
+        Result::Err(TypeError::UnboundVariable {
+            ann: (),
+            identifier: "a".to_string(),
+            suggestions: vec!["b".to_string()]
+        })
+    );
+
+    // let a = (let b = 1 in b) in b
+    let leak_out_of_bound_expr = mk_let(
+        (),
+        "a",
+        mk_let((), "b", int((), 1), var((), "b")),
+        var((), "b"),
+    );
+
+    assert_eq!(
+        elaborate_expr(leak_out_of_bound_expr),
+        Result::Err(TypeError::UnboundVariable {
+            ann: (),
+            identifier: "b".to_string(),
+            suggestions: vec!["a".to_string()]
+        })
+    );
+}
+
+#[test]
+fn test_let_shadowing() {
+    // let a = 1 in let a = True in a
+    let shadowed = mk_let(
+        (),
+        "a",
+        int((), 1),
+        mk_let((), "a", bool((), true), var((), "a")),
+    );
+
+    assert_eq!(
+        Result::map(elaborate_expr(shadowed), get_expr_annotation),
+        Result::Ok(Type::TBool { ann: () })
+    );
+
+    // let a = 1 in let b = (let a = True in a) in a
+    let shadow_ends = mk_let(
+        (),
+        "a",
+        int((), 1),
+        mk_let(
+            (),
+            "b",
+            mk_let((), "a", bool((), true), var((), "a")),
+            var((), "a"),
+        ),
+    );
+
+    assert_eq!(
+        Result::map(elaborate_expr(shadow_ends), get_expr_annotation),
+        Result::Ok(Type::TInt { ann: () })
+    );
+
+    // if (let a = True in a) then (let a = 1 in a) else 2
+    let shadow_in_branches = Expr::EIf {
+        ann: (),
+        pred_expr: Box::new(mk_let((), "a", bool((), true), var((), "a"))),
+        then_expr: Box::new(mk_let((), "a", int((), 1), var((), "a"))),
+        else_expr: Box::new(int((), 2)),
+    };
+
+    assert_eq!(
+        Result::map(elaborate_expr(shadow_in_branches), get_expr_annotation),
+        Result::Ok(Type::TInt { ann: () })
+    );
+}
+
+#[test]
+fn test_bin_ops() {
+    let elaborate_type = |expr| Result::map(elaborate_expr(expr), get_expr_annotation);
+
+    assert_eq!(
+        elaborate_type(mk_bin_op((), Op::Add, int((), 1), int((), 2))),
+        Result::Ok(Type::TInt { ann: () })
+    );
+
+    assert_eq!(
+        elaborate_type(mk_bin_op((), Op::LessThan, int((), 1), int((), 2))),
+        Result::Ok(Type::TBool { ann: () })
+    );
+
+    assert_eq!(
+        elaborate_type(mk_bin_op((), Op::Equals, bool((), true), bool((), false))),
+        Result::Ok(Type::TBool { ann: () })
+    );
+
+    assert_eq!(
+        elaborate_type(mk_bin_op((), Op::Or, bool((), true), bool((), false))),
+        Result::Ok(Type::TBool { ann: () })
+    );
+
+    assert_eq!(
+        elaborate_type(mk_bin_op((), Op::Multiply, int((), 1), bool((), true))),
+        Result::Err(TypeError::TypeMismatch {
+            type_a: Type::TInt { ann: () },
+            type_b: Type::TBool { ann: () }
+        })
+    );
+
+    assert_eq!(
+        elaborate_type(mk_bin_op((), Op::And, int((), 1), bool((), true))),
+        Result::Err(TypeError::TypeMismatch {
+            type_a: Type::TBool { ann: () },
+            type_b: Type::TInt { ann: () }
+        })
+    );
+
+    assert_eq!(
+        elaborate_type(mk_bin_op((), Op::NotEquals, int((), 1), bool((), true))),
+        Result::Err(TypeError::TypeMismatch {
+            type_a: Type::TInt { ann: () },
+            type_b: Type::TBool { ann: () }
+        })
+    );
+
+    // we don't know what `a` and `b` are, so can't compare them
+    assert!(matches!(
+        elaborate_type(mk_lambda(
+            (),
+            "a",
+            mk_lambda(
+                (),
+                "b",
+                mk_bin_op((), Op::Equals, var((), "a"), var((), "b"))
+            )
+        )),
+        Result::Err(TypeError::NotComparable {
+            found: Type::TVar { .. },
+            ..
+        })
+    ));
+    assert_eq!(
+        elaborate_type(mk_bin_op(
+            (),
+            Op::Equals,
+            mk_lambda((), "a", var((), "a")),
+            mk_lambda((), "a", var((), "a"))
+        ))
+        .map_err(|err| matches!(err, TypeError::NotComparable { .. })),
+        Result::Err(true)
+    );
+}
+
+#[test]
+fn test_lambda_and_apply() {
+    let elaborate_type = |expr| Result::map(elaborate_expr(expr), get_expr_annotation);
+
+    // (\a -> a + 1) 41
+    assert_eq!(
+        elaborate_type(mk_apply(
+            (),
+            mk_lambda((), "a", mk_bin_op((), Op::Add, var((), "a"), int((), 1))),
+            int((), 41)
+        )),
+        Result::Ok(Type::TInt { ann: () })
+    );
+
+    // (\a -> a) True
+    assert_eq!(
+        elaborate_type(mk_apply(
+            (),
+            mk_lambda((), "a", var((), "a")),
+            bool((), true)
+        )),
+        Result::Ok(Type::TBool { ann: () })
+    );
+
+    // the type of the lambda itself
+    let typed_lambda = elaborate_expr(mk_apply(
+        (),
+        mk_lambda((), "a", mk_bin_op((), Op::Equals, var((), "a"), int((), 1))),
+        int((), 41),
+    ));
+    match typed_lambda {
+        Result::Ok(Expr::EApply { function_expr, .. }) => assert_eq!(
+            get_expr_annotation(*function_expr),
+            Type::TFunction {
+                ann: (),
+                argument: Box::new(Type::TInt { ann: () }),
+                result: Box::new(Type::TBool { ann: () })
+            }
+        ),
+        other => panic!("expected an application, got {:?}", other),
+    }
+
+    // let f = (\a -> a + 1) in f, where the body tells us what `a` is
+    assert_eq!(
+        elaborate_type(mk_let(
+            (),
+            "f",
+            mk_lambda((), "a", mk_bin_op((), Op::Add, var((), "a"), int((), 1))),
+            var((), "f")
+        )),
+        Result::Ok(Type::TFunction {
+            ann: (),
+            argument: Box::new(Type::TInt { ann: () }),
+            result: Box::new(Type::TInt { ann: () })
+        })
+    );
+
+    // (\a -> a + 1) True
+    assert_eq!(
+        elaborate_type(mk_apply(
+            (),
+            mk_lambda((), "a", mk_bin_op((), Op::Add, var((), "a"), int((), 1))),
+            bool((), true)
+        )),
+        Result::Err(TypeError::TypeMismatch {
+            type_a: Type::TInt { ann: () },
+            type_b: Type::TBool { ann: () }
+        })
+    );
+
+    // 1 2
+    assert_eq!(
+        elaborate_type(mk_apply((), int((), 1), int((), 2))),
+        Result::Err(TypeError::ApplyingNonFunction {
+            ann: (),
+            found: Type::TInt { ann: () }
+        })
+    );
+
+    // function values can be bound and applied
+    // let f = if True then (\a -> a) 1 else 2 in f
+    assert_eq!(
+        elaborate_type(mk_let(
+            (),
+            "f",
+            mk_if(
+                (),
+                bool((), true),
+                mk_apply((), mk_lambda((), "a", var((), "a")), int((), 1)),
+                int((), 2)
+            ),
+            var((), "f")
+        )),
+        Result::Ok(Type::TInt { ann: () })
+    );
+}
+
+#[test]
+fn test_let_polymorphism() {
+    use crate::parser::parse_expr::parse_my_expr;
+    use crate::parser::span::ParseInput;
+    use crate::types::ty::remove_type_annotation;
+
+    let elaborate_source = |source| {
+        let (_, parsed) = parse_my_expr(ParseInput::new(source)).unwrap();
+        Result::map(elaborate_expr(parsed), |expr| {
+            remove_type_annotation(get_expr_annotation(expr))
+        })
+    };
+
+    // `id` is used at both Bool and Int
+    assert_eq!(
+        elaborate_source("let id = \\x -> x in if id True then id 1 else 2"),
+        Result::Ok(Type::TInt { ann: () })
+    );
+
+    assert_eq!(
+        elaborate_source("let const = \\a -> \\b -> a in const (const 1 True) False"),
+        Result::Ok(Type::TInt { ann: () })
+    );
+
+    // lambda arguments are not generalised, so `f` can only be used at one type
+    assert!(matches!(
+        elaborate_source("(\\f -> if f True then f 1 else 2) (\\x -> x)"),
+        Result::Err(TypeError::TypeMismatch {
+            type_a: Type::TBool { .. },
+            type_b: Type::TInt { .. }
+        })
+    ));
+
+    // \x -> x x
+    assert!(matches!(
+        elaborate_source("\\x -> x x"),
+        Result::Err(TypeError::InfiniteType { .. })
+    ));
+
+    // nothing constrains `x`, so its type stays a variable
+    assert_eq!(
+        elaborate_source("\\x -> x"),
+        Result::Ok(Type::TFunction {
+            ann: (),
+            argument: Box::new(Type::TVar { ann: (), var: 0 }),
+            result: Box::new(Type::TVar { ann: (), var: 0 })
+        })
+    );
+}
+
+#[test]
+fn test_elaborate_resolves_every_annotation() {
+    // (\a -> a) 1, where the `a` inside the lambda is only known to be an
+    // Int once we've seen the argument
+    let expr = mk_apply((), mk_lambda((), "a", var((), "a")), int((), 1));
+
+    match elaborate_expr(expr) {
+        Result::Ok(Expr::EApply { function_expr, .. }) => match *function_expr {
+            Expr::ELambda { body_expr, .. } => {
+                assert_eq!(get_expr_annotation(*body_expr), Type::TInt { ann: () })
+            }
+            other => panic!("expected a lambda, got {:?}", other),
+        },
+        other => panic!("expected an application, got {:?}", other),
+    }
+}
+
+#[cfg(test)]
+fn elaborate_module_source(source: &str) -> Result<Vec<(String, Type<()>)>, TypeError<()>> {
+    use crate::parser::parse_module::parse_my_module;
+    use crate::parser::span::ParseInput;
+    use crate::types::module::map_module;
+    use crate::types::ty::remove_type_annotation;
+
+    let (_, parsed) = parse_my_module(ParseInput::new(source)).unwrap();
+
+    Result::map(elaborate_module(map_module(parsed, |_| ())), |module| {
+        module
+            .defs
+            .into_iter()
+            .map(|def| (def.identifier, remove_type_annotation(def.ann)))
+            .collect()
+    })
+}
+
+#[test]
+fn test_elaborate_module() {
+    let int_to = |result| Type::TFunction {
+        ann: (),
+        argument: Box::new(Type::TInt { ann: () }),
+        result: Box::new(result),
+    };
+
+    // definitions can be used before they're defined
+    assert_eq!(
+        elaborate_module_source("def two = inc 1\ndef inc = \\a -> a + 1"),
+        Result::Ok(vec![
+            ("two".to_string(), Type::TInt { ann: () }),
+            ("inc".to_string(), int_to(Type::TInt { ann: () })),
+        ])
+    );
+
+    // and are generalised before anything else uses them
+    assert_eq!(
+        elaborate_module_source("def id = \\x -> x\ndef main = if id True then id 1 else 2"),
+        Result::Ok(vec![
+            (
+                "id".to_string(),
+                Type::TFunction {
+                    ann: (),
+                    argument: Box::new(Type::TVar { ann: (), var: 1 }),
+                    result: Box::new(Type::TVar { ann: (), var: 1 })
+                }
+            ),
+            ("main".to_string(), Type::TInt { ann: () }),
+        ])
+    );
+
+    // mutual recursion
+    assert_eq!(
+        elaborate_module_source(
+            "def isEven = \\n -> if n == 0 then True else isOdd (n - 1)\n\
+             def isOdd = \\n -> if n == 0 then False else isEven (n - 1)"
+        ),
+        Result::Ok(vec![
+            ("isEven".to_string(), int_to(Type::TBool { ann: () })),
+            ("isOdd".to_string(), int_to(Type::TBool { ann: () })),
+        ])
+    );
+
+    // signatures are checked
+    assert_eq!(
+        elaborate_module_source("def a : Bool = 1"),
+        Result::Err(TypeError::AnnotationMismatch {
+            annotated: Type::TBool { ann: () },
+            found: Type::TInt { ann: () }
+        })
+    );
+
+    // and give lambdas their argument types
+    assert_eq!(
+        elaborate_module_source("def f : Int -> Bool = \\a -> a == 1"),
+        Result::Ok(vec![("f".to_string(), int_to(Type::TBool { ann: () }))])
+    );
+
+    assert_eq!(
+        elaborate_module_source("def a = 1\ndef a = 2"),
+        Result::Err(TypeError::DuplicateDefinition {
+            ann: (),
+            identifier: "a".to_string()
+        })
+    );
+
+    assert_eq!(
+        elaborate_module_source("def a = b"),
+        Result::Err(TypeError::UnboundVariable {
+            ann: (),
+            identifier: "b".to_string(),
+            suggestions: vec!["a".to_string()]
+        })
+    );
+}
+
+#[test]
+fn test_strings() {
+    let elaborate_type = |expr| Result::map(elaborate_expr(expr), get_expr_annotation);
+
+    assert_eq!(
+        elaborate_type(string((), "horse")),
+        Result::Ok(Type::TString { ann: () })
+    );
+
+    // "a" ++ "b"
+    let concat = mk_bin_op((), Op::Concat, string((), "a"), string((), "b"));
+    assert_eq!(
+        elaborate_type(concat.clone()),
+        Result::Ok(Type::TString { ann: () })
+    );
+
+    // length ("a" ++ "b") == 2
+    assert_eq!(
+        elaborate_type(mk_bin_op(
+            (),
+            Op::Equals,
+            mk_apply((), var((), "length"), concat),
+            int((), 2)
+        )),
+        Result::Ok(Type::TBool { ann: () })
+    );
+
+    // "a" ++ 1
+    assert_eq!(
+        elaborate_type(mk_bin_op((), Op::Concat, string((), "a"), int((), 1))),
+        Result::Err(TypeError::TypeMismatch {
+            type_a: Type::TString { ann: () },
+            type_b: Type::TInt { ann: () }
+        })
+    );
+
+    // builtins can be shadowed
+    assert_eq!(
+        elaborate_type(mk_let((), "length", int((), 1), var((), "length"))),
+        Result::Ok(Type::TInt { ann: () })
+    );
+}
+
+#[test]
+fn test_data() {
+    let maybe = |arg| Type::TConstructor {
+        ann: (),
+        name: "Maybe".to_string(),
+        args: vec![arg],
+    };
+    let data = "data Maybe a = Just a | Nothing\n";
+    let elaborate_with_data =
+        |source: &str| elaborate_module_source(&format!("{}{}", data, source));
+
+    // constructors are polymorphic in the type's parameters
+    assert_eq!(
+        elaborate_with_data("def a = Just 1\ndef b = Just True"),
+        Result::Ok(vec![
+            ("a".to_string(), maybe(Type::TInt { ann: () })),
+            ("b".to_string(), maybe(Type::TBool { ann: () })),
+        ])
+    );
+
+    // patterns bind the fields of the constructor they match
+    assert_eq!(
+        elaborate_with_data("def f = \\m -> case m of Just a -> a + 1 | Nothing -> 0"),
+        Result::Ok(vec![(
+            "f".to_string(),
+            Type::TFunction {
+                ann: (),
+                argument: Box::new(maybe(Type::TInt { ann: () })),
+                result: Box::new(Type::TInt { ann: () })
+            }
+        )])
+    );
+
+    // every arm must have the same type
+    assert_eq!(
+        elaborate_with_data("def f = case Nothing of Just a -> 1 | Nothing -> False"),
+        Result::Err(TypeError::TypeMismatch {
+            type_a: Type::TInt { ann: () },
+            type_b: Type::TBool { ann: () }
+        })
+    );
+
+    // and every pattern must match the scrutinee
+    assert!(matches!(
+        elaborate_with_data("def f = case 1 of Just a -> a"),
+        Result::Err(TypeError::TypeMismatch { .. })
+    ));
+
+    assert_eq!(
+        elaborate_with_data("def f = Horse 1"),
+        Result::Err(TypeError::UnknownConstructor {
+            ann: (),
+            constructor: "Horse".to_string()
+        })
+    );
+    assert_eq!(
+        elaborate_with_data("def f = case Nothing of Just a b -> 1"),
+        Result::Err(TypeError::ConstructorArityMismatch {
+            ann: (),
+            constructor: "Just".to_string(),
+            expected: 1,
+            found: 2
+        })
+    );
+    assert_eq!(
+        elaborate_with_data("def f : Maybe = Nothing"),
+        Result::Err(TypeError::TypeArityMismatch {
+            ann: (),
+            name: "Maybe".to_string(),
+            expected: 1,
+            found: 0
+        })
+    );
+    assert_eq!(
+        elaborate_with_data("data Box = Box Horse"),
+        Result::Err(TypeError::UnknownType {
+            ann: (),
+            name: "Horse".to_string()
+        })
+    );
+    assert_eq!(
+        elaborate_with_data("data Maybe = Maybe"),
+        Result::Err(TypeError::DuplicateType {
+            ann: (),
+            name: "Maybe".to_string()
+        })
+    );
+    assert_eq!(
+        elaborate_with_data("data Option a = Just a"),
+        Result::Err(TypeError::DuplicateConstructor {
+            ann: (),
+            constructor: "Just".to_string()
+        })
+    );
+    // neither backend can compare data values, so `==` on them is rejected
+    assert_eq!(
+        elaborate_with_data("def a = Just 1 == Just 1"),
+        Result::Err(TypeError::NotComparable {
+            ann: (),
+            found: maybe(Type::TInt { ann: () })
+        })
+    );
+    assert_eq!(
+        elaborate_with_data("data T = A | A"),
+        Result::Err(TypeError::DuplicateConstructor {
+            ann: (),
+            constructor: "A".to_string()
+        })
+    );
+}
+
+#[test]
+fn test_case_exhaustiveness() {
+    use crate::parser::parse_constructors::{p_constructor, p_literal, p_wildcard};
+
+    let data = "data Maybe a = Just a | Nothing\n";
+    let elaborate_with_data =
+        |source: &str| elaborate_module_source(&format!("{}{}", data, source));
+
+    assert!(elaborate_with_data("def f = \\b -> case b of True -> 1 | False -> 0").is_ok());
+    assert!(elaborate_with_data("def f = \\n -> case n of 1 -> True | _ -> False").is_ok());
+    assert!(elaborate_with_data(
+        "def f = \\m -> case m of Just True -> 1 | Just False -> 2 | Nothing -> 3"
+    )
+    .is_ok());
+
+    // literals must have the type of the scrutinee
+    assert_eq!(
+        elaborate_with_data("def f = case 1 of True -> 1 | _ -> 0"),
+        Result::Err(TypeError::TypeMismatch {
+            type_a: Type::TInt { ann: () },
+            type_b: Type::TBool { ann: () }
+        })
+    );
+
+    assert_eq!(
+        elaborate_with_data("def f = \\b -> case b of True -> 1"),
+        Result::Err(TypeError::NonExhaustivePatterns {
+            ann: (),
+            missing: p_literal((), Prim::PBool { bool: false })
+        })
+    );
+    // there are too many ints to list, so any int will do
+    assert_eq!(
+        elaborate_with_data("def f = \\n -> case n of 1 -> True | 2 -> False"),
+        Result::Err(TypeError::NonExhaustivePatterns {
+            ann: (),
+            missing: p_wildcard(())
+        })
+    );
+    assert_eq!(
+        elaborate_with_data("def f = \\m -> case m of Just True -> 1 | Nothing -> 0"),
+        Result::Err(TypeError::NonExhaustivePatterns {
+            ann: (),
+            missing: p_constructor((), "Just", vec![p_literal((), Prim::PBool { bool: false })])
+        })
+    );
+    assert_eq!(
+        elaborate_with_data("def f = \\m -> case m of Just a -> a"),
+        Result::Err(TypeError::NonExhaustivePatterns {
+            ann: (),
+            missing: p_constructor((), "Nothing", vec![])
+        })
+    );
+
+    assert_eq!(
+        elaborate_with_data("def f = \\n -> case n of a -> 1 | 2 -> 3"),
+        Result::Err(TypeError::RedundantPattern { ann: () })
+    );
+    assert_eq!(
+        elaborate_with_data(
+            "def f = \\m -> case m of Just True -> 1 | Just False -> 2 | Just _ -> 3 | Nothing -> 4"
+        ),
+        Result::Err(TypeError::RedundantPattern { ann: () })
+    );
+}
+
+#[test]
+fn test_records() {
+    let record = |fields: Vec<(&str, Type<()>)>| Type::TRecord {
+        ann: (),
+        fields: fields
+            .into_iter()
+            .map(|(name, ty)| (name.to_string(), ty))
+            .collect(),
+    };
+
+    // fields are sorted, whatever order they're written in
+    assert_eq!(
+        elaborate_module_source("def r = { ok: True, name: \"horse\" }"),
+        Result::Ok(vec![(
+            "r".to_string(),
+            record(vec![
+                ("name", Type::TString { ann: () }),
+                ("ok", Type::TBool { ann: () })
+            ])
+        )])
+    );
+
+    assert_eq!(
+        elaborate_module_source(
+            "def r = { name: 1, ok: True }\ndef name = r.name\ndef updated = { r | name: 2 }"
+        ),
+        Result::Ok(vec![
+            (
+                "r".to_string(),
+                record(vec![
+                    ("name", Type::TInt { ann: () }),
+                    ("ok", Type::TBool { ann: () })
+                ])
+            ),
+            ("name".to_string(), Type::TInt { ann: () }),
+            (
+                "updated".to_string(),
+                record(vec![
+                    ("name", Type::TInt { ann: () }),
+                    ("ok", Type::TBool { ann: () })
+                ])
+            ),
+        ])
+    );
+
+    // width subtyping, a record with extra fields can be passed to a function
+    // that only needs some of them
+    assert_eq!(
+        elaborate_module_source(
+            "def getName : { name: Int } -> Int = \\r -> r.name\n\
+             def main = getName { name: 1, ok: True } + getName { name: 2 }"
+        ),
+        Result::Ok(vec![
+            (
+                "getName".to_string(),
+                Type::TFunction {
+                    ann: (),
+                    argument: Box::new(record(vec![("name", Type::TInt { ann: () })])),
+                    result: Box::new(Type::TInt { ann: () })
+                }
+            ),
+            ("main".to_string(), Type::TInt { ann: () }),
+        ])
+    );
+
+    // but not one with fewer
+    assert_eq!(
+        elaborate_module_source("def r : { name: Int, ok: Bool } = { name: 1 }"),
+        Result::Err(TypeError::AnnotationMismatch {
+            annotated: record(vec![
+                ("name", Type::TInt { ann: () }),
+                ("ok", Type::TBool { ann: () })
+            ]),
+            found: record(vec![("name", Type::TInt { ann: () })])
+        })
+    );
+
+    // fields are checked as well
+    assert!(matches!(
+        elaborate_module_source("def r : { name: Int } = { name: True }"),
+        Result::Err(TypeError::AnnotationMismatch { .. })
+    ));
+
+    // functions that need fewer fields can be used where ones that need more
+    // are expected
+    assert!(elaborate_module_source(
+        "def apply : ({ name: Int, ok: Bool } -> Int) -> Int = \\f -> f { name: 1, ok: True }\n\
+         def main = apply (\\r -> 1)"
+    )
+    .is_ok());
+    assert!(elaborate_module_source(
+        "def getName : { name: Int } -> Int = \\r -> r.name\n\
+         def apply : ({ name: Int, ok: Bool } -> Int) -> Int = \\f -> f { name: 1, ok: True }\n\
+         def main = apply getName"
+    )
+    .is_ok());
+
+    assert_eq!(
+        elaborate_module_source("def r = { name: 1 }.ok"),
+        Result::Err(TypeError::MissingField {
+            ann: (),
+            field: "ok".to_string(),
+            found: record(vec![("name", Type::TInt { ann: () })])
+        })
+    );
+    // updates can't add fields
+    assert_eq!(
+        elaborate_module_source("def r = let x = { name: 1 } in { x | ok: True }"),
+        Result::Err(TypeError::MissingField {
+            ann: (),
+            field: "ok".to_string(),
+            found: record(vec![("name", Type::TInt { ann: () })])
+        })
+    );
+    assert_eq!(
+        elaborate_module_source("def r = (1).ok"),
+        Result::Err(TypeError::AccessingNonRecord {
+            ann: (),
+            found: Type::TInt { ann: () }
+        })
+    );
+    // record types aren't inferred from field access, we need to know which
+    // record we have before we can use its fields
+    assert!(matches!(
+        elaborate_module_source("def f = \\r -> r.name"),
+        Result::Err(TypeError::AccessingNonRecord {
+            found: Type::TVar { .. },
+            ..
+        })
+    ));
+    // so parameters whose fields are used need a signature or an annotation
+    assert!(elaborate_module_source("def f : { name: Int } -> Int = \\r -> r.name").is_ok());
+    assert_eq!(
+        elaborate_module_source("def f = \\r -> (r : { name: Int }).name"),
+        Result::Ok(vec![(
+            "f".to_string(),
+            Type::TFunction {
+                ann: (),
+                argument: Box::new(record(vec![("name", Type::TInt { ann: () })])),
+                result: Box::new(Type::TInt { ann: () })
+            }
+        )])
+    );
+    // neither backend can compare records, so `==` on them is rejected
+    assert_eq!(
+        elaborate_module_source("def a = { a: 1 } == { a: 1 }"),
+        Result::Err(TypeError::NotComparable {
+            ann: (),
+            found: record(vec![("a", Type::TInt { ann: () })])
+        })
+    );
+    assert_eq!(
+        elaborate_module_source("def r = { name: 1, name: 2 }"),
+        Result::Err(TypeError::DuplicateField {
+            ann: (),
+            field: "name".to_string()
+        })
+    );
+}
+
+#[test]
+fn test_tuples() {
+    let pair = |a, b| Type::TTuple {
+        ann: (),
+        items: vec![a, b],
+    };
+
+    assert_eq!(
+        elaborate_module_source("def t = (1, (True, \"horse\"))"),
+        Result::Ok(vec![(
+            "t".to_string(),
+            pair(
+                Type::TInt { ann: () },
+                pair(Type::TBool { ann: () }, Type::TString { ann: () })
+            )
+        )])
+    );
+
+    assert_eq!(
+        elaborate_module_source(
+            "def swap = \\t -> case t of (a, b) -> (b, a)\ndef main = swap (1, True)"
+        ),
+        Result::Ok(vec![
+            (
+                "swap".to_string(),
+                Type::TFunction {
+                    ann: (),
+                    argument: Box::new(pair(
+                        Type::TVar { ann: (), var: 3 },
+                        Type::TVar { ann: (), var: 4 }
+                    )),
+                    result: Box::new(pair(
+                        Type::TVar { ann: (), var: 4 },
+                        Type::TVar { ann: (), var: 3 }
+                    ))
+                }
+            ),
+            (
+                "main".to_string(),
+                pair(Type::TBool { ann: () }, Type::TInt { ann: () })
+            ),
+        ])
+    );
+
+    assert!(elaborate_module_source("def t : (Int, Bool) = (1, 2)").is_err());
+    assert!(matches!(
+        elaborate_module_source("def t = case (1, 2) of (a, b, c) -> a"),
+        Result::Err(TypeError::TypeMismatch { .. })
+    ));
+    assert_eq!(
+        elaborate_module_source("def t = case (True, True) of (True, _) -> 1"),
+        Result::Err(TypeError::NonExhaustivePatterns {
+            ann: (),
+            missing: Pattern::PTuple {
+                ann: (),
+                items: vec![
+                    Pattern::PLiteral {
+                        ann: (),
+                        prim: Prim::PBool { bool: false }
+                    },
+                    Pattern::PWildcard { ann: () }
+                ]
+            }
+        })
+    );
+
+    // tuples of records are subtypes of tuples of smaller records
+    assert!(elaborate_module_source(
+        "def first : ({ name: Int }, Int) -> Int = \\t -> case t of (r, _) -> r.name\n\
+         def main = first ({ name: 1, ok: True }, 2)"
+    )
+    .is_ok());
+
+    // neither backend can compare tuples, so `==` on them is rejected
+    assert_eq!(
+        elaborate_module_source("def a = (1, 2) == (1, 2)"),
+        Result::Err(TypeError::NotComparable {
+            ann: (),
+            found: pair(Type::TInt { ann: () }, Type::TInt { ann: () })
+        })
+    );
+}
+
+#[test]
+fn test_annotations() {
+    use crate::parser::parse_expr::parse_my_expr;
+    use crate::parser::span::ParseInput;
+    use crate::types::ty::remove_type_annotation;
+
+    let elaborate_source = |source| {
+        let (_, parsed) = parse_my_expr(ParseInput::new(source)).unwrap();
+        elaborate_expr(parsed)
+    };
+
+    assert_eq!(
+        Result::map(elaborate_source("(1 + 1 : Int)"), |expr| {
+            remove_type_annotation(get_expr_annotation(expr))
+        }),
+        Result::Ok(Type::TInt { ann: () })
+    );
+
+    // annotated lambdas get their argument type from the annotation
+    assert!(elaborate_source("(\\r -> r.name : { name: Int } -> Int)").is_ok());
+
+    // the annotation is the type of the whole expression, so a record can
+    // lose fields
+    assert!(matches!(
+        elaborate_source("({ name: 1, ok: True } : { name: Int }).ok"),
+        Result::Err(TypeError::MissingField { .. })
+    ));
+
+    assert!(matches!(
+        elaborate_source("(1 : Maybe)"),
+        Result::Err(TypeError::UnknownType { .. })
+    ));
+
+    // mismatches point at both the annotation and the expression
+    match elaborate_source("let a = (1 + 2 : Bool) in a") {
+        Result::Err(TypeError::AnnotationMismatch {
+            annotated: Type::TBool { ann: annotated },
+            found: Type::TInt { ann: found },
+        }) => {
+            assert_eq!((annotated.start.offset, annotated.end.offset), (17, 21));
+            assert_eq!((found.start.offset, found.end.offset), (9, 14));
+        }
+        other => panic!("expected AnnotationMismatch, got {:?}", other),
+    }
+
+    // but mismatches inside the expression are still reported as they are
+    assert!(matches!(
+        elaborate_source("((\\a -> a + 1) True : Int)"),
+        Result::Err(TypeError::TypeMismatch { .. })
+    ));
+
+    // lambdas are compared with their whole annotation too
+    let function = |argument, result| Type::TFunction {
+        ann: (),
+        argument: Box::new(argument),
+        result: Box::new(result),
+    };
+    let int = || Type::TInt { ann: () };
+    let bool = || Type::TBool { ann: () };
+    assert_eq!(
+        elaborate_module_source("def f : Int -> Bool = \\x -> x"),
+        Result::Err(TypeError::AnnotationMismatch {
+            annotated: function(int(), bool()),
+            found: function(int(), int())
+        })
+    );
+    assert_eq!(
+        elaborate_module_source("def f : Int -> Int -> Bool = \\a -> \\b -> a + b"),
+        Result::Err(TypeError::AnnotationMismatch {
+            annotated: function(int(), function(int(), bool())),
+            found: function(int(), function(int(), int()))
+        })
+    );
+    assert!(matches!(
+        elaborate_source("(\\x -> x : Int -> Bool)"),
+        Result::Err(TypeError::AnnotationMismatch { .. })
+    ));
+    assert!(matches!(
+        elaborate_module_source("def f : Int -> Int = \\x -> x + True"),
+        Result::Err(TypeError::TypeMismatch { .. })
+    ));
+}
+
+#[test]
+fn test_let_rec() {
+    use crate::parser::parse_constructors::{mk_lambda, mk_let_rec};
+    use crate::parser::parse_expr::parse_my_expr;
+    use crate::parser::span::ParseInput;
+    use crate::types::ty::remove_type_annotation;
+
+    let elaborate_type = |source| {
+        let (_, parsed) = parse_my_expr(ParseInput::new(source)).unwrap();
+        Result::map(elaborate_expr(map_expr(parsed, |_| ())), |expr| {
+            remove_type_annotation(get_expr_annotation(expr))
+        })
+    };
+
+    assert_eq!(
+        elaborate_type("let rec fact = \\n -> if n == 0 then 1 else n * fact (n - 1) in fact 5"),
+        Result::Ok(Type::TInt { ann: () })
+    );
+
+    // generalised for the rest, but not inside its own body
+    assert_eq!(
+        elaborate_type(
+            "let rec loop = \\a -> loop a in \
+             let a = (loop 1 : Bool) in (loop True : Int)"
+        ),
+        Result::Ok(Type::TInt { ann: () })
+    );
+    assert!(matches!(
+        elaborate_type("let rec f = \\a -> if f 1 then f True else True in f"),
+        Result::Err(TypeError::TypeMismatch { .. })
+    ));
+
+    // the function shadows anything else with its name
+    assert_eq!(
+        elaborate_type("let f = 1 in let rec f = \\a -> f a in f"),
+        Result::Ok(Type::TFunction {
+            ann: (),
+            argument: Box::new(Type::TVar { ann: (), var: 4 }),
+            result: Box::new(Type::TVar { ann: (), var: 5 })
+        })
+    );
+
+    assert_eq!(
+        elaborate_expr(mk_let_rec((), "a", int((), 1), var((), "a"))),
+        Result::Err(TypeError::RecursiveNonFunction {
+            ann: (),
+            identifier: "a".to_string()
+        })
+    );
+    assert!(elaborate_expr(mk_let_rec(
+        (),
+        "f",
+        mk_lambda((), "a", var((), "a")),
+        var((), "f")
+    ))
+    .is_ok());
+}
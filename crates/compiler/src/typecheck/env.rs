@@ -0,0 +1,180 @@
+use crate::types::module::Data;
+use crate::types::ty::Type;
+
+use std::collections::HashMap;
+
+// a type that may be used at many types, one for each of `vars`
+// `let id = \x -> x` gives `id` the scheme `forall 0. 0 -> 0`
+#[derive(Debug, PartialEq, Clone)]
+pub struct TypeScheme<Ann>
+where
+    Ann: Clone + Copy,
+{
+    pub vars: Vec<u32>,
+    pub ty: Type<Ann>,
+}
+
+impl<Ann> TypeScheme<Ann>
+where
+    Ann: Clone + Copy,
+{
+    // a scheme that is only ever used at `ty`, for lambda arguments
+    pub fn monomorphic(ty: Type<Ann>) -> Self {
+        TypeScheme { vars: vec![], ty }
+    }
+}
+
+// what a `data` declaration tells us about one of its constructors
+// `fields` mention the type's parameters as `TVar`s numbered by position
+#[derive(Debug, PartialEq, Clone)]
+pub struct ConstructorInfo<Ann>
+where
+    Ann: Clone + Copy,
+{
+    pub data_name: String,
+    pub param_count: usize,
+    pub fields: Vec<Type<Ann>>,
+}
+
+// the variables in scope while typechecking
+// bindings are pushed when we enter a `let` and popped when we leave it, so
+// nothing is visible outside the expression it was bound for
+#[derive(Debug)]
+pub struct TypeEnv<Ann>
+where
+    Ann: Clone + Copy,
+{
+    // innermost binding last, so later bindings shadow earlier ones
+    bindings: Vec<(String, TypeScheme<Ann>)>,
+    // declared types, how many arguments they take and their constructors
+    data_types: HashMap<String, (usize, Vec<String>)>,
+    constructors: HashMap<String, ConstructorInfo<Ann>>,
+}
+
+impl<Ann> TypeEnv<Ann>
+where
+    Ann: Clone + Copy,
+{
+    pub fn new() -> Self {
+        TypeEnv {
+            bindings: vec![],
+            data_types: HashMap::new(),
+            constructors: HashMap::new(),
+        }
+    }
+
+    // bring a `data` declaration and its constructors into scope
+    pub fn define_data(&mut self, data: &Data<Ann>) {
+        let constructor_names = data
+            .constructors
+            .iter()
+            .map(|constructor| constructor.name.clone())
+            .collect();
+        self.data_types
+            .insert(data.name.clone(), (data.params.len(), constructor_names));
+        for constructor in &data.constructors {
+            self.constructors.insert(
+                constructor.name.clone(),
+                ConstructorInfo {
+                    data_name: data.name.clone(),
+                    param_count: data.params.len(),
+                    fields: constructor.fields.clone(),
+                },
+            );
+        }
+    }
+
+    // how many arguments a declared type takes
+    pub fn lookup_data_type(&self, name: &str) -> Option<usize> {
+        self.data_types
+            .get(name)
+            .map(|(param_count, _)| *param_count)
+    }
+
+    // every constructor of a declared type, in the order they were declared
+    pub fn data_constructors(&self, name: &str) -> Option<&[String]> {
+        self.data_types
+            .get(name)
+            .map(|(_, constructors)| constructors.as_slice())
+    }
+
+    pub fn lookup_constructor(&self, constructor: &str) -> Option<&ConstructorInfo<Ann>> {
+        self.constructors.get(constructor)
+    }
+
+    pub fn lookup(&self, identifier: &str) -> Option<&TypeScheme<Ann>> {
+        self.bindings
+            .iter()
+            .rev()
+            .find(|(name, _)| name == identifier)
+            .map(|(_, ty)| ty)
+    }
+
+    // run `f` with `identifier` in scope, removing it again afterwards
+    pub fn with_binding<A, F>(&mut self, identifier: String, scheme: TypeScheme<Ann>, f: F) -> A
+    where
+        F: FnOnce(&mut Self) -> A,
+    {
+        self.bindings.push((identifier, scheme));
+        let result = f(self);
+        self.bindings.pop();
+        result
+    }
+
+    // run `f` with every one of `bindings` in scope, for definitions that can
+    // all see each other
+    pub fn with_bindings<A, F>(&mut self, bindings: Vec<(String, TypeScheme<Ann>)>, f: F) -> A
+    where
+        F: FnOnce(&mut Self) -> A,
+    {
+        let count = bindings.len();
+        self.bindings.extend(bindings);
+        let result = f(self);
+        self.bindings.truncate(self.bindings.len() - count);
+        result
+    }
+
+    // bring a top level definition into scope for the rest of the module
+    pub fn define(&mut self, identifier: String, scheme: TypeScheme<Ann>) {
+        self.bindings.push((identifier, scheme));
+    }
+
+    // every name currently in scope
+    pub fn names(&self) -> impl Iterator<Item = &String> {
+        self.bindings.iter().map(|(name, _)| name)
+    }
+
+    // every scheme currently in scope, which we must not generalise over
+    pub fn schemes(&self) -> impl Iterator<Item = &TypeScheme<Ann>> {
+        self.bindings.iter().map(|(_, scheme)| scheme)
+    }
+}
+
+impl<Ann> Default for TypeEnv<Ann>
+where
+    Ann: Clone + Copy,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[test]
+fn test_type_env_scoping() {
+    let mut env = TypeEnv::new();
+
+    let int = TypeScheme::monomorphic(Type::TInt { ann: () });
+    let bool = TypeScheme::monomorphic(Type::TBool { ann: () });
+
+    env.with_binding("a".to_string(), int.clone(), |env| {
+        assert_eq!(env.lookup("a"), Some(&int));
+
+        env.with_binding("a".to_string(), bool.clone(), |env| {
+            assert_eq!(env.lookup("a"), Some(&bool));
+        });
+
+        assert_eq!(env.lookup("a"), Some(&int));
+    });
+
+    assert_eq!(env.lookup("a"), None);
+}
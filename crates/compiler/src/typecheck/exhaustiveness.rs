@@ -0,0 +1,276 @@
+use crate::types::expr::{get_pattern_annotation, Pattern, Prim};
+use crate::types::typeerror::TypeError;
+
+use super::env::TypeEnv;
+
+// the part of a pattern that tells values apart
+#[derive(Debug, PartialEq, Clone)]
+enum Head {
+    Literal(Prim),
+    Constructor(String),
+    // a tuple with this many items, which is the only head its type has
+    Tuple(usize),
+}
+
+// a pattern without its variables, which match anything just like `_` does
+#[derive(Debug, Clone)]
+enum Space {
+    Anything,
+    Head(Head, Vec<Space>),
+}
+
+// check that every arm of a `case` can match something the arms before it
+// don't, and that together they match every value, reporting an example of a
+// value they miss if they don't
+//
+// this is the usefulness algorithm from Maranget's "Warnings for pattern
+// matching", a pattern is useful if there is a value it matches that none of
+// the patterns before it do
+pub fn check_case_patterns<Ann>(
+    env: &TypeEnv<Ann>,
+    ann: Ann,
+    patterns: &[Pattern<Ann>],
+) -> Result<(), TypeError<Ann>>
+where
+    Ann: Clone + Copy,
+{
+    let mut rows: Vec<Vec<Space>> = vec![];
+
+    for pattern in patterns {
+        let row = vec![to_space(pattern)];
+        if useful(env, &rows, &row).is_none() {
+            return Err(TypeError::RedundantPattern {
+                ann: get_pattern_annotation(pattern.clone()),
+            });
+        }
+        rows.push(row);
+    }
+
+    match useful(env, &rows, &[Space::Anything]) {
+        Some(mut witness) => Err(TypeError::NonExhaustivePatterns {
+            ann,
+            missing: to_pattern(witness.remove(0)),
+        }),
+        None => Ok(()),
+    }
+}
+
+fn to_space<Ann>(pattern: &Pattern<Ann>) -> Space {
+    match pattern {
+        Pattern::PVar { .. } | Pattern::PWildcard { .. } => Space::Anything,
+        Pattern::PLiteral { prim, .. } => Space::Head(Head::Literal(prim.clone()), vec![]),
+        Pattern::PTuple { items, .. } => Space::Head(
+            Head::Tuple(items.len()),
+            items.iter().map(to_space).collect(),
+        ),
+        Pattern::PConstructor {
+            constructor, args, ..
+        } => Space::Head(
+            Head::Constructor(constructor.clone()),
+            args.iter().map(to_space).collect(),
+        ),
+    }
+}
+
+fn to_pattern(space: Space) -> Pattern<()> {
+    match space {
+        Space::Anything => Pattern::PWildcard { ann: () },
+        Space::Head(Head::Literal(prim), _) => Pattern::PLiteral { ann: (), prim },
+        Space::Head(Head::Constructor(constructor), args) => Pattern::PConstructor {
+            ann: (),
+            constructor,
+            args: args.into_iter().map(to_pattern).collect(),
+        },
+        Space::Head(Head::Tuple(_), items) => Pattern::PTuple {
+            ann: (),
+            items: items.into_iter().map(to_pattern).collect(),
+        },
+    }
+}
+
+// if some value matches `vector` but none of `rows`, an example of one
+fn useful<Ann>(env: &TypeEnv<Ann>, rows: &[Vec<Space>], vector: &[Space]) -> Option<Vec<Space>>
+where
+    Ann: Clone + Copy,
+{
+    let (first, rest) = match vector.split_first() {
+        Some(split) => split,
+        // nothing left to look at, so `vector` matches and rows only don't
+        // if there aren't any
+        None => return if rows.is_empty() { Some(vec![]) } else { None },
+    };
+
+    match first {
+        Space::Head(head, args) => {
+            let vector = args.iter().chain(rest).cloned().collect::<Vec<_>>();
+            let witness = useful(env, &specialise(rows, head, args.len()), &vector)?;
+            Some(rebuild(head.clone(), args.len(), witness))
+        }
+        Space::Anything => {
+            let heads = first_column_heads(rows);
+            let signature = heads.first().and_then(|head| signature(env, head));
+
+            match signature {
+                // every possible head is used, so we need a value that one of
+                // them matches and the rows don't
+                Some(signature) if signature.iter().all(|(head, _)| heads.contains(head)) => {
+                    signature.into_iter().find_map(|(head, arity)| {
+                        let vector = std::iter::repeat(Space::Anything)
+                            .take(arity)
+                            .chain(rest.iter().cloned())
+                            .collect::<Vec<_>>();
+                        let witness = useful(env, &specialise(rows, &head, arity), &vector)?;
+                        Some(rebuild(head, arity, witness))
+                    })
+                }
+                // some head isn't used, so any value with that head only
+                // matches the rows that start with a wildcard
+                signature => {
+                    let default: Vec<Vec<Space>> = rows
+                        .iter()
+                        .filter(|row| matches!(row[0], Space::Anything))
+                        .map(|row| row[1..].to_vec())
+                        .collect();
+                    let mut witness = useful(env, &default, rest)?;
+
+                    let missing = signature
+                        .and_then(|signature| {
+                            signature
+                                .into_iter()
+                                .find(|(head, _)| !heads.contains(head))
+                        })
+                        .map(|(head, arity)| Space::Head(head, vec![Space::Anything; arity]))
+                        .unwrap_or(Space::Anything);
+
+                    witness.insert(0, missing);
+                    Some(witness)
+                }
+            }
+        }
+    }
+}
+
+// the rows that match values with `head`, with its arguments in place of the
+// first column
+fn specialise(rows: &[Vec<Space>], head: &Head, arity: usize) -> Vec<Vec<Space>> {
+    rows.iter()
+        .filter_map(|row| match &row[0] {
+            Space::Head(other, args) if other == head => {
+                Some(args.iter().chain(&row[1..]).cloned().collect())
+            }
+            Space::Head(_, _) => None,
+            Space::Anything => Some(
+                std::iter::repeat(Space::Anything)
+                    .take(arity)
+                    .chain(row[1..].iter().cloned())
+                    .collect(),
+            ),
+        })
+        .collect()
+}
+
+// put the first `arity` parts of a witness back under `head`
+fn rebuild(head: Head, arity: usize, mut witness: Vec<Space>) -> Vec<Space> {
+    let rest = witness.split_off(arity);
+    let mut rebuilt = vec![Space::Head(head, witness)];
+    rebuilt.extend(rest);
+    rebuilt
+}
+
+fn first_column_heads(rows: &[Vec<Space>]) -> Vec<Head> {
+    let mut heads = vec![];
+    for row in rows {
+        if let Space::Head(head, _) = &row[0] {
+            if !heads.contains(head) {
+                heads.push(head.clone());
+            }
+        }
+    }
+    heads
+}
+
+// every head a value of the same type as `head` could have, along with how
+// many arguments each one takes, or `None` if there are too many to list
+fn signature<Ann>(env: &TypeEnv<Ann>, head: &Head) -> Option<Vec<(Head, usize)>>
+where
+    Ann: Clone + Copy,
+{
+    match head {
+        Head::Literal(Prim::PBool { .. }) => Some(vec![
+            (Head::Literal(Prim::PBool { bool: true }), 0),
+            (Head::Literal(Prim::PBool { bool: false }), 0),
+        ]),
+        Head::Literal(Prim::PInt { .. } | Prim::PString { .. }) => None,
+        Head::Tuple(size) => Some(vec![(Head::Tuple(*size), *size)]),
+        Head::Constructor(constructor) => {
+            let data_name = &env.lookup_constructor(constructor)?.data_name;
+            env.data_constructors(data_name)?
+                .iter()
+                .map(|name| {
+                    let arity = env.lookup_constructor(name)?.fields.len();
+                    Some((Head::Constructor(name.clone()), arity))
+                })
+                .collect()
+        }
+    }
+}
+
+#[test]
+fn test_check_case_patterns() {
+    use crate::parser::parse_constructors::{p_literal, p_tuple, p_var, p_wildcard};
+
+    let env = TypeEnv::new();
+    let int = |int| p_literal((), Prim::PInt { int });
+    let bool = |bool| p_literal((), Prim::PBool { bool });
+
+    assert_eq!(
+        check_case_patterns(&env, (), &[bool(true), bool(false)]),
+        Ok(())
+    );
+    assert_eq!(
+        check_case_patterns(&env, (), &[int(1), p_var((), "a")]),
+        Ok(())
+    );
+    assert_eq!(
+        check_case_patterns(&env, (), &[bool(false)]),
+        Err(TypeError::NonExhaustivePatterns {
+            ann: (),
+            missing: bool(true)
+        })
+    );
+    assert_eq!(
+        check_case_patterns(&env, (), &[]),
+        Err(TypeError::NonExhaustivePatterns {
+            ann: (),
+            missing: p_wildcard(())
+        })
+    );
+    assert_eq!(
+        check_case_patterns(&env, (), &[int(1), int(1), p_wildcard(())]),
+        Err(TypeError::RedundantPattern { ann: () })
+    );
+    assert_eq!(
+        check_case_patterns(&env, (), &[bool(true), bool(false), p_wildcard(())]),
+        Err(TypeError::RedundantPattern { ann: () })
+    );
+
+    let pair = |a, b| p_tuple((), vec![a, b]);
+    assert_eq!(
+        check_case_patterns(
+            &env,
+            (),
+            &[
+                pair(bool(true), p_wildcard(())),
+                pair(p_wildcard(()), bool(true))
+            ]
+        ),
+        Err(TypeError::NonExhaustivePatterns {
+            ann: (),
+            missing: pair(bool(false), bool(false))
+        })
+    );
+    assert_eq!(
+        check_case_patterns(&env, (), &[pair(p_var((), "a"), p_wildcard(()))]),
+        Ok(())
+    );
+}
@@ -1 +1,6 @@
+pub mod dependencies;
 pub mod elaborate;
+pub mod env;
+pub mod exhaustiveness;
+pub mod suggest;
+pub mod unify;
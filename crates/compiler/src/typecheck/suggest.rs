@@ -0,0 +1,76 @@
+// when a variable isn't in scope, find the names that are in scope that the
+// user might have meant instead
+pub fn suggest_names<'a, I>(identifier: &str, names_in_scope: I) -> Vec<String>
+where
+    I: IntoIterator<Item = &'a String>,
+{
+    // allow roughly one typo for every three characters
+    let max_distance = std::cmp::max(1, identifier.chars().count() / 3);
+
+    let mut suggestions: Vec<(usize, &String)> = names_in_scope
+        .into_iter()
+        .map(|name| (edit_distance(identifier, name), name))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .collect();
+
+    // closest first, then alphabetical so results are stable
+    suggestions.sort();
+    suggestions.dedup();
+
+    suggestions
+        .into_iter()
+        .map(|(_, name)| name.clone())
+        .collect()
+}
+
+// Levenshtein distance between two strings, counted in chars
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b_chars: Vec<char> = b.chars().collect();
+
+    // distances from the previous row, starting with "" -> b
+    let mut previous: Vec<usize> = (0..=b_chars.len()).collect();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+
+        for (j, b_char) in b_chars.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            let insertion = current[j] + 1;
+            let deletion = previous[j + 1] + 1;
+            current.push(substitution.min(insertion).min(deletion));
+        }
+
+        previous = current;
+    }
+
+    previous[b_chars.len()]
+}
+
+#[test]
+fn test_edit_distance() {
+    assert_eq!(edit_distance("", ""), 0);
+    assert_eq!(edit_distance("horse", "horse"), 0);
+    assert_eq!(edit_distance("horse", "house"), 1);
+    assert_eq!(edit_distance("horse", "hose"), 1);
+    assert_eq!(edit_distance("horse", "horses"), 1);
+    assert_eq!(edit_distance("kitten", "sitting"), 3);
+    assert_eq!(edit_distance("", "abc"), 3);
+}
+
+#[test]
+fn test_suggest_names() {
+    let names = vec![
+        "horse".to_string(),
+        "house".to_string(),
+        "stable".to_string(),
+        "a".to_string(),
+    ];
+
+    assert_eq!(
+        suggest_names("hoarse", &names),
+        vec!["horse".to_string(), "house".to_string()]
+    );
+    assert_eq!(suggest_names("stabel", &names), vec!["stable".to_string()]);
+    assert_eq!(suggest_names("b", &names), vec!["a".to_string()]);
+    assert_eq!(suggest_names("giraffe", &names), Vec::<String>::new());
+}
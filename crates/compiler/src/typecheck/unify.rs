@@ -0,0 +1,354 @@
+use crate::types::ty::Type;
+use crate::types::typeerror::TypeError;
+
+use std::collections::HashMap;
+
+// the solutions we've found for type variables so far
+#[derive(Debug)]
+pub struct Substitution<Ann>
+where
+    Ann: Clone + Copy,
+{
+    next_var: u32,
+    solved: HashMap<u32, Type<Ann>>,
+}
+
+impl<Ann> Substitution<Ann>
+where
+    Ann: Clone + Copy,
+{
+    pub fn new() -> Self {
+        Substitution {
+            next_var: 0,
+            solved: HashMap::new(),
+        }
+    }
+
+    // a type variable we haven't used before
+    pub fn fresh(&mut self, ann: Ann) -> Type<Ann> {
+        let var = self.next_var;
+        self.next_var += 1;
+        Type::TVar { ann, var }
+    }
+
+    // replace every solved type variable in `ty` with its solution
+    pub fn apply(&self, ty: Type<Ann>) -> Type<Ann> {
+        match ty {
+            Type::TVar { ann, var } => match self.solved.get(&var) {
+                Some(solution) => self.apply(solution.clone()),
+                None => Type::TVar { ann, var },
+            },
+            Type::TFunction {
+                ann,
+                argument,
+                result,
+            } => Type::TFunction {
+                ann,
+                argument: Box::new(self.apply(*argument)),
+                result: Box::new(self.apply(*result)),
+            },
+            Type::TConstructor { ann, name, args } => Type::TConstructor {
+                ann,
+                name,
+                args: args.into_iter().map(|arg| self.apply(arg)).collect(),
+            },
+            Type::TTuple { ann, items } => Type::TTuple {
+                ann,
+                items: items.into_iter().map(|item| self.apply(item)).collect(),
+            },
+            Type::TRecord { ann, fields } => Type::TRecord {
+                ann,
+                fields: fields
+                    .into_iter()
+                    .map(|(name, ty)| (name, self.apply(ty)))
+                    .collect(),
+            },
+            other => other,
+        }
+    }
+
+    // make two types equal by solving type variables, or fail with a
+    // `TypeMismatch` between `type_a` (the one we expected) and `type_b`
+    pub fn unify(&mut self, type_a: Type<Ann>, type_b: Type<Ann>) -> Result<(), TypeError<Ann>> {
+        match (self.apply(type_a), self.apply(type_b)) {
+            (Type::TVar { var: var_a, .. }, Type::TVar { var: var_b, .. }) if var_a == var_b => {
+                Ok(())
+            }
+            (Type::TVar { ann, var }, other) | (other, Type::TVar { ann, var }) => {
+                if free_type_vars(&other).contains(&var) {
+                    Err(TypeError::InfiniteType {
+                        var: Type::TVar { ann, var },
+                        ty: other,
+                    })
+                } else {
+                    self.solved.insert(var, other);
+                    Ok(())
+                }
+            }
+            (Type::TInt { .. }, Type::TInt { .. }) => Ok(()),
+            (Type::TBool { .. }, Type::TBool { .. }) => Ok(()),
+            (Type::TString { .. }, Type::TString { .. }) => Ok(()),
+            (
+                Type::TFunction {
+                    argument: argument_a,
+                    result: result_a,
+                    ..
+                },
+                Type::TFunction {
+                    argument: argument_b,
+                    result: result_b,
+                    ..
+                },
+            ) => {
+                self.unify(*argument_a, *argument_b)?;
+                self.unify(*result_a, *result_b)
+            }
+            (
+                Type::TConstructor {
+                    name: name_a,
+                    args: args_a,
+                    ..
+                },
+                Type::TConstructor {
+                    name: name_b,
+                    args: args_b,
+                    ..
+                },
+            ) if name_a == name_b && args_a.len() == args_b.len() => {
+                for (arg_a, arg_b) in args_a.into_iter().zip(args_b) {
+                    self.unify(arg_a, arg_b)?;
+                }
+                Ok(())
+            }
+            (Type::TTuple { items: items_a, .. }, Type::TTuple { items: items_b, .. })
+                if items_a.len() == items_b.len() =>
+            {
+                for (item_a, item_b) in items_a.into_iter().zip(items_b) {
+                    self.unify(item_a, item_b)?;
+                }
+                Ok(())
+            }
+            // fields are sorted, so records with the same fields line up
+            (
+                Type::TRecord {
+                    fields: fields_a, ..
+                },
+                Type::TRecord {
+                    fields: fields_b, ..
+                },
+            ) if fields_a.len() == fields_b.len()
+                && fields_a
+                    .iter()
+                    .zip(&fields_b)
+                    .all(|((name_a, _), (name_b, _))| name_a == name_b) =>
+            {
+                for ((_, field_a), (_, field_b)) in fields_a.into_iter().zip(fields_b) {
+                    self.unify(field_a, field_b)?;
+                }
+                Ok(())
+            }
+            (type_a, type_b) => Err(TypeError::TypeMismatch { type_a, type_b }),
+        }
+    }
+}
+
+impl<Ann> Default for Substitution<Ann>
+where
+    Ann: Clone + Copy,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// type variables that appear in `ty`, in the order they first appear
+pub fn free_type_vars<Ann>(ty: &Type<Ann>) -> Vec<u32>
+where
+    Ann: Clone + Copy,
+{
+    let mut vars = vec![];
+    collect_type_vars(ty, &mut vars);
+    vars
+}
+
+fn collect_type_vars<Ann>(ty: &Type<Ann>, vars: &mut Vec<u32>)
+where
+    Ann: Clone + Copy,
+{
+    match ty {
+        Type::TVar { var, .. } => {
+            if !vars.contains(var) {
+                vars.push(*var)
+            }
+        }
+        Type::TFunction {
+            argument, result, ..
+        } => {
+            collect_type_vars(argument, vars);
+            collect_type_vars(result, vars);
+        }
+        Type::TConstructor { args, .. } => {
+            for arg in args {
+                collect_type_vars(arg, vars);
+            }
+        }
+        Type::TTuple { items, .. } => {
+            for item in items {
+                collect_type_vars(item, vars);
+            }
+        }
+        Type::TRecord { fields, .. } => {
+            for (_, field) in fields {
+                collect_type_vars(field, vars);
+            }
+        }
+        Type::TInt { .. } | Type::TBool { .. } | Type::TString { .. } => {}
+    }
+}
+
+// number type variables from 0 in the order they first appear, so they are
+// displayed as `a`, `b` and so on however many we made while checking
+pub fn normalise_type_vars<Ann>(ty: Type<Ann>) -> Type<Ann>
+where
+    Ann: Clone + Copy,
+{
+    let vars = free_type_vars(&ty);
+    rename_type_vars(ty, &vars)
+}
+
+fn rename_type_vars<Ann>(ty: Type<Ann>, vars: &[u32]) -> Type<Ann>
+where
+    Ann: Clone + Copy,
+{
+    match ty {
+        Type::TVar { ann, var } => Type::TVar {
+            ann,
+            var: vars.iter().position(|&found| found == var).unwrap_or(0) as u32,
+        },
+        Type::TFunction {
+            ann,
+            argument,
+            result,
+        } => Type::TFunction {
+            ann,
+            argument: Box::new(rename_type_vars(*argument, vars)),
+            result: Box::new(rename_type_vars(*result, vars)),
+        },
+        Type::TConstructor { ann, name, args } => Type::TConstructor {
+            ann,
+            name,
+            args: args
+                .into_iter()
+                .map(|arg| rename_type_vars(arg, vars))
+                .collect(),
+        },
+        Type::TTuple { ann, items } => Type::TTuple {
+            ann,
+            items: items
+                .into_iter()
+                .map(|item| rename_type_vars(item, vars))
+                .collect(),
+        },
+        Type::TRecord { ann, fields } => Type::TRecord {
+            ann,
+            fields: fields
+                .into_iter()
+                .map(|(name, field)| (name, rename_type_vars(field, vars)))
+                .collect(),
+        },
+        other => other,
+    }
+}
+
+#[cfg(test)]
+fn mk_function(argument: Type<()>, result: Type<()>) -> Type<()> {
+    Type::TFunction {
+        ann: (),
+        argument: Box::new(argument),
+        result: Box::new(result),
+    }
+}
+
+#[test]
+fn test_unify() {
+    let mut substitution = Substitution::new();
+    let a = substitution.fresh(());
+    let b = substitution.fresh(());
+
+    // a -> Int ~ Bool -> b
+    assert_eq!(
+        substitution.unify(
+            mk_function(a.clone(), Type::TInt { ann: () }),
+            mk_function(Type::TBool { ann: () }, b.clone())
+        ),
+        Ok(())
+    );
+    assert_eq!(substitution.apply(a.clone()), Type::TBool { ann: () });
+    assert_eq!(substitution.apply(b.clone()), Type::TInt { ann: () });
+
+    // a is now Bool, so this fails
+    assert_eq!(
+        substitution.unify(Type::TInt { ann: () }, a),
+        Err(TypeError::TypeMismatch {
+            type_a: Type::TInt { ann: () },
+            type_b: Type::TBool { ann: () }
+        })
+    );
+}
+
+#[test]
+fn test_unify_occurs_check() {
+    let mut substitution = Substitution::new();
+    let a = substitution.fresh(());
+
+    // a ~ a -> Int would make an infinite type
+    assert_eq!(
+        substitution.unify(a.clone(), mk_function(a.clone(), Type::TInt { ann: () })),
+        Err(TypeError::InfiniteType {
+            var: a.clone(),
+            ty: mk_function(a, Type::TInt { ann: () })
+        })
+    );
+}
+
+#[test]
+fn test_unify_constructors() {
+    let mut substitution = Substitution::new();
+    let a = substitution.fresh(());
+
+    let maybe = |arg: Type<()>| Type::TConstructor {
+        ann: (),
+        name: "Maybe".to_string(),
+        args: vec![arg],
+    };
+
+    // Maybe a ~ Maybe Int
+    assert_eq!(
+        substitution.unify(maybe(a.clone()), maybe(Type::TInt { ann: () })),
+        Ok(())
+    );
+    assert_eq!(substitution.apply(a), Type::TInt { ann: () });
+
+    // different constructors never unify, whatever their arguments
+    let list_int = Type::TConstructor {
+        ann: (),
+        name: "List".to_string(),
+        args: vec![Type::TInt { ann: () }],
+    };
+    assert_eq!(
+        substitution.unify(maybe(Type::TInt { ann: () }), list_int.clone()),
+        Err(TypeError::TypeMismatch {
+            type_a: maybe(Type::TInt { ann: () }),
+            type_b: list_int
+        })
+    );
+}
+
+#[test]
+fn test_normalise_type_vars() {
+    let var = |var| Type::TVar { ann: (), var };
+    assert_eq!(
+        normalise_type_vars(mk_function(var(7), mk_function(var(3), var(7)))),
+        mk_function(var(0), mk_function(var(1), var(0)))
+    );
+}
@@ -0,0 +1,35 @@
+use super::ty::Type;
+
+// functions that are always in scope, unless something shadows them
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Builtin {
+    // number of bytes in a string
+    Length,
+}
+
+pub const BUILTINS: [Builtin; 1] = [Builtin::Length];
+
+pub fn builtin_name(builtin: Builtin) -> &'static str {
+    match builtin {
+        Builtin::Length => "length",
+    }
+}
+
+pub fn lookup_builtin(identifier: &str) -> Option<Builtin> {
+    BUILTINS
+        .into_iter()
+        .find(|builtin| builtin_name(*builtin) == identifier)
+}
+
+pub fn builtin_type<Ann>(builtin: Builtin, ann: Ann) -> Type<Ann>
+where
+    Ann: Clone + Copy,
+{
+    match builtin {
+        Builtin::Length => Type::TFunction {
+            ann,
+            argument: Box::new(Type::TString { ann }),
+            result: Box::new(Type::TInt { ann }),
+        },
+    }
+}
@@ -1,3 +1,7 @@
+use super::ty::{map_type, Type};
+
+use std::fmt;
+
 #[derive(Debug, PartialEq, Clone)]
 
 pub enum Expr<Ann> {
@@ -17,16 +21,135 @@ pub enum Expr<Ann> {
         bound_expr: Box<Self>,
         rest_expr: Box<Self>,
     },
+    // `let rec f = \a -> ... in ...`, where the function can call itself
+    // `bound_expr` is always a lambda, so there's something to call
+    ELetRec {
+        ann: Ann,
+        identifier: String,
+        bound_expr: Box<Self>,
+        rest_expr: Box<Self>,
+    },
     EVar {
         ann: Ann,
         identifier: String,
     },
+    EBinOp {
+        ann: Ann,
+        op: Op,
+        left_expr: Box<Self>,
+        right_expr: Box<Self>,
+    },
+    ELambda {
+        ann: Ann,
+        identifier: String,
+        body_expr: Box<Self>,
+    },
+    EApply {
+        ann: Ann,
+        function_expr: Box<Self>,
+        argument_expr: Box<Self>,
+    },
+    // a data constructor applied to all of its fields, ie `Just 1`
+    EConstructor {
+        ann: Ann,
+        constructor: String,
+        args: Vec<Self>,
+    },
+    // `case scrutinee of pattern -> expr | pattern -> expr`
+    ECase {
+        ann: Ann,
+        scrutinee_expr: Box<Self>,
+        arms: Vec<CaseArm<Ann>>,
+    },
+    // `(1, True)`, always at least two items
+    ETuple {
+        ann: Ann,
+        items: Vec<Self>,
+    },
+    // `{ name: 1, ok: True }`
+    ERecord {
+        ann: Ann,
+        fields: Vec<(String, Self)>,
+    },
+    // `record.field`
+    EFieldAccess {
+        ann: Ann,
+        record_expr: Box<Self>,
+        field: String,
+    },
+    // `{ record | name: 2 }`, a copy of `record` with some fields replaced
+    ERecordUpdate {
+        ann: Ann,
+        record_expr: Box<Self>,
+        fields: Vec<(String, Self)>,
+    },
+    // `(expr : Int)`, checking `expr` against a type written in the source
+    EAnnotation {
+        ann: Ann,
+        expr: Box<Self>,
+        annotation: Type<Ann>,
+    },
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct CaseArm<Ann> {
+    pub pattern: Pattern<Ann>,
+    pub body_expr: Expr<Ann>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Pattern<Ann> {
+    // matches anything, binding it to `identifier`
+    PVar {
+        ann: Ann,
+        identifier: String,
+    },
+    // `_`, matches anything without binding it
+    PWildcard {
+        ann: Ann,
+    },
+    // matches a value equal to `prim`
+    PLiteral {
+        ann: Ann,
+        prim: Prim,
+    },
+    // matches tuples whose items match `items`
+    PTuple {
+        ann: Ann,
+        items: Vec<Self>,
+    },
+    // matches values built with `constructor` whose fields match `args`
+    PConstructor {
+        ann: Ann,
+        constructor: String,
+        args: Vec<Self>,
+    },
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Op {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+    Equals,
+    NotEquals,
+    LessThan,
+    LessThanOrEqual,
+    GreaterThan,
+    GreaterThanOrEqual,
+    And,
+    Or,
+    // `++`, joins two strings
+    Concat,
 }
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Prim {
     PBool { bool: bool },
     PInt { int: i32 },
+    PString { string: String },
 }
 
 pub fn map_expr<F, A, B>(expr: Expr<A>, f: F) -> Expr<B>
@@ -63,6 +186,158 @@ where
             bound_expr: Box::new(map_expr(*bound_expr, f)),
             rest_expr: Box::new(map_expr(*rest_expr, f)),
         },
+        Expr::ELetRec {
+            ann,
+            identifier,
+            bound_expr,
+            rest_expr,
+        } => Expr::ELetRec {
+            ann: f(ann),
+            identifier,
+            bound_expr: Box::new(map_expr(*bound_expr, f)),
+            rest_expr: Box::new(map_expr(*rest_expr, f)),
+        },
+        Expr::EBinOp {
+            ann,
+            op,
+            left_expr,
+            right_expr,
+        } => Expr::EBinOp {
+            ann: f(ann),
+            op,
+            left_expr: Box::new(map_expr(*left_expr, f)),
+            right_expr: Box::new(map_expr(*right_expr, f)),
+        },
+        Expr::ELambda {
+            ann,
+            identifier,
+            body_expr,
+        } => Expr::ELambda {
+            ann: f(ann),
+            identifier,
+            body_expr: Box::new(map_expr(*body_expr, f)),
+        },
+        Expr::EApply {
+            ann,
+            function_expr,
+            argument_expr,
+        } => Expr::EApply {
+            ann: f(ann),
+            function_expr: Box::new(map_expr(*function_expr, f)),
+            argument_expr: Box::new(map_expr(*argument_expr, f)),
+        },
+        Expr::EConstructor {
+            ann,
+            constructor,
+            args,
+        } => Expr::EConstructor {
+            ann: f(ann),
+            constructor,
+            args: args.into_iter().map(|arg| map_expr(arg, f)).collect(),
+        },
+        Expr::ECase {
+            ann,
+            scrutinee_expr,
+            arms,
+        } => Expr::ECase {
+            ann: f(ann),
+            scrutinee_expr: Box::new(map_expr(*scrutinee_expr, f)),
+            arms: arms
+                .into_iter()
+                .map(|arm| CaseArm {
+                    pattern: map_pattern(arm.pattern, f),
+                    body_expr: map_expr(arm.body_expr, f),
+                })
+                .collect(),
+        },
+        Expr::ETuple { ann, items } => Expr::ETuple {
+            ann: f(ann),
+            items: items.into_iter().map(|item| map_expr(item, f)).collect(),
+        },
+        Expr::ERecord { ann, fields } => Expr::ERecord {
+            ann: f(ann),
+            fields: fields
+                .into_iter()
+                .map(|(name, field_expr)| (name, map_expr(field_expr, f)))
+                .collect(),
+        },
+        Expr::EFieldAccess {
+            ann,
+            record_expr,
+            field,
+        } => Expr::EFieldAccess {
+            ann: f(ann),
+            record_expr: Box::new(map_expr(*record_expr, f)),
+            field,
+        },
+        Expr::ERecordUpdate {
+            ann,
+            record_expr,
+            fields,
+        } => Expr::ERecordUpdate {
+            ann: f(ann),
+            record_expr: Box::new(map_expr(*record_expr, f)),
+            fields: fields
+                .into_iter()
+                .map(|(name, field_expr)| (name, map_expr(field_expr, f)))
+                .collect(),
+        },
+        Expr::EAnnotation {
+            ann,
+            expr,
+            annotation,
+        } => Expr::EAnnotation {
+            ann: f(ann),
+            expr: Box::new(map_expr(*expr, f)),
+            annotation: map_type(annotation, f),
+        },
+    }
+}
+
+pub fn map_pattern<F, A, B>(pattern: Pattern<A>, f: F) -> Pattern<B>
+where
+    F: FnOnce(A) -> B + Copy,
+{
+    match pattern {
+        Pattern::PVar { ann, identifier } => Pattern::PVar {
+            ann: f(ann),
+            identifier,
+        },
+        Pattern::PWildcard { ann } => Pattern::PWildcard { ann: f(ann) },
+        Pattern::PLiteral { ann, prim } => Pattern::PLiteral { ann: f(ann), prim },
+        Pattern::PTuple { ann, items } => Pattern::PTuple {
+            ann: f(ann),
+            items: items.into_iter().map(|item| map_pattern(item, f)).collect(),
+        },
+        Pattern::PConstructor {
+            ann,
+            constructor,
+            args,
+        } => Pattern::PConstructor {
+            ann: f(ann),
+            constructor,
+            args: args.into_iter().map(|arg| map_pattern(arg, f)).collect(),
+        },
+    }
+}
+
+pub fn get_pattern_annotation<Ann>(pattern: Pattern<Ann>) -> Ann {
+    match pattern {
+        Pattern::PVar { ann, .. } => ann,
+        Pattern::PWildcard { ann } => ann,
+        Pattern::PLiteral { ann, .. } => ann,
+        Pattern::PTuple { ann, .. } => ann,
+        Pattern::PConstructor { ann, .. } => ann,
+    }
+}
+
+// variables a pattern binds, left to right
+pub fn pattern_variables<Ann>(pattern: &Pattern<Ann>) -> Vec<String> {
+    match pattern {
+        Pattern::PVar { identifier, .. } => vec![identifier.clone()],
+        Pattern::PWildcard { .. } | Pattern::PLiteral { .. } => vec![],
+        Pattern::PTuple { items, .. } => items.iter().flat_map(pattern_variables).collect(),
+        Pattern::PConstructor { args, .. } => args.iter().flat_map(pattern_variables).collect(),
     }
 }
 
@@ -71,6 +346,121 @@ pub fn get_expr_annotation<Ann>(expr: Expr<Ann>) -> Ann {
         Expr::EPrim { ann, .. } => ann,
         Expr::EIf { ann, .. } => ann,
         Expr::ELet { ann, .. } => ann,
+        Expr::ELetRec { ann, .. } => ann,
         Expr::EVar { ann, .. } => ann,
+        Expr::EBinOp { ann, .. } => ann,
+        Expr::ELambda { ann, .. } => ann,
+        Expr::EApply { ann, .. } => ann,
+        Expr::EConstructor { ann, .. } => ann,
+        Expr::ECase { ann, .. } => ann,
+        Expr::ETuple { ann, .. } => ann,
+        Expr::ERecord { ann, .. } => ann,
+        Expr::EFieldAccess { ann, .. } => ann,
+        Expr::ERecordUpdate { ann, .. } => ann,
+        Expr::EAnnotation { ann, .. } => ann,
+    }
+}
+
+// literals as they would be written in the source
+impl fmt::Display for Prim {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Prim::PBool { bool: true } => write!(f, "True"),
+            Prim::PBool { bool: false } => write!(f, "False"),
+            Prim::PInt { int } => write!(f, "{}", int),
+            Prim::PString { string } => {
+                write!(f, "\"")?;
+                for c in string.chars() {
+                    match c {
+                        '"' => write!(f, "\\\"")?,
+                        '\\' => write!(f, "\\\\")?,
+                        '\n' => write!(f, "\\n")?,
+                        '\t' => write!(f, "\\t")?,
+                        '\r' => write!(f, "\\r")?,
+                        '\0' => write!(f, "\\0")?,
+                        c => write!(f, "{}", c)?,
+                    }
+                }
+                write!(f, "\"")
+            }
+        }
+    }
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let op = match self {
+            Op::Add => "+",
+            Op::Subtract => "-",
+            Op::Multiply => "*",
+            Op::Divide => "/",
+            Op::Modulo => "%",
+            Op::Equals => "==",
+            Op::NotEquals => "!=",
+            Op::LessThan => "<",
+            Op::LessThanOrEqual => "<=",
+            Op::GreaterThan => ">",
+            Op::GreaterThanOrEqual => ">=",
+            Op::And => "&&",
+            Op::Or => "||",
+            Op::Concat => "++",
+        };
+        write!(f, "{}", op)
+    }
+}
+
+// patterns as they would be written in a `case` arm
+impl<Ann> fmt::Display for Pattern<Ann> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Pattern::PVar { identifier, .. } => write!(f, "{}", identifier),
+            Pattern::PWildcard { .. } => write!(f, "_"),
+            Pattern::PLiteral { prim, .. } => write!(f, "{}", prim),
+            Pattern::PTuple { items, .. } => {
+                let items: Vec<String> = items.iter().map(ToString::to_string).collect();
+                write!(f, "({})", items.join(", "))
+            }
+            Pattern::PConstructor {
+                constructor, args, ..
+            } => {
+                write!(f, "{}", constructor)?;
+                for arg in args {
+                    match arg {
+                        Pattern::PConstructor { args, .. } if !args.is_empty() => {
+                            write!(f, " ({})", arg)?
+                        }
+                        _ => write!(f, " {}", arg)?,
+                    }
+                }
+                Ok(())
+            }
+        }
     }
 }
+
+#[test]
+fn test_display_pattern() {
+    use crate::parser::parse_constructors::{p_constructor, p_literal, p_tuple, p_wildcard};
+
+    let pattern = p_tuple(
+        (),
+        vec![
+            p_constructor(
+                (),
+                "Just",
+                vec![p_constructor((), "Just", vec![p_wildcard(())])],
+            ),
+            p_literal(
+                (),
+                Prim::PString {
+                    string: "a \"b\"".to_string(),
+                },
+            ),
+            p_constructor((), "Nothing", vec![]),
+        ],
+    );
+    assert_eq!(
+        pattern.to_string(),
+        "(Just (Just _), \"a \\\"b\\\"\", Nothing)"
+    );
+}
@@ -1,3 +1,7 @@
+pub mod builtin;
 pub mod expr;
+pub mod module;
+pub mod runtimeerror;
 pub mod ty;
 pub mod typeerror;
+pub mod value;
@@ -0,0 +1,78 @@
+use super::expr::{map_expr, Expr};
+use super::ty::{map_type, Type};
+
+// a whole source file, a list of top level definitions
+#[derive(Debug, PartialEq, Clone)]
+pub struct Module<Ann> {
+    pub datas: Vec<Data<Ann>>,
+    pub defs: Vec<Def<Ann>>,
+}
+
+// `data Maybe a = Just a | Nothing`
+// fields refer to the type parameters as type variables numbered by their
+// position, so `a` is `TVar { var: 0 }`
+#[derive(Debug, PartialEq, Clone)]
+pub struct Data<Ann> {
+    pub ann: Ann,
+    pub name: String,
+    pub params: Vec<String>,
+    pub constructors: Vec<DataConstructor<Ann>>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct DataConstructor<Ann> {
+    pub ann: Ann,
+    pub name: String,
+    pub fields: Vec<Type<Ann>>,
+}
+
+// `def name : signature = expr`, where the signature is optional
+#[derive(Debug, PartialEq, Clone)]
+pub struct Def<Ann> {
+    pub ann: Ann,
+    pub identifier: String,
+    pub signature: Option<Type<Ann>>,
+    pub expr: Expr<Ann>,
+}
+
+pub fn map_module<F, A, B>(module: Module<A>, f: F) -> Module<B>
+where
+    F: FnOnce(A) -> B + Copy,
+    A: Clone,
+    B: Clone,
+{
+    Module {
+        datas: module
+            .datas
+            .into_iter()
+            .map(|data| Data {
+                ann: f(data.ann),
+                name: data.name,
+                params: data.params,
+                constructors: data
+                    .constructors
+                    .into_iter()
+                    .map(|constructor| DataConstructor {
+                        ann: f(constructor.ann),
+                        name: constructor.name,
+                        fields: constructor
+                            .fields
+                            .into_iter()
+                            .map(|field| map_type(field, f))
+                            .collect(),
+                    })
+                    .collect(),
+            })
+            .collect(),
+        defs: module
+            .defs
+            .into_iter()
+            .map(|def| Def {
+                ann: f(def.ann),
+                identifier: def.identifier,
+                signature: def.signature.map(|signature| map_type(signature, f)),
+                expr: map_expr(def.expr, f),
+            })
+            .collect(),
+    }
+}
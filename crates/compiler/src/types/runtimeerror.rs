@@ -0,0 +1,55 @@
+use super::builtin::Builtin;
+use super::expr::Op;
+use super::value::Value;
+
+#[derive(Debug, PartialEq)]
+pub enum RuntimeError<Ann>
+where
+    Ann: Clone,
+{
+    UnboundVariable {
+        ann: Ann,
+        identifier: String,
+    },
+    PredicateShouldBeBool {
+        ann: Ann,
+        found: Value<Ann>,
+    },
+    InvalidOperands {
+        ann: Ann,
+        op: Op,
+        left: Value<Ann>,
+        right: Value<Ann>,
+    },
+    // these match the traps wasm produces for `i32.div_s` and `i32.rem_s`
+    DivisionByZero {
+        ann: Ann,
+    },
+    IntegerOverflow {
+        ann: Ann,
+    },
+    ApplyingNonFunction {
+        ann: Ann,
+        found: Value<Ann>,
+    },
+    InvalidBuiltinArgument {
+        ann: Ann,
+        builtin: Builtin,
+        argument: Value<Ann>,
+    },
+    // `found` isn't a record with `field`
+    MissingField {
+        ann: Ann,
+        field: String,
+        found: Value<Ann>,
+    },
+    // none of the arms of a `case` matched the value
+    NoMatchingPattern {
+        ann: Ann,
+        found: Value<Ann>,
+    },
+    // asked to run a definition the module doesn't have
+    UnknownDefinition {
+        identifier: String,
+    },
+}
@@ -1,22 +1,84 @@
+use crate::pretty::print_type::print_type;
 
-#[derive(Debug, PartialEq, Clone, Copy)]
-pub enum Type<Ann>
-where
-    Ann: Clone + Copy,
-{
-    TInt { ann: Ann },
-    TBool { ann: Ann },
+use std::fmt;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Type<Ann> {
+    TInt {
+        ann: Ann,
+    },
+    TBool {
+        ann: Ann,
+    },
+    TString {
+        ann: Ann,
+    },
+    TFunction {
+        ann: Ann,
+        argument: Box<Type<Ann>>,
+        result: Box<Type<Ann>>,
+    },
+    // a type declared with `data`, applied to its arguments, ie `Maybe Int`
+    TConstructor {
+        ann: Ann,
+        name: String,
+        args: Vec<Type<Ann>>,
+    },
+    // `(Int, Bool)`, always at least two items
+    TTuple {
+        ann: Ann,
+        items: Vec<Type<Ann>>,
+    },
+    // `{ name: Int, ok: Bool }`, with fields sorted by name so records with
+    // the same fields in a different order have the same type
+    TRecord {
+        ann: Ann,
+        fields: Vec<(String, Type<Ann>)>,
+    },
+    // a type variable, either one we're still solving or one that has been
+    // generalised in a `let`
+    TVar {
+        ann: Ann,
+        var: u32,
+    },
 }
 
 pub fn map_type<F, A, B>(a: Type<A>, f: F) -> Type<B>
 where
-    F: FnOnce(A) -> B,
-    A: Clone + Copy,
-    B: Clone + Copy,
+    F: FnOnce(A) -> B + Copy,
+    A: Clone,
+    B: Clone,
 {
     match a {
         Type::TInt { ann } => Type::TInt { ann: f(ann) },
         Type::TBool { ann } => Type::TBool { ann: f(ann) },
+        Type::TString { ann } => Type::TString { ann: f(ann) },
+        Type::TFunction {
+            ann,
+            argument,
+            result,
+        } => Type::TFunction {
+            ann: f(ann),
+            argument: Box::new(map_type(*argument, f)),
+            result: Box::new(map_type(*result, f)),
+        },
+        Type::TConstructor { ann, name, args } => Type::TConstructor {
+            ann: f(ann),
+            name,
+            args: args.into_iter().map(|arg| map_type(arg, f)).collect(),
+        },
+        Type::TTuple { ann, items } => Type::TTuple {
+            ann: f(ann),
+            items: items.into_iter().map(|item| map_type(item, f)).collect(),
+        },
+        Type::TRecord { ann, fields } => Type::TRecord {
+            ann: f(ann),
+            fields: fields
+                .into_iter()
+                .map(|(name, ty)| (name, map_type(ty, f)))
+                .collect(),
+        },
+        Type::TVar { ann, var } => Type::TVar { ann: f(ann), var },
     }
 }
 
@@ -27,4 +89,74 @@ where
     map_type(ty, |_| ())
 }
 
+pub fn get_type_annotation<Ann>(ty: &Type<Ann>) -> Ann
+where
+    Ann: Clone + Copy,
+{
+    match ty {
+        Type::TInt { ann }
+        | Type::TBool { ann }
+        | Type::TString { ann }
+        | Type::TFunction { ann, .. }
+        | Type::TConstructor { ann, .. }
+        | Type::TTuple { ann, .. }
+        | Type::TRecord { ann, .. }
+        | Type::TVar { ann, .. } => *ann,
+    }
+}
 
+// type variables are shown as letters, `a` to `z` then `a1` and so on
+pub fn type_var_name(var: u32) -> String {
+    let letter = char::from(b'a' + (var % 26) as u8);
+    match var / 26 {
+        0 => letter.to_string(),
+        round => format!("{}{}", letter, round),
+    }
+}
+
+// types as they would be written in a signature, ie `Maybe Int -> Bool`
+impl<Ann> fmt::Display for Type<Ann> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", print_type(self, &[]))
+    }
+}
+
+#[test]
+fn test_display_type() {
+    let int = || Type::TInt { ann: () };
+    let function = |argument, result| Type::TFunction {
+        ann: (),
+        argument: Box::new(argument),
+        result: Box::new(result),
+    };
+    let maybe = |arg| Type::TConstructor {
+        ann: (),
+        name: "Maybe".to_string(),
+        args: vec![arg],
+    };
+
+    assert_eq!(
+        function(function(int(), int()), function(int(), int())).to_string(),
+        "(Int -> Int) -> Int -> Int"
+    );
+    assert_eq!(
+        maybe(maybe(Type::TVar { ann: (), var: 27 })).to_string(),
+        "Maybe (Maybe b1)"
+    );
+    assert_eq!(
+        Type::TTuple {
+            ann: (),
+            items: vec![int(), Type::TBool { ann: () }]
+        }
+        .to_string(),
+        "(Int, Bool)"
+    );
+    assert_eq!(
+        Type::TRecord {
+            ann: (),
+            fields: vec![("name".to_string(), Type::TString { ann: () })]
+        }
+        .to_string(),
+        "{ name: String }"
+    );
+}
@@ -1,4 +1,7 @@
-use super::ty::Type;
+use super::expr::Pattern;
+use super::ty::{get_type_annotation, Type};
+
+use std::fmt;
 
 #[derive(Debug, PartialEq)]
 pub enum TypeError<Ann>
@@ -18,4 +21,308 @@ where
         type_a: Type<Ann>,
         type_b: Type<Ann>,
     },
+    // an expression that doesn't have the type written for it, either in
+    // `(expr : Type)` or in a definition's signature. Each type is annotated
+    // with where it came from, the annotation or the expression
+    AnnotationMismatch {
+        annotated: Type<Ann>,
+        found: Type<Ann>,
+    },
+    UnboundVariable {
+        ann: Ann,
+        identifier: String,
+        // names in scope that look similar, closest first
+        suggestions: Vec<String>,
+    },
+    ApplyingNonFunction {
+        ann: Ann,
+        found: Type<Ann>,
+    },
+    // two top level definitions with the same name
+    DuplicateDefinition {
+        ann: Ann,
+        identifier: String,
+    },
+    // `let rec` binding something other than a function
+    RecursiveNonFunction {
+        ann: Ann,
+        identifier: String,
+    },
+    // solving `var` would need it to contain itself
+    InfiniteType {
+        var: Type<Ann>,
+        ty: Type<Ann>,
+    },
+    // a constructor that no `data` declaration defines
+    UnknownConstructor {
+        ann: Ann,
+        constructor: String,
+    },
+    ConstructorArityMismatch {
+        ann: Ann,
+        constructor: String,
+        expected: usize,
+        found: usize,
+    },
+    // a type name that is neither built in nor declared with `data`
+    UnknownType {
+        ann: Ann,
+        name: String,
+    },
+    TypeArityMismatch {
+        ann: Ann,
+        name: String,
+        expected: usize,
+        found: usize,
+    },
+    // two `data` declarations with the same name
+    DuplicateType {
+        ann: Ann,
+        name: String,
+    },
+    // two constructors with the same name, in any `data` declarations
+    DuplicateConstructor {
+        ann: Ann,
+        constructor: String,
+    },
+    // a record literal, record update or record type that mentions the
+    // same field twice
+    DuplicateField {
+        ann: Ann,
+        field: String,
+    },
+    // accessing or updating a field the record doesn't have
+    MissingField {
+        ann: Ann,
+        field: String,
+        found: Type<Ann>,
+    },
+    // accessing or updating a field of something that isn't a record, or
+    // that we don't know is a record yet
+    AccessingNonRecord {
+        ann: Ann,
+        found: Type<Ann>,
+    },
+    // `==` or `!=` on something other than an int, bool or string, or on
+    // something whose type we don't know yet
+    NotComparable {
+        ann: Ann,
+        found: Type<Ann>,
+    },
+    // a `case` that doesn't match every value, along with an example of a
+    // value it doesn't match
+    NonExhaustivePatterns {
+        ann: Ann,
+        missing: Pattern<()>,
+    },
+    // a `case` arm that can never match, because the arms before it match
+    // everything it does
+    RedundantPattern {
+        ann: Ann,
+    },
+}
+
+// the part of the source an error is about
+// errors about two types point at the one we found
+pub fn get_type_error_annotation<Ann>(error: &TypeError<Ann>) -> Ann
+where
+    Ann: Clone + Copy,
+{
+    match error {
+        TypeError::PredicateShouldBeBool { ann, .. }
+        | TypeError::MismatchedIfBranches { ann, .. }
+        | TypeError::UnboundVariable { ann, .. }
+        | TypeError::ApplyingNonFunction { ann, .. }
+        | TypeError::DuplicateDefinition { ann, .. }
+        | TypeError::RecursiveNonFunction { ann, .. }
+        | TypeError::UnknownConstructor { ann, .. }
+        | TypeError::ConstructorArityMismatch { ann, .. }
+        | TypeError::UnknownType { ann, .. }
+        | TypeError::TypeArityMismatch { ann, .. }
+        | TypeError::DuplicateType { ann, .. }
+        | TypeError::DuplicateConstructor { ann, .. }
+        | TypeError::DuplicateField { ann, .. }
+        | TypeError::MissingField { ann, .. }
+        | TypeError::AccessingNonRecord { ann, .. }
+        | TypeError::NotComparable { ann, .. }
+        | TypeError::NonExhaustivePatterns { ann, .. }
+        | TypeError::RedundantPattern { ann } => *ann,
+        TypeError::TypeMismatch { type_b, .. } => get_type_annotation(type_b),
+        TypeError::AnnotationMismatch { found, .. } => get_type_annotation(found),
+        TypeError::InfiniteType { var, .. } => get_type_annotation(var),
+    }
+}
+
+// "1 argument", "2 arguments"
+fn count(amount: usize, thing: &str) -> String {
+    match amount {
+        1 => format!("1 {}", thing),
+        _ => format!("{} {}s", amount, thing),
+    }
+}
+
+impl<Ann> fmt::Display for TypeError<Ann>
+where
+    Ann: Clone + Copy,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TypeError::PredicateShouldBeBool { found, .. } => write!(
+                f,
+                "the condition of an `if` must be a Bool, but this is {}",
+                found
+            ),
+            TypeError::MismatchedIfBranches {
+                then_found,
+                else_found,
+                ..
+            } => write!(
+                f,
+                "both branches of an `if` must have the same type, but one is {} and the other is {}",
+                then_found, else_found
+            ),
+            TypeError::TypeMismatch { type_a, type_b } => {
+                write!(f, "expected {}, but found {}", type_a, type_b)
+            }
+            TypeError::AnnotationMismatch { annotated, found } => write!(
+                f,
+                "this has type {}, but it is annotated as {}",
+                found, annotated
+            ),
+            TypeError::UnboundVariable {
+                identifier,
+                suggestions,
+                ..
+            } => match suggestions.first() {
+                Some(suggestion) => write!(
+                    f,
+                    "`{}` is not in scope, did you mean `{}`?",
+                    identifier, suggestion
+                ),
+                None => write!(f, "`{}` is not in scope", identifier),
+            },
+            TypeError::ApplyingNonFunction { found, .. } => {
+                write!(f, "this is {}, not a function, so it can't be applied", found)
+            }
+            TypeError::DuplicateDefinition { identifier, .. } => {
+                write!(f, "`{}` is defined more than once", identifier)
+            }
+            TypeError::RecursiveNonFunction { identifier, .. } => write!(
+                f,
+                "`let rec` can only bind functions, but `{}` isn't one",
+                identifier
+            ),
+            TypeError::InfiniteType { var, ty } => write!(
+                f,
+                "{} would have to contain itself, as {} = {}",
+                var, var, ty
+            ),
+            TypeError::UnknownConstructor { constructor, .. } => {
+                write!(f, "there is no constructor called `{}`", constructor)
+            }
+            TypeError::ConstructorArityMismatch {
+                constructor,
+                expected,
+                found,
+                ..
+            } => write!(
+                f,
+                "`{}` takes {}, but was given {}",
+                constructor,
+                count(*expected, "argument"),
+                found
+            ),
+            TypeError::UnknownType { name, .. } => {
+                write!(f, "there is no type called `{}`", name)
+            }
+            TypeError::TypeArityMismatch {
+                name,
+                expected,
+                found,
+                ..
+            } => write!(
+                f,
+                "`{}` takes {}, but was given {}",
+                name,
+                count(*expected, "type argument"),
+                found
+            ),
+            TypeError::DuplicateType { name, .. } => {
+                write!(f, "the type `{}` is declared more than once", name)
+            }
+            TypeError::DuplicateConstructor { constructor, .. } => write!(
+                f,
+                "the constructor `{}` is declared more than once",
+                constructor
+            ),
+            TypeError::DuplicateField { field, .. } => {
+                write!(f, "the field `{}` appears more than once", field)
+            }
+            TypeError::MissingField { field, found, .. } => {
+                write!(f, "{} has no field called `{}`", found, field)
+            }
+            TypeError::AccessingNonRecord {
+                found: Type::TVar { .. },
+                ..
+            } => write!(
+                f,
+                "the type of this isn't known yet, so its fields can't be used, try annotating it"
+            ),
+            TypeError::AccessingNonRecord { found, .. } => {
+                write!(f, "this is {}, not a record, so it has no fields", found)
+            }
+            TypeError::NotComparable {
+                found: Type::TVar { .. },
+                ..
+            } => write!(
+                f,
+                "the type of this isn't known yet, so it can't be compared, try annotating it"
+            ),
+            TypeError::NotComparable { found, .. } => write!(
+                f,
+                "only Int, Bool and String can be compared, but this is {}",
+                found
+            ),
+            TypeError::NonExhaustivePatterns { missing, .. } => write!(
+                f,
+                "this `case` doesn't match every value, for example `{}`",
+                missing
+            ),
+            TypeError::RedundantPattern { .. } => write!(
+                f,
+                "this pattern can never match, the arms before it match everything it does"
+            ),
+        }
+    }
+}
+
+#[test]
+fn test_display_type_error() {
+    assert_eq!(
+        TypeError::TypeMismatch {
+            type_a: Type::TInt { ann: () },
+            type_b: Type::TBool { ann: () }
+        }
+        .to_string(),
+        "expected Int, but found Bool"
+    );
+    assert_eq!(
+        TypeError::ConstructorArityMismatch {
+            ann: (),
+            constructor: "Just".to_string(),
+            expected: 1,
+            found: 2
+        }
+        .to_string(),
+        "`Just` takes 1 argument, but was given 2"
+    );
+    assert_eq!(
+        TypeError::UnboundVariable {
+            ann: (),
+            identifier: "horse".to_string(),
+            suggestions: vec!["house".to_string()]
+        }
+        .to_string(),
+        "`horse` is not in scope, did you mean `house`?"
+    );
 }
@@ -0,0 +1,47 @@
+use super::builtin::Builtin;
+use super::expr::Expr;
+
+use std::collections::HashMap;
+
+// the result of running an expression in the interpreter
+#[derive(Debug, PartialEq, Clone)]
+pub enum Value<Ann> {
+    VInt {
+        int: i32,
+    },
+    VBool {
+        bool: bool,
+    },
+    VString {
+        string: String,
+    },
+    // a lambda along with the variables it could see when it was created
+    VClosure {
+        env: HashMap<String, Value<Ann>>,
+        identifier: String,
+        body_expr: Expr<Ann>,
+    },
+    // a function bound with `let rec`, which can see itself as `name`
+    VRecursiveClosure {
+        env: HashMap<String, Value<Ann>>,
+        name: String,
+        identifier: String,
+        body_expr: Expr<Ann>,
+    },
+    // a function built into the language, waiting for its argument
+    VBuiltin {
+        builtin: Builtin,
+    },
+    VTuple {
+        items: Vec<Value<Ann>>,
+    },
+    // fields sorted by name
+    VRecord {
+        fields: Vec<(String, Value<Ann>)>,
+    },
+    // a value made with a constructor from a `data` declaration
+    VData {
+        constructor: String,
+        fields: Vec<Value<Ann>>,
+    },
+}
@@ -0,0 +1,141 @@
+use crate::scope::{contains, innermost, Binder};
+use rusty::parser::parse_expr::PROTECTED_WORDS;
+use rusty::parser::parse_module::parse_my_module;
+use rusty::parser::span::{ParseInput, Span};
+use rusty::typecheck::elaborate::elaborate_module;
+use rusty::typecheck::unify::normalise_type_vars;
+use rusty::types::expr::get_expr_annotation;
+use rusty::types::module::Module;
+use tower_lsp::lsp_types::{CompletionItem, CompletionItemKind};
+
+// stands in for the word being typed, which usually isn't bound to anything
+// yet, so the document still parses and typechecks
+// it is defined as itself, so it can be used at any type
+const HOLE: &str = "smolcompletionhole";
+
+// everything that could go at `offset` that starts with the word being typed
+pub fn completions(source: &str, offset: usize) -> Vec<CompletionItem> {
+    let is_word = |c: char| c.is_alphabetic();
+    let start = source[..offset].rfind(|c| !is_word(c)).map_or(0, |before| {
+        before + source[before..].chars().next().unwrap().len_utf8()
+    });
+    let end = source[offset..]
+        .find(|c| !is_word(c))
+        .map_or(source.len(), |after| offset + after);
+    let prefix = &source[start..offset];
+
+    let mut items: Vec<CompletionItem> = PROTECTED_WORDS
+        .iter()
+        .map(|keyword| CompletionItem {
+            label: keyword.to_string(),
+            kind: Some(CompletionItemKind::KEYWORD),
+            ..Default::default()
+        })
+        .collect();
+
+    let with_hole = format!(
+        "{}{}{}\ndef {} = {}",
+        &source[..start],
+        HOLE,
+        &source[end..],
+        HOLE,
+        HOLE
+    );
+    if let Ok((_, module)) = parse_my_module(ParseInput::new(&with_hole)) {
+        // without types we can still say what's in scope
+        let variables = match elaborate_module(module.clone()) {
+            Ok(typed) => variables(&module, &typed, start, |binder| {
+                Some(normalise_type_vars(binder.bound_type()).to_string())
+            }),
+            Err(_) => variables(&module, &module, start, |_| None),
+        };
+        items.extend(variables);
+    }
+
+    items.retain(|item| item.label.starts_with(prefix) && item.label != HOLE);
+    items
+}
+
+// the variables in scope at `offset`, innermost first, skipping any that
+// are shadowed
+fn variables<Ann, F>(
+    parsed: &Module<Span>,
+    module: &Module<Ann>,
+    offset: usize,
+    detail: F,
+) -> Vec<CompletionItem>
+where
+    F: Fn(&Binder<Ann>) -> Option<String>,
+{
+    let mut scope: Vec<Binder<Ann>> = module.defs.iter().map(Binder::Def).collect();
+    let def = parsed
+        .defs
+        .iter()
+        .zip(&module.defs)
+        .find(|(def, _)| contains(&get_expr_annotation(def.expr.clone()), offset));
+    if let Some((parsed_def, def)) = def {
+        innermost(&parsed_def.expr, &def.expr, offset, &mut scope);
+    }
+
+    let mut items: Vec<CompletionItem> = vec![];
+    for binder in scope.iter().rev() {
+        if items.iter().any(|item| item.label == binder.identifier()) {
+            continue;
+        }
+        items.push(CompletionItem {
+            label: binder.identifier().to_string(),
+            kind: Some(match binder {
+                Binder::Def(_) => CompletionItemKind::FUNCTION,
+                _ => CompletionItemKind::VARIABLE,
+            }),
+            detail: detail(binder),
+            ..Default::default()
+        });
+    }
+    items
+}
+
+#[cfg(test)]
+fn completions_at(source: &str) -> Vec<(String, Option<String>)> {
+    // `|` marks the cursor
+    let offset = source.find('|').unwrap();
+    let source = source.replace('|', "");
+    completions(&source, offset)
+        .into_iter()
+        .map(|item| (item.label, item.detail))
+        .collect()
+}
+
+#[test]
+fn test_completions() {
+    let labels = |source| -> Vec<String> {
+        completions_at(source)
+            .into_iter()
+            .map(|(label, _)| label)
+            .collect()
+    };
+
+    assert_eq!(
+        completions_at("def main = let apple = 1 in let avocado = \\a -> a in a|"),
+        vec![
+            ("avocado".to_string(), Some("a -> a".to_string())),
+            ("apple".to_string(), Some("Int".to_string())),
+        ]
+    );
+    // `apple` isn't in scope in its own definition, and `t` starts keywords
+    assert_eq!(
+        labels("def main = let apple = a| in apple"),
+        Vec::<String>::new()
+    );
+    assert_eq!(labels("def main = if True t| "), vec!["then"]);
+    // shadowing, and a top level definition
+    assert_eq!(
+        completions_at("def pear = 1\ndef main = \\pear -> p|"),
+        vec![("pear".to_string(), Some("a".to_string()))]
+    );
+    // it doesn't need to typecheck
+    assert_eq!(
+        completions_at("def pear = 1\ndef main = \\plum -> plum + True + p|"),
+        vec![("plum".to_string(), None), ("pear".to_string(), None)]
+    );
+}
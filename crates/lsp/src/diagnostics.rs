@@ -0,0 +1,107 @@
+use crate::position::{offset_to_position, span_to_range};
+use rusty::parser::parse_module::parse_my_module;
+use rusty::parser::span::{ParseInput, Span};
+use rusty::typecheck::elaborate::elaborate_module;
+use rusty::types::ty::get_type_annotation;
+use rusty::types::typeerror::{get_type_error_annotation, TypeError};
+use tower_lsp::lsp_types::{
+    Diagnostic, DiagnosticRelatedInformation, DiagnosticSeverity, Location, Range, Url,
+};
+
+// everything wrong with a document, which is at most one error for now, as
+// both the parser and typechecker stop at the first one
+pub fn diagnostics(uri: &Url, source: &str) -> Vec<Diagnostic> {
+    let module = match parse_my_module(ParseInput::new(source)) {
+        Ok((_, module)) => module,
+        Err(nom::Err::Error(err) | nom::Err::Failure(err)) => {
+            return vec![error(
+                span_to_range(source, &err.span),
+                err.kind.to_string(),
+            )]
+        }
+        Err(nom::Err::Incomplete(_)) => {
+            let end = offset_to_position(source, source.len());
+            return vec![error(
+                Range::new(end, end),
+                "unexpected end of file".to_string(),
+            )];
+        }
+    };
+
+    match elaborate_module(module) {
+        Ok(_) => vec![],
+        Err(type_error) => vec![type_error_diagnostic(uri, source, &type_error)],
+    }
+}
+
+fn type_error_diagnostic(uri: &Url, source: &str, type_error: &TypeError<Span>) -> Diagnostic {
+    let span = get_type_error_annotation(type_error);
+    let mut diagnostic = error(span_to_range(source, &span), type_error.to_string());
+
+    // point at the annotation too, it might be the part that's wrong
+    if let TypeError::AnnotationMismatch { annotated, .. } = type_error {
+        diagnostic.related_information = Some(vec![DiagnosticRelatedInformation {
+            location: Location {
+                uri: uri.clone(),
+                range: span_to_range(source, &get_type_annotation(annotated)),
+            },
+            message: format!("annotated as {}", annotated),
+        }]);
+    }
+
+    diagnostic
+}
+
+fn error(range: Range, message: String) -> Diagnostic {
+    Diagnostic {
+        range,
+        severity: Some(DiagnosticSeverity::ERROR),
+        source: Some("smol".to_string()),
+        message,
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+fn diagnostics_for(source: &str) -> Vec<(Range, String)> {
+    let uri = Url::parse("file:///test.smol").unwrap();
+    diagnostics(&uri, source)
+        .into_iter()
+        .map(|diagnostic| (diagnostic.range, diagnostic.message))
+        .collect()
+}
+
+#[test]
+fn test_diagnostics() {
+    use tower_lsp::lsp_types::Position;
+
+    assert_eq!(diagnostics_for("def main = 1 + 2"), vec![]);
+    assert_eq!(
+        diagnostics_for("def main =\n  1 + True"),
+        vec![(
+            Range::new(Position::new(1, 6), Position::new(1, 10)),
+            "expected Int, but found Bool".to_string()
+        )]
+    );
+    assert_eq!(
+        diagnostics_for("def main = horse"),
+        vec![(
+            Range::new(Position::new(0, 11), Position::new(0, 16)),
+            "`horse` is not in scope".to_string()
+        )]
+    );
+    assert_eq!(
+        diagnostics_for("def main = \"horse"),
+        vec![(
+            Range::new(Position::new(0, 11), Position::new(0, 17)),
+            "this string has no closing `\"`".to_string()
+        )]
+    );
+    assert_eq!(
+        diagnostics_for("def a = 1\ndef main = let x = 1 in"),
+        vec![(
+            Range::new(Position::new(1, 23), Position::new(1, 23)),
+            "unexpected end of file".to_string()
+        )]
+    );
+}
@@ -0,0 +1,70 @@
+use rusty::parser::parse_module::parse_my_module;
+use rusty::parser::span::{ParseInput, Span};
+use rusty::pretty::print_expr::DEFAULT_WIDTH;
+use rusty::pretty::print_module::{print_data, print_def, print_module};
+use std::ops::Range;
+
+// the whole document reformatted, if it parses and isn't formatted already
+pub fn format_document(source: &str) -> Option<Vec<(Range<usize>, String)>> {
+    let (_, module) = parse_my_module(ParseInput::new(source)).ok()?;
+    let formatted = print_module(&module, DEFAULT_WIDTH);
+    match formatted == source {
+        true => Some(vec![]),
+        false => Some(vec![(0..source.len(), formatted)]),
+    }
+}
+
+// every top level `def` or `data` that overlaps `range`, reformatted
+// they stay where they are, unlike when we format everything
+pub fn format_range(source: &str, range: Range<usize>) -> Option<Vec<(Range<usize>, String)>> {
+    let (_, module) = parse_my_module(ParseInput::new(source)).ok()?;
+    let overlaps = |span: &Span| span.start.offset <= range.end && range.start <= span.end.offset;
+
+    let mut edits: Vec<(Range<usize>, String)> = module
+        .datas
+        .iter()
+        .filter(|data| overlaps(&data.ann))
+        .map(|data| (offsets(&data.ann), print_data(data, DEFAULT_WIDTH)))
+        .chain(
+            module
+                .defs
+                .iter()
+                .filter(|def| overlaps(&def.ann))
+                .map(|def| (offsets(&def.ann), print_def(def, DEFAULT_WIDTH))),
+        )
+        .filter(|(range, formatted)| &source[range.clone()] != formatted)
+        .collect();
+    edits.sort_by_key(|(range, _)| range.start);
+    Some(edits)
+}
+
+fn offsets(span: &Span) -> Range<usize> {
+    span.start.offset..span.end.offset
+}
+
+#[test]
+fn test_format_document() {
+    assert_eq!(
+        format_document("def   main=1+2"),
+        Some(vec![(0..14, "def main = 1 + 2\n".to_string())])
+    );
+    assert_eq!(format_document("def main = 1 + 2\n"), Some(vec![]));
+    assert_eq!(format_document("def main = ("), None);
+}
+
+#[test]
+fn test_format_range() {
+    let source = "def a=1\n\ndef b=2\n\ndata   T = T";
+    assert_eq!(
+        format_range(source, 10..11),
+        Some(vec![(9..16, "def b = 2".to_string())])
+    );
+    assert_eq!(
+        format_range(source, 0..source.len()),
+        Some(vec![
+            (0..7, "def a = 1".to_string()),
+            (9..16, "def b = 2".to_string()),
+            (18..30, "data T = T".to_string()),
+        ])
+    );
+}
@@ -0,0 +1,105 @@
+use crate::position::span_to_range;
+use crate::scope::{contains, innermost, Binder};
+use rusty::parser::parse_module::parse_my_module;
+use rusty::parser::span::{ParseInput, Span};
+use rusty::typecheck::elaborate::elaborate_module;
+use rusty::typecheck::unify::normalise_type_vars;
+use rusty::types::expr::{get_expr_annotation, Expr};
+use rusty::types::ty::Type;
+use tower_lsp::lsp_types::{Hover, HoverContents, MarkupContent, MarkupKind};
+
+// the type of the innermost expression at `offset`, and for variables the
+// type of whatever they were bound to
+// we can only do this for documents that typecheck
+pub fn hover(source: &str, offset: usize) -> Option<Hover> {
+    let (_, module) = parse_my_module(ParseInput::new(source)).ok()?;
+    let typed = elaborate_module(module.clone()).ok()?;
+
+    let (def, typed_def) = module
+        .defs
+        .iter()
+        .zip(&typed.defs)
+        .find(|(def, _)| contains(&def.ann, offset))?;
+
+    let expr_span = get_expr_annotation(def.expr.clone());
+    if !contains(&expr_span, offset) {
+        // somewhere in `def name : signature =`
+        return Some(markdown(
+            &def.ann,
+            source,
+            code(format!(
+                "{} : {}",
+                def.identifier,
+                normalise_type_vars(typed_def.ann.clone())
+            )),
+        ));
+    }
+
+    let mut scope: Vec<Binder<Type<Span>>> = typed.defs.iter().map(Binder::Def).collect();
+    let (span, typed_expr) = innermost(&def.expr, &typed_def.expr, offset, &mut scope);
+    let ty = normalise_type_vars(get_expr_annotation(typed_expr.clone()));
+
+    let value = match typed_expr {
+        Expr::EVar { identifier, .. } => {
+            let mut value = code(format!("{} : {}", identifier, ty));
+            let binder = scope
+                .iter()
+                .rev()
+                .find(|binder| binder.identifier() == identifier);
+            if let Some(binder) = binder {
+                value.push_str("\n\ndefined as\n\n");
+                value.push_str(&code(normalise_type_vars(binder.bound_type()).to_string()));
+            }
+            value
+        }
+        _ => code(ty.to_string()),
+    };
+
+    Some(markdown(&span, source, value))
+}
+
+fn code(code: String) -> String {
+    format!("```smol\n{}\n```", code)
+}
+
+fn markdown(span: &Span, source: &str, value: String) -> Hover {
+    Hover {
+        contents: HoverContents::Markup(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value,
+        }),
+        range: Some(span_to_range(source, span)),
+    }
+}
+
+#[cfg(test)]
+fn hover_at(source: &str, needle: &str) -> Option<String> {
+    let offset = source.find(needle).unwrap();
+    hover(source, offset).map(|hover| match hover.contents {
+        HoverContents::Markup(markup) => markup.value,
+        _ => unreachable!(),
+    })
+}
+
+#[test]
+fn test_hover() {
+    let source = "def id = \\x -> x\ndef main = let f = id in f (1 + 2) == 3";
+    assert_eq!(
+        hover_at(source, "1 +"),
+        Some("```smol\nInt\n```".to_string())
+    );
+    assert_eq!(
+        hover_at(source, "=="),
+        Some("```smol\nBool\n```".to_string())
+    );
+    // `f` is used at `Int`, but was bound to something more general
+    assert_eq!(
+        hover_at(source, "f ("),
+        Some("```smol\nf : Int -> Int\n```\n\ndefined as\n\n```smol\na -> a\n```".to_string())
+    );
+    assert_eq!(
+        hover_at(source, "id ="),
+        Some("```smol\nid : a -> a\n```".to_string())
+    );
+    assert_eq!(hover("def main = 1 + True", 11), None);
+}
@@ -1,3 +1,15 @@
+mod completion;
+mod diagnostics;
+mod formatting;
+mod hover;
+mod position;
+mod resolve;
+mod scope;
+
+use diagnostics::diagnostics;
+use position::{offsets_to_range, position_to_offset};
+use std::collections::HashMap;
+use std::sync::Mutex;
 use tower_lsp::jsonrpc::Result;
 use tower_lsp::lsp_types::*;
 use tower_lsp::{Client, LanguageServer, LspService, Server};
@@ -5,6 +17,22 @@ use tower_lsp::{Client, LanguageServer, LspService, Server};
 #[derive(Debug)]
 struct Backend {
     client: Client,
+    // the latest text of every open document
+    documents: Mutex<HashMap<Url, String>>,
+}
+
+impl Backend {
+    fn document(&self, uri: &Url) -> Option<String> {
+        self.documents.lock().unwrap().get(uri).cloned()
+    }
+
+    async fn update_document(&self, uri: Url, text: String, version: Option<i32>) {
+        let diagnostics = diagnostics(&uri, &text);
+        self.documents.lock().unwrap().insert(uri.clone(), text);
+        self.client
+            .publish_diagnostics(uri, diagnostics, version)
+            .await;
+    }
 }
 
 #[tower_lsp::async_trait]
@@ -12,8 +40,16 @@ impl LanguageServer for Backend {
     async fn initialize(&self, _: InitializeParams) -> Result<InitializeResult> {
         Ok(InitializeResult {
             capabilities: ServerCapabilities {
+                text_document_sync: Some(TextDocumentSyncCapability::Kind(
+                    TextDocumentSyncKind::FULL,
+                )),
                 hover_provider: Some(HoverProviderCapability::Simple(true)),
                 completion_provider: Some(CompletionOptions::default()),
+                definition_provider: Some(OneOf::Left(true)),
+                references_provider: Some(OneOf::Left(true)),
+                rename_provider: Some(OneOf::Left(true)),
+                document_formatting_provider: Some(OneOf::Left(true)),
+                document_range_formatting_provider: Some(OneOf::Left(true)),
                 ..Default::default()
             },
             ..Default::default()
@@ -30,19 +66,125 @@ impl LanguageServer for Backend {
         Ok(())
     }
 
-    async fn completion(&self, _: CompletionParams) -> Result<Option<CompletionResponse>> {
-        Ok(Some(CompletionResponse::Array(vec![
-            CompletionItem::new_simple("Horse".to_string(), "Horses".to_string()),
-            CompletionItem::new_simple("stable".to_string(), "where horse lives".to_string()),
-        ])))
+    async fn did_open(&self, params: DidOpenTextDocumentParams) {
+        let document = params.text_document;
+        self.update_document(document.uri, document.text, Some(document.version))
+            .await;
+    }
+
+    // we only ask for full syncs, so the last change is the whole document
+    async fn did_change(&self, mut params: DidChangeTextDocumentParams) {
+        if let Some(change) = params.content_changes.pop() {
+            let document = params.text_document;
+            self.update_document(document.uri, change.text, Some(document.version))
+                .await;
+        }
+    }
+
+    async fn did_close(&self, params: DidCloseTextDocumentParams) {
+        let uri = params.text_document.uri;
+        self.documents.lock().unwrap().remove(&uri);
+        self.client.publish_diagnostics(uri, vec![], None).await;
+    }
+
+    async fn completion(&self, params: CompletionParams) -> Result<Option<CompletionResponse>> {
+        let position = params.text_document_position;
+        Ok(self.document(&position.text_document.uri).map(|source| {
+            let offset = position_to_offset(&source, position.position);
+            CompletionResponse::Array(completion::completions(&source, offset))
+        }))
+    }
+
+    async fn hover(&self, params: HoverParams) -> Result<Option<Hover>> {
+        let position = params.text_document_position_params;
+        Ok(self
+            .document(&position.text_document.uri)
+            .and_then(|source| {
+                hover::hover(&source, position_to_offset(&source, position.position))
+            }))
     }
 
-    async fn hover(&self, _: HoverParams) -> Result<Option<Hover>> {
-        Ok(Some(Hover {
-            contents: HoverContents::Scalar(MarkedString::String("You're hovering!".to_string())),
-            range: None,
+    async fn goto_definition(
+        &self,
+        params: GotoDefinitionParams,
+    ) -> Result<Option<GotoDefinitionResponse>> {
+        let position = params.text_document_position_params;
+        let uri = position.text_document.uri;
+        Ok(self.document(&uri).and_then(|source| {
+            let offset = position_to_offset(&source, position.position);
+            resolve::definition(&source, offset).map(|range| {
+                GotoDefinitionResponse::Scalar(Location {
+                    uri: uri.clone(),
+                    range: offsets_to_range(&source, &range),
+                })
+            })
         }))
     }
+
+    async fn references(&self, params: ReferenceParams) -> Result<Option<Vec<Location>>> {
+        let position = params.text_document_position;
+        let uri = position.text_document.uri;
+        Ok(self.document(&uri).map(|source| {
+            let offset = position_to_offset(&source, position.position);
+            resolve::references(&source, offset, params.context.include_declaration)
+                .iter()
+                .map(|range| Location {
+                    uri: uri.clone(),
+                    range: offsets_to_range(&source, range),
+                })
+                .collect()
+        }))
+    }
+
+    async fn rename(&self, params: RenameParams) -> Result<Option<WorkspaceEdit>> {
+        let position = params.text_document_position;
+        let uri = position.text_document.uri;
+        let source = match self.document(&uri) {
+            Some(source) => source,
+            None => return Ok(None),
+        };
+        let offset = position_to_offset(&source, position.position);
+        let ranges = resolve::rename(&source, offset, &params.new_name)
+            .map_err(tower_lsp::jsonrpc::Error::invalid_params)?;
+        let edits = ranges
+            .iter()
+            .map(|range| TextEdit {
+                range: offsets_to_range(&source, range),
+                new_text: params.new_name.clone(),
+            })
+            .collect();
+        Ok(Some(WorkspaceEdit {
+            changes: Some(HashMap::from([(uri, edits)])),
+            ..Default::default()
+        }))
+    }
+
+    async fn formatting(&self, params: DocumentFormattingParams) -> Result<Option<Vec<TextEdit>>> {
+        Ok(self.document(&params.text_document.uri).and_then(|source| {
+            formatting::format_document(&source).map(|edits| text_edits(&source, edits))
+        }))
+    }
+
+    async fn range_formatting(
+        &self,
+        params: DocumentRangeFormattingParams,
+    ) -> Result<Option<Vec<TextEdit>>> {
+        Ok(self.document(&params.text_document.uri).and_then(|source| {
+            let range = position_to_offset(&source, params.range.start)
+                ..position_to_offset(&source, params.range.end);
+            formatting::format_range(&source, range).map(|edits| text_edits(&source, edits))
+        }))
+    }
+}
+
+fn text_edits(source: &str, edits: Vec<(std::ops::Range<usize>, String)>) -> Vec<TextEdit> {
+    edits
+        .into_iter()
+        .map(|(range, new_text)| TextEdit {
+            range: offsets_to_range(source, &range),
+            new_text,
+        })
+        .collect()
 }
 
 #[tokio::main]
@@ -50,6 +192,9 @@ async fn main() {
     let stdin = tokio::io::stdin();
     let stdout = tokio::io::stdout();
 
-    let (service, socket) = LspService::new(|client| Backend { client });
+    let (service, socket) = LspService::new(|client| Backend {
+        client,
+        documents: Mutex::new(HashMap::new()),
+    });
     Server::new(stdin, stdout, socket).serve(service).await;
 }
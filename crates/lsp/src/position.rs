@@ -0,0 +1,77 @@
+use rusty::parser::span::Span;
+use std::ops;
+use tower_lsp::lsp_types::{Position, Range};
+
+// lsp positions are 0-indexed lines and utf-16 columns, our spans are byte
+// offsets
+pub fn offset_to_position(source: &str, offset: usize) -> Position {
+    let offset = offset.min(source.len());
+    let before = &source[..offset];
+    let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+
+    Position {
+        line: before.matches('\n').count() as u32,
+        character: before[line_start..].encode_utf16().count() as u32,
+    }
+}
+
+pub fn span_to_range(source: &str, span: &Span) -> Range {
+    offsets_to_range(source, &(span.start.offset..span.end.offset))
+}
+
+pub fn offsets_to_range(source: &str, offsets: &ops::Range<usize>) -> Range {
+    Range {
+        start: offset_to_position(source, offsets.start),
+        end: offset_to_position(source, offsets.end),
+    }
+}
+
+#[test]
+fn test_offset_to_position() {
+    let source = "def a = 1\ndef ü = \"🐴\" ++ b";
+    assert_eq!(offset_to_position(source, 0), Position::new(0, 0));
+    assert_eq!(offset_to_position(source, 9), Position::new(0, 9));
+    assert_eq!(offset_to_position(source, 10), Position::new(1, 0));
+    // `b` comes after a two byte char and a four byte char
+    assert_eq!(
+        offset_to_position(source, source.len() - 1),
+        Position::new(1, 16)
+    );
+    assert_eq!(offset_to_position(source, 1000), Position::new(1, 17));
+}
+
+// the byte offset of an lsp position, clamped to the end of its line
+pub fn position_to_offset(source: &str, position: Position) -> usize {
+    let mut line_start = 0;
+    for _ in 0..position.line {
+        match source[line_start..].find('\n') {
+            Some(newline) => line_start += newline + 1,
+            None => return source.len(),
+        }
+    }
+
+    let mut units = 0;
+    for (index, c) in source[line_start..].char_indices() {
+        if c == '\n' || units >= position.character as usize {
+            return line_start + index;
+        }
+        units += c.len_utf16();
+    }
+    source.len()
+}
+
+#[test]
+fn test_position_to_offset() {
+    let source = "def a = 1\ndef ü = \"🐴\" ++ b";
+    for offset in [0, 9, 10, 14, source.len() - 1, source.len()] {
+        assert_eq!(
+            position_to_offset(source, offset_to_position(source, offset)),
+            offset
+        );
+    }
+    assert_eq!(position_to_offset(source, Position::new(0, 100)), 9);
+    assert_eq!(
+        position_to_offset(source, Position::new(5, 0)),
+        source.len()
+    );
+}
@@ -0,0 +1,315 @@
+use crate::scope::{children, Binder};
+use rusty::parser::parse_expr::parse_my_identifier;
+use rusty::parser::parse_module::parse_my_module;
+use rusty::parser::span::{ParseInput, Span};
+use rusty::types::expr::{get_expr_annotation, get_pattern_annotation, Expr};
+use rusty::types::module::Module;
+use std::ops::Range;
+
+// the place a variable is bound, ie the `a` in `let a = 1 in ...`
+#[derive(Debug, PartialEq)]
+pub struct Binding {
+    pub identifier: String,
+    pub range: Range<usize>,
+}
+
+// a variable, and the binding it refers to if it's one of ours
+#[derive(Debug, PartialEq)]
+pub struct Use {
+    pub identifier: String,
+    pub range: Range<usize>,
+    pub binding: Option<usize>,
+}
+
+// every binding in a module, and every variable with the binding it refers to
+// both are in the order we find them, which only depends on the shape of the
+// module, so it can be compared between two versions of the same document
+#[derive(Debug, PartialEq, Default)]
+pub struct Resolution {
+    pub bindings: Vec<Binding>,
+    pub uses: Vec<Use>,
+    // how many of the bindings, from the start, are top level definitions
+    pub defs: usize,
+}
+
+impl Resolution {
+    // the binding of the variable or binding at `offset`
+    fn binding_at(&self, offset: usize) -> Option<usize> {
+        let contains = |range: &Range<usize>| range.start <= offset && offset <= range.end;
+        self.bindings
+            .iter()
+            .position(|binding| contains(&binding.range))
+            .or_else(|| {
+                self.uses
+                    .iter()
+                    .find(|var| contains(&var.range))
+                    .and_then(|var| var.binding)
+            })
+    }
+
+    // the binding and every use of it
+    fn occurrences(&self, binding: usize) -> Vec<Range<usize>> {
+        std::iter::once(self.bindings[binding].range.clone())
+            .chain(
+                self.uses
+                    .iter()
+                    .filter(|var| var.binding == Some(binding))
+                    .map(|var| var.range.clone()),
+            )
+            .collect()
+    }
+}
+
+pub fn resolve(source: &str, module: &Module<Span>) -> Resolution {
+    let mut resolution = Resolution::default();
+    let mut scope: Vec<usize> = module
+        .defs
+        .iter()
+        .map(|def| add_binding(source, &Binder::Def(def), &mut resolution))
+        .collect();
+    resolution.defs = scope.len();
+    for def in &module.defs {
+        resolve_expr(source, &def.expr, &mut scope, &mut resolution);
+    }
+    resolution
+}
+
+fn resolve_expr(
+    source: &str,
+    expr: &Expr<Span>,
+    scope: &mut Vec<usize>,
+    resolution: &mut Resolution,
+) {
+    if let Expr::EVar { ann, identifier } = expr {
+        let binding = scope
+            .iter()
+            .rev()
+            .find(|&&binding| &resolution.bindings[binding].identifier == identifier)
+            .copied();
+        resolution.uses.push(Use {
+            identifier: identifier.clone(),
+            range: ann.start.offset..ann.end.offset,
+            binding,
+        });
+    }
+
+    for (binders, child) in children(expr) {
+        let count = binders.len();
+        for binder in binders {
+            let binding = add_binding(source, &binder, resolution);
+            scope.push(binding);
+        }
+        resolve_expr(source, child, scope, resolution);
+        scope.truncate(scope.len() - count);
+    }
+}
+
+// `let rec` hands us the same binder for both of its children, so we only
+// add it once
+fn add_binding(source: &str, binder: &Binder<Span>, resolution: &mut Resolution) -> usize {
+    let range = binder_range(source, binder);
+    match resolution
+        .bindings
+        .iter()
+        .position(|binding| binding.range == range)
+    {
+        Some(existing) => existing,
+        None => {
+            resolution.bindings.push(Binding {
+                identifier: binder.identifier().to_string(),
+                range,
+            });
+            resolution.bindings.len() - 1
+        }
+    }
+}
+
+// we only keep spans for whole expressions, so find the name in the source
+fn binder_range(source: &str, binder: &Binder<Span>) -> Range<usize> {
+    let identifier = binder.identifier();
+    let start = match binder {
+        // `def a`
+        Binder::Def(def) => first_word(source, def.ann.start.offset, identifier),
+        // `\a ->`
+        Binder::Argument { lambda, .. } => {
+            let span = get_expr_annotation((*lambda).clone());
+            first_word(source, span.start.offset, identifier)
+        }
+        // `let a =`, which is right before what it's bound to
+        Binder::Let { bound_expr, .. } => {
+            let span = get_expr_annotation((*bound_expr).clone());
+            last_word(source, span.start.offset, identifier)
+        }
+        Binder::Pattern { pattern, .. } => {
+            let span = get_pattern_annotation((*pattern).clone());
+            Some(span.start.offset)
+        }
+    }
+    .expect("binders are in the source");
+    start..start + identifier.len()
+}
+
+fn is_word_at(source: &str, start: usize, word: &str) -> bool {
+    let end = start + word.len();
+    source[start..].starts_with(word)
+        && !source[..start].ends_with(char::is_alphabetic)
+        && !source[end..].starts_with(char::is_alphabetic)
+}
+
+fn first_word(source: &str, from: usize, word: &str) -> Option<usize> {
+    source[from..]
+        .match_indices(word)
+        .map(|(index, _)| from + index)
+        .find(|&start| is_word_at(source, start, word))
+}
+
+fn last_word(source: &str, until: usize, word: &str) -> Option<usize> {
+    source[..until]
+        .rmatch_indices(word)
+        .map(|(start, _)| start)
+        .find(|&start| is_word_at(source, start, word))
+}
+
+fn resolve_source(source: &str) -> Option<Resolution> {
+    let (_, module) = parse_my_module(ParseInput::new(source)).ok()?;
+    Some(resolve(source, &module))
+}
+
+// where the variable at `offset` is bound
+pub fn definition(source: &str, offset: usize) -> Option<Range<usize>> {
+    let resolution = resolve_source(source)?;
+    let binding = resolution.binding_at(offset)?;
+    Some(resolution.bindings[binding].range.clone())
+}
+
+// every use of the variable at `offset`
+pub fn references(source: &str, offset: usize, include_declaration: bool) -> Vec<Range<usize>> {
+    let resolution = match resolve_source(source) {
+        Some(resolution) => resolution,
+        None => return vec![],
+    };
+    match resolution.binding_at(offset) {
+        Some(binding) => resolution
+            .occurrences(binding)
+            .into_iter()
+            .skip(if include_declaration { 0 } else { 1 })
+            .collect(),
+        None => vec![],
+    }
+}
+
+// the places that need to change to rename the variable at `offset`
+// we refuse if any variable would end up referring to something else, either
+// because the new name is captured by an inner binding, or because the
+// renamed binding would capture other variables, or if a definition would
+// end up with the same name as another one
+pub fn rename(source: &str, offset: usize, new_name: &str) -> Result<Vec<Range<usize>>, String> {
+    match parse_my_identifier(ParseInput::new(new_name)) {
+        Ok((rest, _)) if rest.fragment().is_empty() => {}
+        _ => return Err(format!("`{}` can't be used as a variable name", new_name)),
+    }
+
+    let resolution =
+        resolve_source(source).ok_or_else(|| "the document doesn't parse".to_string())?;
+    let binding = resolution
+        .binding_at(offset)
+        .ok_or_else(|| "there's no variable here".to_string())?;
+    let clashes = resolution.bindings[..resolution.defs]
+        .iter()
+        .enumerate()
+        .any(|(other, def)| other != binding && def.identifier == new_name);
+    if binding < resolution.defs && clashes {
+        return Err(format!(
+            "there's already a definition called `{}`",
+            new_name
+        ));
+    }
+    let mut occurrences = resolution.occurrences(binding);
+
+    // renaming doesn't change the shape of the module, so every variable
+    // should refer to the same binding afterwards
+    occurrences.sort_by_key(|range| range.start);
+    let mut renamed = source.to_string();
+    for range in occurrences.iter().rev() {
+        renamed.replace_range(range.clone(), new_name);
+    }
+    let binding_indexes = |resolution: &Resolution| -> Vec<Option<usize>> {
+        resolution.uses.iter().map(|var| var.binding).collect()
+    };
+    match resolve_source(&renamed) {
+        Some(after) if binding_indexes(&after) == binding_indexes(&resolution) => Ok(occurrences),
+        _ => Err(format!(
+            "renaming `{}` to `{}` would change what some variables refer to",
+            resolution.bindings[binding].identifier, new_name
+        )),
+    }
+}
+
+#[cfg(test)]
+fn words(source: &str, ranges: Vec<Range<usize>>) -> Vec<(usize, &str)> {
+    ranges
+        .into_iter()
+        .map(|range| (range.start, &source[range]))
+        .collect()
+}
+
+#[test]
+fn test_definition_and_references() {
+    let source = "def main = let a = 1 in let b = \\a -> a + a in let a = a in a";
+    // the `a` after the lambda is shadowed by it
+    let after_lambda = source.find("a + a").unwrap();
+    assert_eq!(
+        words(source, references(source, after_lambda, true)),
+        vec![(33, "a"), (38, "a"), (42, "a")]
+    );
+    // `let a = a` refers to the first `a` on the right
+    let inner_bound = source.rfind("a in").unwrap();
+    assert_eq!(definition(source, inner_bound), Some(15..16));
+    assert_eq!(
+        words(source, references(source, 15, false)),
+        vec![(inner_bound, "a")]
+    );
+    let last = source.len() - 1;
+    assert_eq!(definition(source, last), Some(51..52));
+
+    // definitions can refer to each other, and `let rec` to itself
+    let source = "def main = f 1\ndef f = let rec go = \\n -> go n in go";
+    assert_eq!(definition(source, 11), Some(19..20));
+    assert_eq!(
+        words(source, references(source, source.len() - 1, true)),
+        vec![(31, "go"), (42, "go"), (50, "go")]
+    );
+    // pattern variables
+    let source = "def main = case Just 1 of Just x -> x | Nothing -> 0";
+    assert_eq!(definition(source, 36), Some(31..32));
+}
+
+#[test]
+fn test_rename() {
+    let source = "def main = let a = 1 in let b = 2 in a + b";
+    assert_eq!(
+        words(source, rename(source, 15, "c").unwrap()),
+        vec![(15, "a"), (37, "a")]
+    );
+    // the inner `b` would capture the uses of `a`
+    assert!(rename(source, 15, "b").is_err());
+    // the renamed `b` would shadow the outer `a`
+    assert!(rename(source, 28, "a").is_err());
+    assert!(rename(source, 15, "in").is_err());
+    assert!(rename(source, 15, "Horse").is_err());
+
+    // definitions can't share a name, even if nothing refers to them
+    let source = "def a = 1\ndef b = 2\ndef main = 3";
+    assert!(rename(source, 4, "b").is_err());
+    assert!(rename(source, 4, "main").is_err());
+    assert_eq!(
+        words(source, rename(source, 4, "c").unwrap()),
+        vec![(4, "a")]
+    );
+    // but a local can have the name of a definition it doesn't use
+    let source = "def a = 1\ndef main = let x = 2 in x";
+    assert_eq!(
+        words(source, rename(source, 25, "a").unwrap()),
+        vec![(25, "x"), (34, "x")]
+    );
+}
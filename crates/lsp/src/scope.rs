@@ -0,0 +1,196 @@
+use rusty::parser::span::Span;
+use rusty::types::expr::{get_expr_annotation, Expr, Pattern};
+use rusty::types::module::Def;
+use rusty::types::ty::Type;
+
+// something that puts a variable in scope
+pub enum Binder<'a, Ann> {
+    // a top level `def`
+    Def(&'a Def<Ann>),
+    // `let` or `let rec`
+    Let {
+        identifier: &'a str,
+        bound_expr: &'a Expr<Ann>,
+    },
+    // the argument of `lambda`
+    Argument {
+        identifier: &'a str,
+        lambda: &'a Expr<Ann>,
+    },
+    // a `PVar` in a case arm
+    Pattern {
+        identifier: &'a str,
+        pattern: &'a Pattern<Ann>,
+    },
+}
+
+impl<'a, Ann> Binder<'a, Ann> {
+    pub fn identifier(&self) -> &'a str {
+        match self {
+            Binder::Def(def) => &def.identifier,
+            Binder::Let { identifier, .. }
+            | Binder::Argument { identifier, .. }
+            | Binder::Pattern { identifier, .. } => identifier,
+        }
+    }
+}
+
+impl<'a, Ann> Binder<'a, Type<Ann>>
+where
+    Ann: Clone + Copy,
+{
+    // the type of the thing the variable was bound to, before it was
+    // instantiated anywhere
+    pub fn bound_type(&self) -> Type<Ann> {
+        match self {
+            Binder::Def(def) => def.ann.clone(),
+            Binder::Let { bound_expr, .. } => get_expr_annotation((*bound_expr).clone()),
+            Binder::Argument { lambda, .. } => match get_expr_annotation((*lambda).clone()) {
+                Type::TFunction { argument, .. } => *argument,
+                other => other,
+            },
+            Binder::Pattern { pattern, .. } => match pattern {
+                Pattern::PVar { ann, .. } => ann.clone(),
+                _ => unreachable!("only variables are binders"),
+            },
+        }
+    }
+}
+
+// the expressions directly inside `expr`, along with the variables each of
+// them can see that `expr` can't
+pub fn children<Ann>(expr: &Expr<Ann>) -> Vec<(Vec<Binder<Ann>>, &Expr<Ann>)> {
+    match expr {
+        Expr::EPrim { .. } | Expr::EVar { .. } => vec![],
+        Expr::EIf {
+            pred_expr,
+            then_expr,
+            else_expr,
+            ..
+        } => vec![
+            (vec![], pred_expr),
+            (vec![], then_expr),
+            (vec![], else_expr),
+        ],
+        Expr::ELet {
+            identifier,
+            bound_expr,
+            rest_expr,
+            ..
+        } => vec![
+            (vec![], bound_expr),
+            (
+                vec![Binder::Let {
+                    identifier,
+                    bound_expr,
+                }],
+                rest_expr,
+            ),
+        ],
+        // the function can see itself
+        Expr::ELetRec {
+            identifier,
+            bound_expr,
+            rest_expr,
+            ..
+        } => vec![bound_expr, rest_expr]
+            .into_iter()
+            .map(|child| {
+                (
+                    vec![Binder::Let {
+                        identifier,
+                        bound_expr,
+                    }],
+                    child.as_ref(),
+                )
+            })
+            .collect(),
+        Expr::EBinOp {
+            left_expr,
+            right_expr,
+            ..
+        } => vec![(vec![], left_expr), (vec![], right_expr)],
+        Expr::ELambda {
+            identifier,
+            body_expr,
+            ..
+        } => vec![(
+            vec![Binder::Argument {
+                identifier,
+                lambda: expr,
+            }],
+            body_expr,
+        )],
+        Expr::EApply {
+            function_expr,
+            argument_expr,
+            ..
+        } => vec![(vec![], function_expr), (vec![], argument_expr)],
+        Expr::EConstructor { args, .. } => args.iter().map(|arg| (vec![], arg)).collect(),
+        Expr::ECase {
+            scrutinee_expr,
+            arms,
+            ..
+        } => {
+            let mut found = vec![(vec![], scrutinee_expr.as_ref())];
+            for arm in arms {
+                let mut binders = vec![];
+                pattern_binders(&arm.pattern, &mut binders);
+                found.push((binders, &arm.body_expr));
+            }
+            found
+        }
+        Expr::ETuple { items, .. } => items.iter().map(|item| (vec![], item)).collect(),
+        Expr::ERecord { fields, .. } => fields
+            .iter()
+            .map(|(_, field_expr)| (vec![], field_expr))
+            .collect(),
+        Expr::EFieldAccess { record_expr, .. } => vec![(vec![], record_expr)],
+        Expr::ERecordUpdate {
+            record_expr,
+            fields,
+            ..
+        } => std::iter::once((vec![], record_expr.as_ref()))
+            .chain(fields.iter().map(|(_, field_expr)| (vec![], field_expr)))
+            .collect(),
+        Expr::EAnnotation { expr, .. } => vec![(vec![], expr)],
+    }
+}
+
+// the innermost expression containing `offset`, adding every variable in
+// scope there to `scope`
+// `expr` has the same shape as `parsed`, but different annotations, ie the
+// result of typechecking it, so we walk both together
+pub fn innermost<'a, Ann>(
+    parsed: &'a Expr<Span>,
+    expr: &'a Expr<Ann>,
+    offset: usize,
+    scope: &mut Vec<Binder<'a, Ann>>,
+) -> (Span, &'a Expr<Ann>) {
+    for ((_, parsed_child), (binders, child)) in children(parsed).into_iter().zip(children(expr)) {
+        if contains(&get_expr_annotation(parsed_child.clone()), offset) {
+            scope.extend(binders);
+            return innermost(parsed_child, child, offset, scope);
+        }
+    }
+    (get_expr_annotation(parsed.clone()), expr)
+}
+
+pub fn contains(span: &Span, offset: usize) -> bool {
+    span.start.offset <= offset && offset < span.end.offset
+}
+
+fn pattern_binders<'a, Ann>(pattern: &'a Pattern<Ann>, binders: &mut Vec<Binder<'a, Ann>>) {
+    match pattern {
+        Pattern::PVar { identifier, .. } => binders.push(Binder::Pattern {
+            identifier,
+            pattern,
+        }),
+        Pattern::PWildcard { .. } | Pattern::PLiteral { .. } => {}
+        Pattern::PTuple { items: args, .. } | Pattern::PConstructor { args, .. } => {
+            for arg in args {
+                pattern_binders(arg, binders);
+            }
+        }
+    }
+}